@@ -0,0 +1,362 @@
+//! API keys: which routes a request may call, and with which voices.
+//!
+//! Every route needs one scope (see `required_scope`). Keys come from
+//! `[[auth.keys]]`; `auth.admin_token` is a key with every scope. Without
+//! any keys the server stays open, apart from the admin API when an admin
+//! token is set.
+
+use std::net::IpAddr;
+
+use axum::http::{header, HeaderMap, Method, Uri};
+use serde::{Deserialize, Serialize};
+
+use crate::api::signed_url::constant_time_eq;
+use crate::config::AuthConfig;
+use crate::error::AppError;
+use crate::tts::alias::DEFAULT_ALIAS;
+use crate::tts::TtsService;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Key name the admin token acts under; no `[[auth.keys]]` entry may use it
+pub const ADMIN_KEY_NAME: &str = "admin";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Scope {
+    /// Synthesize audio: `/api/speak`, jobs, batches, templates, `/v1`, `/process`
+    #[serde(rename = "synthesize")]
+    Synthesize,
+    /// Use the server's speakers: `/api/speak-aloud`, playback, schedules
+    #[serde(rename = "speak-aloud")]
+    SpeakAloud,
+    /// `/api/admin`, and changing prompts and templates
+    #[serde(rename = "admin")]
+    Admin,
+    /// List voices and aliases
+    #[serde(rename = "voices:read")]
+    VoicesRead,
+}
+
+impl Scope {
+    pub fn name(self) -> &'static str {
+        match self {
+            Scope::Synthesize => "synthesize",
+            Scope::SpeakAloud => "speak-aloud",
+            Scope::Admin => "admin",
+            Scope::VoicesRead => "voices:read",
+        }
+    }
+}
+
+/// The scope a route needs; `None` for the web UI and health checks
+pub fn required_scope(method: &Method, path: &str) -> Option<Scope> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["api", "health"] | ["api", "ready"] => None,
+        // Validation loads the voice into the engine cache and synthesizes
+        ["api", "voices", _, "status"] => Some(Scope::Synthesize),
+        ["api", "admin", ..] => Some(Scope::Admin),
+        ["api", "speak-aloud"] | ["api", "playback", ..] | ["api", "schedules", ..] => {
+            Some(Scope::SpeakAloud)
+        }
+        ["api", "voices", ..]
+        | ["api", "aliases"]
+        | ["api", "engines"]
+        | ["v1", "models"]
+        | ["voices"]
+        | ["locales"] => Some(Scope::VoicesRead),
+        ["api", "templates", _, "speak"] => Some(Scope::Synthesize),
+        ["api", "prompts" | "templates", ..] if method != Method::GET => Some(Scope::Admin),
+        ["api", ..] | ["v1", ..] | ["process"] => Some(Scope::Synthesize),
+        _ => None,
+    }
+}
+
+/// Who made a request, as far as voice restrictions go; added to every
+/// request's extensions
+#[derive(Debug, Clone, Default)]
+pub struct Caller {
+    /// Key name; `None` when no key was needed
+    pub key: Option<String>,
+    /// Voices (ids or aliases) the key may use; `None` for any
+    voices: Option<Vec<String>>,
+    /// Presented `auth.admin_token`
+    admin: bool,
+    /// Client address, for per-IP rate limits
+    pub ip: Option<IpAddr>,
+}
+
+impl Caller {
+    /// A client without a key, such as a Wyoming client, known by address
+    pub fn from_ip(ip: IpAddr) -> Self {
+        Self {
+            ip: Some(ip),
+            ..Self::default()
+        }
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.admin
+    }
+
+    /// Whether the caller may see something submitted under `owner`: its
+    /// own key's work, or anything with the admin token
+    pub fn owns(&self, owner: Option<&str>) -> bool {
+        self.admin || self.key.as_deref() == owner
+    }
+
+    /// Refuse voices outside the key's allowlist. `voice` may be an alias
+    /// or, when `None`, the default voice; either the name or the voice it
+    /// resolves to must be listed.
+    pub fn check_voice(&self, tts: &TtsService, voice: Option<&str>) -> Result<(), AppError> {
+        let Some(allowed) = &self.voices else {
+            return Ok(());
+        };
+        let requested = voice
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .unwrap_or(DEFAULT_ALIAS);
+        let listed = |name: &str| allowed.iter().any(|v| v == name);
+        if listed(requested)
+            || tts
+                .resolve_voice(Some(requested))
+                .is_ok_and(|resolved| listed(&resolved.voice_id))
+        {
+            return Ok(());
+        }
+        Err(AppError::Forbidden(format!(
+            "API key '{}' may not use voice '{}'",
+            self.key.as_deref().unwrap_or_default(),
+            requested
+        )))
+    }
+}
+
+/// Check a request's key against the scope its route needs
+pub fn authorize(
+    auth: &AuthConfig,
+    signed_urls: bool,
+    method: &Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+) -> Result<Caller, AppError> {
+    let path = uri.path();
+    let Some(scope) = required_scope(method, path) else {
+        return Ok(Caller::default());
+    };
+    // The signature is the credential; the handler checks it
+    if signed_urls
+        && method == Method::GET
+        && path == "/api/speak"
+        && uri
+            .query()
+            .is_some_and(|q| q.split('&').any(|p| p.starts_with("sig=")))
+    {
+        return Ok(Caller::default());
+    }
+    // Exact paths only, so listing `/api/voices` doesn't open the routes
+    // below it, such as voice validation
+    let public = auth
+        .public_routes
+        .iter()
+        .any(|route| path.trim_end_matches('/') == route.trim_end_matches('/'));
+
+    let Some(presented) = presented_key(headers) else {
+        if public || anonymous_allowed(auth, scope) {
+            return Ok(Caller::default());
+        }
+        return Err(AppError::Unauthorized(format!(
+            "An API key with the '{}' scope is required",
+            scope.name()
+        )));
+    };
+
+    if auth
+        .admin_token
+        .as_deref()
+        .is_some_and(|token| constant_time_eq(token.as_bytes(), presented.as_bytes()))
+    {
+        return Ok(Caller {
+            key: Some(ADMIN_KEY_NAME.to_string()),
+            admin: true,
+            ..Caller::default()
+        });
+    }
+    let key = auth
+        .keys
+        .iter()
+        .find(|key| constant_time_eq(key.key.as_bytes(), presented.as_bytes()))
+        .ok_or_else(|| AppError::Unauthorized("Invalid API key".into()))?;
+    if !public && !key.scopes.contains(&scope) {
+        return Err(AppError::Forbidden(format!(
+            "API key '{}' lacks the '{}' scope",
+            key.name,
+            scope.name()
+        )));
+    }
+    Ok(Caller {
+        key: Some(key.name.clone()),
+        voices: key.voices.clone(),
+        ..Caller::default()
+    })
+}
+
+/// `X-API-Key: <key>` or `Authorization: Bearer <key>`
+fn presented_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| {
+            headers
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+        })
+        .map(str::trim)
+}
+
+/// Without keys the server is open, except the admin API once an admin
+/// token is set
+fn anonymous_allowed(auth: &AuthConfig, scope: Scope) -> bool {
+    auth.keys.is_empty() && (scope != Scope::Admin || auth.admin_token.is_none())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ApiKey;
+
+    fn auth() -> AuthConfig {
+        AuthConfig {
+            admin_token: Some("admin-secret".into()),
+            keys: vec![ApiKey {
+                name: "lobby-panel".into(),
+                key: "lobby-panel-key".into(),
+                scopes: vec![Scope::SpeakAloud, Scope::VoicesRead],
+                voices: Some(vec!["announcer".into()]),
+                requests_per_minute: None,
+                daily_chars: None,
+            }],
+            public_routes: vec!["/api/voices".into()],
+            ..AuthConfig::default()
+        }
+    }
+
+    fn request(
+        auth: &AuthConfig,
+        method: Method,
+        uri: &str,
+        key: Option<&str>,
+    ) -> Result<Caller, AppError> {
+        let mut headers = HeaderMap::new();
+        if let Some(key) = key {
+            headers.insert(API_KEY_HEADER, key.parse().unwrap());
+        }
+        authorize(auth, true, &method, &uri.parse().unwrap(), &headers)
+    }
+
+    #[test]
+    fn maps_routes_to_scopes() {
+        let cases = [
+            (Method::POST, "/api/speak", Some(Scope::Synthesize)),
+            (Method::POST, "/v1/audio/speech", Some(Scope::Synthesize)),
+            (Method::POST, "/api/speak-aloud", Some(Scope::SpeakAloud)),
+            (
+                Method::POST,
+                "/api/schedules/noon/run",
+                Some(Scope::SpeakAloud),
+            ),
+            (
+                Method::GET,
+                "/api/voices/en_GB-alba-medium",
+                Some(Scope::VoicesRead),
+            ),
+            (Method::GET, "/locales", Some(Scope::VoicesRead)),
+            (
+                Method::GET,
+                "/api/voices/en_GB-alba-medium/status",
+                Some(Scope::Synthesize),
+            ),
+            (Method::DELETE, "/api/admin/cache", Some(Scope::Admin)),
+            (Method::POST, "/api/templates", Some(Scope::Admin)),
+            (Method::GET, "/api/templates", Some(Scope::Synthesize)),
+            (
+                Method::POST,
+                "/api/templates/departure/speak",
+                Some(Scope::Synthesize),
+            ),
+            (Method::GET, "/api/health", None),
+            (Method::GET, "/index.html", None),
+        ];
+        for (method, path, scope) in cases {
+            assert_eq!(required_scope(&method, path), scope, "{} {}", method, path);
+        }
+    }
+
+    #[test]
+    fn checks_keys_and_scopes() {
+        let auth = auth();
+        let caller = request(
+            &auth,
+            Method::POST,
+            "/api/speak-aloud",
+            Some("lobby-panel-key"),
+        )
+        .unwrap();
+        assert_eq!(caller.key.as_deref(), Some("lobby-panel"));
+        assert!(caller.owns(Some("lobby-panel")));
+        assert!(!caller.owns(Some("kiosk")) && !caller.owns(None));
+
+        assert!(matches!(
+            request(&auth, Method::POST, "/api/speak", Some("lobby-panel-key")),
+            Err(AppError::Forbidden(_))
+        ));
+        assert!(matches!(
+            request(&auth, Method::POST, "/api/speak", None),
+            Err(AppError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            request(&auth, Method::POST, "/api/speak", Some("wrong")),
+            Err(AppError::Unauthorized(_))
+        ));
+        let admin = request(
+            &auth,
+            Method::DELETE,
+            "/api/admin/cache",
+            Some("admin-secret"),
+        )
+        .unwrap();
+        assert!(admin.owns(Some("lobby-panel")));
+        assert!(request(&auth, Method::GET, "/api/health", None).is_ok());
+    }
+
+    #[test]
+    fn allows_public_routes_and_signed_urls() {
+        let auth = auth();
+        assert!(request(&auth, Method::GET, "/api/voices", None).is_ok());
+        assert!(request(&auth, Method::GET, "/api/voices/", None).is_ok());
+        assert!(matches!(
+            request(&auth, Method::GET, "/api/voices/x/status", None),
+            Err(AppError::Unauthorized(_))
+        ));
+        assert!(request(
+            &auth,
+            Method::GET,
+            "/api/speak?text=Hi&expires=1&sig=ab",
+            None
+        )
+        .is_ok());
+        assert!(request(&auth, Method::GET, "/api/speak?text=Hi", None).is_err());
+    }
+
+    #[test]
+    fn stays_open_without_keys() {
+        let mut auth = AuthConfig::default();
+        assert!(request(&auth, Method::POST, "/api/speak", None).is_ok());
+        assert!(request(&auth, Method::POST, "/api/admin/voices/install", None).is_ok());
+
+        auth.admin_token = Some("admin-secret".into());
+        assert!(request(&auth, Method::POST, "/api/speak", None).is_ok());
+        assert!(request(&auth, Method::POST, "/api/admin/voices/install", None).is_err());
+    }
+}
@@ -1,101 +1,940 @@
 use axum::{
-    extract::State,
-    http::{header, StatusCode},
-    response::{IntoResponse, Response},
+    extract::{Extension, FromRequest, Multipart, Path, Query, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        AppendHeaders, IntoResponse, Response,
+    },
     Json,
 };
-#[cfg(feature = "audio-playback")]
-use rodio::Source;
-#[cfg(feature = "audio-playback")]
-use std::io::Cursor;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
 
-use super::{HealthResponse, SpeakRequest, VoicesResponse};
+use super::{
+    AliasesResponse, BatchRequest, CatalogResponse, ClearResponse, DevicesResponse, FailedVoice,
+    HealthResponse, InstallRequest, InstallResponse, JobRequest, JobsResponse, PromptsRequest,
+    PromptsResponse, ReadyResponse, SchedulesRequest, SchedulesResponse, SignUrlRequest,
+    SignUrlResponse, SkipResponse, SpeakAloudRequest, SpeakRequest, TemplateSpeakRequest,
+    TemplatesRequest, TemplatesResponse, UsageResponse, VoicesQuery, VoicesResponse, VolumeRequest,
+};
+use crate::api::auth::Caller;
 use crate::api::routes::AppState;
+use crate::api::signed_url::{self, SpeakQuery};
+use crate::dsl;
 use crate::error::AppError;
+use crate::tts::playback::{PlaybackQueue, PlaybackStatus, QueuedItem};
+use crate::tts::{
+    batch, AudioCacheStats, AudioFormat, AudiobookOptions, Catalog, EngineCacheStats, Job,
+    JobQueue, PromptLibrary, PurgeReport, ScheduleStatus, Scheduler, TemplateInfo, TemplateLibrary,
+    VoiceDetail, VoiceEventKind, VoiceStatus,
+};
+
+/// Reject empty text and text over the configured length limit
+pub(crate) fn validate_text(state: &AppState, text: &str) -> Result<(), AppError> {
+    if text.is_empty() {
+        return Err(AppError::BadRequest("Text cannot be empty".into()));
+    }
+
+    let max = state.config.server.max_text_length;
+    if text.chars().count() > max {
+        return Err(AppError::BadRequest(format!(
+            "Text too long (max {} chars)",
+            max
+        )));
+    }
+
+    Ok(())
+}
 
 pub async fn speak(
     State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
     Json(request): Json<SpeakRequest>,
 ) -> Result<Response, AppError> {
-    // Validate input
-    if request.text.is_empty() {
-        return Err(AppError::BadRequest("Text cannot be empty".into()));
+    validate_text(&state, &request.text)?;
+    caller.check_voice(&state.tts, request.voice.as_deref())?;
+    let chars = request.text.chars().count();
+    state.limiter.admit(&caller, chars)?;
+    let result = speak_audio(
+        Arc::clone(&state),
+        &headers,
+        request.text,
+        request.voice,
+        AudioFormat::Wav,
+        None,
+    )
+    .await;
+    state.limiter.refund_on_error(&caller, chars, result)
+}
+
+/// `GET /api/speak`, for clients that can only fetch a URL; signed when
+/// `auth.url_signing_key` is set, unless an API key is sent
+pub async fn speak_url(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
+    Query(query): Query<SpeakQuery>,
+) -> Result<Response, AppError> {
+    if let Some(signer) = &state.url_signer {
+        signer.admit(&caller, &query)?;
     }
+    validate_text(&state, &query.text)?;
+    caller.check_voice(&state.tts, query.voice.as_deref())?;
+    let format = query.format()?;
+    let chars = query.text.chars().count();
+    state.limiter.admit(&caller, chars)?;
+
+    // The same URL always yields the same audio, so shared caches may keep
+    // it, but not past the signature's expiry. `[time]` and `[date]` change
+    // what is said, so that audio is never stored.
+    let cache_control = if dsl::clock::has_tags(&query.text) {
+        "no-store".to_string()
+    } else {
+        let max_age = signed_url::remaining_secs(&query)
+            .map_or(MAX_URL_CACHE_SECS, |secs| secs.min(MAX_URL_CACHE_SECS));
+        format!("public, max-age={}", max_age)
+    };
+
+    let result = speak_audio(
+        Arc::clone(&state),
+        &headers,
+        query.text,
+        query.voice,
+        format,
+        Some(cache_control),
+    )
+    .await;
+    state.limiter.refund_on_error(&caller, chars, result)
+}
 
-    if request.text.len() > 10000 {
+/// Longest `Cache-Control` max-age for `GET /api/speak`
+const MAX_URL_CACHE_SECS: u64 = 86400;
+
+pub async fn sign_speak_url(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SignUrlRequest>,
+) -> Result<Json<SignUrlResponse>, AppError> {
+    let signer = state
+        .url_signer
+        .as_ref()
+        .ok_or_else(|| AppError::BadRequest("No URL signing key configured".into()))?;
+    validate_text(&state, &request.text)?;
+    if request.ttl_secs == 0 {
         return Err(AppError::BadRequest(
-            "Text too long (max 10000 chars)".into(),
+            "ttl_secs must be greater than 0".into(),
         ));
     }
 
-    if request.voice.is_empty() {
-        return Err(AppError::BadRequest("Voice cannot be empty".into()));
+    let (url, expires) = signer.sign(
+        &request.text,
+        request.voice.as_deref(),
+        request.format,
+        request.ttl_secs,
+    )?;
+    Ok(Json(SignUrlResponse { url, expires }))
+}
+
+/// Synthesize (or fetch from the cache) and answer conditional requests
+async fn speak_audio(
+    state: Arc<AppState>,
+    headers: &HeaderMap,
+    text: String,
+    voice: Option<String>,
+    format: AudioFormat,
+    cache_control: Option<String>,
+) -> Result<Response, AppError> {
+    let mut extra = Vec::new();
+    if let Some(cache_control) = cache_control {
+        extra.push((header::CACHE_CONTROL, cache_control));
+    }
+
+    // Repeat requests for audio the client already has skip synthesis
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+        // Loads the voice config and hashes the model, so off the executor
+        let etag = {
+            let (state, text, voice) = (Arc::clone(&state), text.clone(), voice.clone());
+            tokio::task::spawn_blocking(move || {
+                state.tts.audio_etag(&text, voice.as_deref(), format)
+            })
+            .await
+            .map_err(|e| AppError::TtsError(format!("ETag task failed: {}", e)))??
+        };
+        if etag_matches(if_none_match, &etag) {
+            extra.push((header::ETAG, quote_etag(&etag)));
+            return Ok((StatusCode::NOT_MODIFIED, AppendHeaders(extra)).into_response());
+        }
     }
 
     // Generate audio
-    let wav = state.tts.speak(&request.text, &request.voice)?;
+    let cached = tokio::task::spawn_blocking(move || {
+        state.tts.speak_cached(&text, voice.as_deref(), format)
+    })
+    .await
+    .map_err(|e| AppError::TtsError(format!("Synthesis task failed: {}", e)))??;
 
     // Return audio response
-    Ok((StatusCode::OK, [(header::CONTENT_TYPE, "audio/wav")], wav).into_response())
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::ETAG, quote_etag(&cached.etag)),
+            (
+                HeaderName::from_static("x-cache"),
+                if cached.hit { "HIT" } else { "MISS" }.to_string(),
+            ),
+        ],
+        AppendHeaders(extra),
+        cached.audio,
+    )
+        .into_response())
+}
+
+fn quote_etag(etag: &str) -> String {
+    format!("\"{}\"", etag)
+}
+
+/// Whether an `If-None-Match` header lists `etag` (or is `*`)
+fn etag_matches(if_none_match: &HeaderValue, etag: &str) -> bool {
+    let Ok(value) = if_none_match.to_str() else {
+        return false;
+    };
+    value.split(',').map(str::trim).any(|candidate| {
+        candidate == "*"
+            || candidate
+                .trim_start_matches("W/")
+                .trim_matches('"')
+                .eq(etag)
+    })
 }
 
-#[cfg(feature = "audio-playback")]
+fn playback_queue(state: &AppState) -> Result<&PlaybackQueue, AppError> {
+    state
+        .playback
+        .as_deref()
+        .ok_or_else(|| AppError::BadRequest("Playback is not enabled".into()))
+}
+
+/// Synthesize and queue for playback on the server's speakers
 pub async fn speak_aloud(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<SpeakRequest>,
-) -> Result<Json<serde_json::Value>, AppError> {
-    // Validate input
-    if request.text.is_empty() {
-        return Err(AppError::BadRequest("Text cannot be empty".into()));
+    Extension(caller): Extension<Caller>,
+    Json(request): Json<SpeakAloudRequest>,
+) -> Result<(StatusCode, Json<QueuedItem>), AppError> {
+    validate_text(&state, &request.text)?;
+    caller.check_voice(&state.tts, request.voice.as_deref())?;
+    playback_queue(&state)?;
+    let chars = request.text.chars().count();
+    state.limiter.admit(&caller, chars)?;
+
+    let task_state = Arc::clone(&state);
+    let result = tokio::task::spawn_blocking(move || {
+        let audio = task_state
+            .tts
+            .synthesize(&request.text, request.voice.as_deref())?;
+        playback_queue(&task_state)?.enqueue(
+            &request.text,
+            request.voice.as_deref(),
+            audio,
+            &request.options,
+        )
+    })
+    .await
+    .map_err(|e| AppError::TtsError(format!("Synthesis task failed: {}", e)))
+    .and_then(|result| result);
+    let item = state.limiter.refund_on_error(&caller, chars, result)?;
+
+    Ok((StatusCode::ACCEPTED, Json(item)))
+}
+
+pub async fn playback_status(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<PlaybackStatus>, AppError> {
+    Ok(Json(playback_queue(&state)?.status()))
+}
+
+pub async fn playback_devices(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<DevicesResponse>, AppError> {
+    let backend = playback_queue(&state)?.backend();
+    let devices = tokio::task::spawn_blocking(move || backend.devices())
+        .await
+        .map_err(|e| AppError::TtsError(format!("Device listing failed: {}", e)))??;
+    Ok(Json(DevicesResponse { backend, devices }))
+}
+
+pub async fn set_playback_volume(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<VolumeRequest>,
+) -> Result<Json<PlaybackStatus>, AppError> {
+    let queue = playback_queue(&state)?;
+    queue.set_volume(request.volume)?;
+    Ok(Json(queue.status()))
+}
+
+pub async fn skip_playback(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<SkipResponse>, AppError> {
+    Ok(Json(SkipResponse {
+        skipped: playback_queue(&state)?.skip(),
+    }))
+}
+
+pub async fn clear_playback(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ClearResponse>, AppError> {
+    Ok(Json(ClearResponse {
+        removed: playback_queue(&state)?.clear(),
+    }))
+}
+
+pub async fn pause_playback(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<PlaybackStatus>, AppError> {
+    let queue = playback_queue(&state)?;
+    queue.pause();
+    Ok(Json(queue.status()))
+}
+
+pub async fn resume_playback(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<PlaybackStatus>, AppError> {
+    let queue = playback_queue(&state)?;
+    queue.resume();
+    Ok(Json(queue.status()))
+}
+
+pub async fn list_voices(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<VoicesQuery>,
+) -> Result<Json<VoicesResponse>, AppError> {
+    // Reads every voice config, so off the executor
+    let voices = tokio::task::spawn_blocking(move || state.tts.list_voices())
+        .await
+        .map_err(|e| AppError::TtsError(format!("Voice listing failed: {}", e)))??
+        .into_iter()
+        .filter(|v| {
+            query
+                .language
+                .as_deref()
+                .map(|l| v.matches_language(l))
+                .unwrap_or(true)
+        })
+        .filter(|v| {
+            query
+                .quality
+                .as_deref()
+                .map(|q| v.matches_quality(q))
+                .unwrap_or(true)
+        })
+        .collect();
+    Ok(Json(VoicesResponse { voices }))
+}
+
+/// Validate a voice end to end; 200 with `ok: false` if any check fails
+pub async fn voice_status(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Path(voice_id): Path<String>,
+) -> Result<Json<VoiceStatus>, AppError> {
+    caller.check_voice(&state.tts, Some(&voice_id))?;
+    let status = tokio::task::spawn_blocking(move || state.tts.validate_voice(&voice_id))
+        .await
+        .map_err(|e| AppError::TtsError(format!("Validation task failed: {}", e)))?;
+    Ok(Json(status))
+}
+
+pub async fn list_aliases(State(state): State<Arc<AppState>>) -> Json<AliasesResponse> {
+    Json(AliasesResponse {
+        aliases: state.tts.aliases().entries().clone(),
+    })
+}
+
+pub async fn voice_detail(
+    State(state): State<Arc<AppState>>,
+    Path(voice_id): Path<String>,
+) -> Result<Json<VoiceDetail>, AppError> {
+    let detail = tokio::task::spawn_blocking(move || state.tts.voice_detail(&voice_id))
+        .await
+        .map_err(|e| AppError::TtsError(format!("Voice lookup failed: {}", e)))??;
+    Ok(Json(detail))
+}
+
+/// Server-sent events for voices added, changed or removed on disk
+pub async fn voice_events(
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let stream = BroadcastStream::new(state.tts.subscribe_voice_events())
+        .filter_map(|event| event.ok())
+        .map(|event| Event::default().event("voice").json_data(event));
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+fn prompt_library(state: &AppState) -> Result<&PromptLibrary, AppError> {
+    state.prompts.as_ref().ok_or_else(|| {
+        AppError::BadRequest("No prompt library configured (set prompts.dir or PROMPTS_DIR)".into())
+    })
+}
+
+pub async fn list_prompts(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<PromptsResponse>, AppError> {
+    Ok(Json(PromptsResponse {
+        prompts: prompt_library(&state)?.list(),
+    }))
+}
+
+pub async fn register_prompts(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<PromptsRequest>,
+) -> Result<Json<PromptsResponse>, AppError> {
+    for prompt in request.prompts.values() {
+        validate_text(&state, &prompt.text)?;
     }
+    let prompts =
+        tokio::task::spawn_blocking(move || prompt_library(&state)?.register(request.prompts))
+            .await
+            .map_err(|e| AppError::TtsError(format!("Prompt registration failed: {}", e)))??;
 
-    if request.text.len() > 10000 {
-        return Err(AppError::BadRequest(
-            "Text too long (max 10000 chars)".into(),
-        ));
+    Ok(Json(PromptsResponse { prompts }))
+}
+
+/// `/api/prompts/{id}.wav` returns the audio, `/api/prompts/{id}` the status
+pub async fn get_prompt(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let Some(id) = id.strip_suffix(".wav").map(str::to_string) else {
+        let status = prompt_library(&state)?
+            .status(&id)
+            .ok_or_else(|| AppError::NotFound(format!("Prompt '{}' not found", id)))?;
+        return Ok(Json(status).into_response());
+    };
+
+    let (wav, etag) =
+        tokio::task::spawn_blocking(move || prompt_library(&state)?.audio(&id, &state.tts))
+            .await
+            .map_err(|e| AppError::TtsError(format!("Prompt render failed: {}", e)))??;
+
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+        if etag_matches(if_none_match, &etag) {
+            return Ok((
+                StatusCode::NOT_MODIFIED,
+                [(header::ETAG, quote_etag(&etag))],
+            )
+                .into_response());
+        }
+    }
+
+    Ok((
+        StatusCode::OK,
+        [
+            (
+                header::CONTENT_TYPE,
+                AudioFormat::Wav.content_type().to_string(),
+            ),
+            (header::ETAG, quote_etag(&etag)),
+        ],
+        wav,
+    )
+        .into_response())
+}
+
+pub async fn delete_prompt(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    if prompt_library(&state)?.remove(&id)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound(format!("Prompt '{}' not found", id)))
     }
+}
+
+fn scheduler(state: &AppState) -> Result<&Scheduler, AppError> {
+    state.schedules.as_ref().ok_or_else(|| {
+        AppError::BadRequest("No schedules configured (set schedules.dir or SCHEDULES_DIR)".into())
+    })
+}
+
+pub async fn list_schedules(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<SchedulesResponse>, AppError> {
+    Ok(Json(SchedulesResponse {
+        schedules: scheduler(&state)?.list(),
+    }))
+}
 
-    if request.voice.is_empty() {
-        return Err(AppError::BadRequest("Voice cannot be empty".into()));
+pub async fn put_schedules(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Json(request): Json<SchedulesRequest>,
+) -> Result<Json<SchedulesResponse>, AppError> {
+    for schedule in request.schedules.values() {
+        validate_text(&state, &schedule.text)?;
+        caller.check_voice(&state.tts, schedule.voice.as_deref())?;
     }
+    let schedules = tokio::task::spawn_blocking(move || scheduler(&state)?.put(request.schedules))
+        .await
+        .map_err(|e| AppError::TtsError(format!("Saving schedules failed: {}", e)))??;
 
-    // Generate audio
-    let wav = state.tts.speak(&request.text, &request.voice)?;
+    Ok(Json(SchedulesResponse { schedules }))
+}
 
-    // Play audio in a background task
-    tokio::task::spawn_blocking(move || {
-        if let Err(e) = play_audio(wav) {
-            tracing::error!("Failed to play audio: {}", e);
+pub async fn get_schedule(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<ScheduleStatus>, AppError> {
+    scheduler(&state)?
+        .status(&id)
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("Schedule '{}' not found", id)))
+}
+
+pub async fn delete_schedule(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    if scheduler(&state)?.remove(&id)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound(format!("Schedule '{}' not found", id)))
+    }
+}
+
+/// Speak a schedule's announcement now, whatever its rule says
+pub async fn run_schedule(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<QueuedItem>), AppError> {
+    let schedule = scheduler(&state)?
+        .status(&id)
+        .ok_or_else(|| AppError::NotFound(format!("Schedule '{}' not found", id)))?
+        .schedule;
+    playback_queue(&state)?;
+    caller.check_voice(&state.tts, schedule.voice.as_deref())?;
+    let chars = schedule.text.chars().count();
+    state.limiter.admit(&caller, chars)?;
+
+    let task_state = Arc::clone(&state);
+    let result = tokio::task::spawn_blocking(move || {
+        scheduler(&task_state)?.fire(&id, &task_state.tts, playback_queue(&task_state)?)
+    })
+    .await
+    .map_err(|e| AppError::TtsError(format!("Schedule run failed: {}", e)))
+    .and_then(|result| result);
+    let item = state.limiter.refund_on_error(&caller, chars, result)?;
+
+    Ok((StatusCode::ACCEPTED, Json(item)))
+}
+
+fn template_library(state: &AppState) -> Result<&TemplateLibrary, AppError> {
+    state.templates.as_ref().ok_or_else(|| {
+        AppError::BadRequest("No templates configured (set templates.dir or TEMPLATES_DIR)".into())
+    })
+}
+
+pub async fn list_templates(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<TemplatesResponse>, AppError> {
+    Ok(Json(TemplatesResponse {
+        templates: template_library(&state)?.list(),
+    }))
+}
+
+pub async fn put_templates(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<TemplatesRequest>,
+) -> Result<Json<TemplatesResponse>, AppError> {
+    for template in request.templates.values() {
+        validate_text(&state, &template.text)?;
+    }
+    let templates =
+        tokio::task::spawn_blocking(move || template_library(&state)?.put(request.templates))
+            .await
+            .map_err(|e| AppError::TtsError(format!("Saving templates failed: {}", e)))??;
+
+    Ok(Json(TemplatesResponse { templates }))
+}
+
+pub async fn get_template(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<TemplateInfo>, AppError> {
+    template_library(&state)?
+        .info(&name)
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("Template '{}' not found", name)))
+}
+
+pub async fn delete_template(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, AppError> {
+    if template_library(&state)?.remove(&name)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound(format!("Template '{}' not found", name)))
+    }
+}
+
+/// Fill in a template's variables and speak the result
+pub async fn speak_template(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
+    Json(request): Json<TemplateSpeakRequest>,
+) -> Result<Response, AppError> {
+    let template = template_library(&state)?
+        .get(&name)
+        .ok_or_else(|| AppError::NotFound(format!("Template '{}' not found", name)))?;
+    let voice = request.voice.or_else(|| template.voice.clone());
+    caller.check_voice(&state.tts, voice.as_deref())?;
+    let format = request
+        .format
+        .as_deref()
+        .map_or(Ok(AudioFormat::Wav), str::parse)?;
+
+    // Time and date hints are phrased for the voice's language
+    let render_state = Arc::clone(&state);
+    let render_voice = voice.clone();
+    let text = tokio::task::spawn_blocking(move || {
+        let language = render_state.tts.voice_language(render_voice.as_deref())?;
+        template.render(&request.variables, &language)
+    })
+    .await
+    .map_err(|e| AppError::TtsError(format!("Template rendering failed: {}", e)))??;
+
+    validate_text(&state, &text)?;
+    let chars = text.chars().count();
+    state.limiter.admit(&caller, chars)?;
+    let result = speak_audio(Arc::clone(&state), &headers, text, voice, format, None).await;
+    state.limiter.refund_on_error(&caller, chars, result)
+}
+
+fn job_queue(state: &AppState) -> Result<&JobQueue, AppError> {
+    state.jobs.as_ref().ok_or_else(|| {
+        AppError::BadRequest("No job queue configured (set jobs.dir or JOBS_DIR)".into())
+    })
+}
+
+/// Submit a job as JSON (`{"text", "voice", "format"}`) or as a multipart
+/// form with a `file` (or `text`) field plus optional `voice`/`format`
+pub async fn submit_job(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    request: Request,
+) -> Result<Response, AppError> {
+    job_queue(&state)?;
+
+    let is_multipart = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("multipart/form-data"));
+
+    let job_request = if is_multipart {
+        read_job_form(
+            Multipart::from_request(request, &())
+                .await
+                .map_err(|e| AppError::BadRequest(e.body_text()))?,
+        )
+        .await?
+    } else {
+        let Json(job_request) = Json::<JobRequest>::from_request(request, &())
+            .await
+            .map_err(|e| AppError::BadRequest(e.body_text()))?;
+        job_request
+    };
+
+    let max = state.config.jobs.max_text_length;
+    if job_request.text.chars().count() > max {
+        return Err(AppError::BadRequest(format!(
+            "Text too long (max {} chars)",
+            max
+        )));
+    }
+    caller.check_voice(&state.tts, job_request.voice.as_deref())?;
+    if let Some(heading_voice) = job_request
+        .audiobook
+        .as_ref()
+        .and_then(|options| options.heading_voice.as_deref())
+    {
+        caller.check_voice(&state.tts, Some(heading_voice))?;
+    }
+    let chars = job_request.text.chars().count();
+    state.limiter.admit(&caller, chars)?;
+
+    let (task_state, owner) = (Arc::clone(&state), caller.key.clone());
+    let result = tokio::task::spawn_blocking(move || {
+        job_queue(&task_state)?.submit(
+            &job_request.text,
+            job_request.voice,
+            job_request.format,
+            job_request.audiobook,
+            owner,
+        )
+    })
+    .await
+    .map_err(|e| AppError::TtsError(format!("Job submission failed: {}", e)))
+    .and_then(|result| result);
+    let job = state.limiter.refund_on_error(&caller, chars, result)?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, format!("/api/jobs/{}", job.id))],
+        Json(job),
+    )
+        .into_response())
+}
+
+async fn read_job_form(mut form: Multipart) -> Result<JobRequest, AppError> {
+    let mut text = None;
+    let mut voice = None;
+    let mut format = AudioFormat::default();
+    let mut audiobook = None;
+
+    while let Some(field) = form
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(e.body_text()))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+        let value = field
+            .text()
+            .await
+            .map_err(|e| AppError::BadRequest(e.body_text()))?;
+        match name.as_str() {
+            "file" | "text" => text = Some(value),
+            "voice" if !value.trim().is_empty() => voice = Some(value),
+            "format" => format = value.parse()?,
+            // `true` for the defaults, or a JSON object of options
+            "audiobook" => match value.trim() {
+                "" | "false" => audiobook = None,
+                "true" => audiobook = Some(AudiobookOptions::default()),
+                json => {
+                    audiobook = Some(serde_json::from_str(json).map_err(|e| {
+                        AppError::BadRequest(format!("Invalid 'audiobook' field: {}", e))
+                    })?)
+                }
+            },
+            _ => {}
         }
-    });
+    }
 
-    Ok(Json(serde_json::json!({
-        "status": "playing",
-        "text": request.text
-    })))
+    Ok(JobRequest {
+        text: text.ok_or_else(|| AppError::BadRequest("Missing 'file' or 'text' field".into()))?,
+        voice,
+        format,
+        audiobook,
+    })
 }
 
-pub async fn list_voices(
+pub async fn batch(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<VoicesResponse>, AppError> {
-    let voices = state.tts.list_voices()?;
-    Ok(Json(VoicesResponse { voices }))
+    Extension(caller): Extension<Caller>,
+    Json(request): Json<BatchRequest>,
+) -> Result<Response, AppError> {
+    batch::validate(&request.items, state.config.batch.max_items)?;
+    for item in &request.items {
+        caller.check_voice(&state.tts, item.voice.as_deref())?;
+    }
+    let chars = request
+        .items
+        .iter()
+        .map(|item| item.text.chars().count())
+        .sum();
+    state.limiter.admit(&caller, chars)?;
+
+    let task_state = Arc::clone(&state);
+    let result = tokio::task::spawn_blocking(move || {
+        batch::run(
+            &task_state.tts,
+            &request.items,
+            request.format,
+            task_state.config.batch.workers,
+            task_state.config.server.max_text_length,
+        )
+    })
+    .await
+    .map_err(|e| AppError::TtsError(format!("Batch synthesis failed: {}", e)))
+    .and_then(|result| result);
+    let archive = state.limiter.refund_on_error(&caller, chars, result)?;
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/zip"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"batch.zip\"",
+            ),
+        ],
+        archive,
+    )
+        .into_response())
 }
 
-#[cfg(feature = "audio-playback")]
-fn play_audio(wav_data: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
-    let (_stream, stream_handle) = rodio::OutputStream::try_default()?;
-    let cursor = Cursor::new(wav_data.clone());
-    let source = rodio::Decoder::new(cursor)?;
-    stream_handle.play_raw(source.convert_samples())?;
+pub async fn list_jobs(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<JobsResponse>, AppError> {
+    let mut jobs = job_queue(&state)?.list();
+    jobs.retain(|job| caller.owns(job.owner.as_deref()));
+    Ok(Json(JobsResponse { jobs }))
+}
 
-    // Sleep to allow audio to finish playing
-    std::thread::sleep(std::time::Duration::from_secs(
-        (wav_data.len() / 44100 / 2) as u64 + 1
-    ));
+/// A job the caller may see; other keys' jobs are reported as missing
+fn owned_job(state: &AppState, caller: &Caller, id: &str) -> Result<Job, AppError> {
+    job_queue(state)?
+        .get(id)
+        .filter(|job| caller.owns(job.owner.as_deref()))
+        .ok_or_else(|| AppError::NotFound(format!("Job '{}' not found", id)))
+}
 
-    Ok(())
+pub async fn job_status(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, AppError> {
+    owned_job(&state, &caller, &id).map(Json)
+}
+
+pub async fn job_result(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    owned_job(&state, &caller, &id)?;
+    let filename = id.clone();
+    let (audio, format) = tokio::task::spawn_blocking(move || job_queue(&state)?.result(&id))
+        .await
+        .map_err(|e| AppError::TtsError(format!("Reading job result failed: {}", e)))??;
+
+    Ok(attachment(audio, format, &filename))
+}
+
+pub async fn job_chapter(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Path((id, index)): Path<(String, usize)>,
+) -> Result<Response, AppError> {
+    owned_job(&state, &caller, &id)?;
+    let filename = format!("{}-chapter-{:03}", id, index);
+    let (audio, format) =
+        tokio::task::spawn_blocking(move || job_queue(&state)?.chapter(&id, index))
+            .await
+            .map_err(|e| AppError::TtsError(format!("Reading job chapter failed: {}", e)))??;
+
+    Ok(attachment(audio, format, &filename))
+}
+
+fn attachment(audio: Vec<u8>, format: AudioFormat, filename: &str) -> Response {
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}.{}\"",
+                    filename,
+                    format.extension()
+                ),
+            ),
+        ],
+        audio,
+    )
+        .into_response()
+}
+
+pub async fn cancel_job(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, AppError> {
+    owned_job(&state, &caller, &id)?;
+    Ok(Json(job_queue(&state)?.cancel(&id)?))
+}
+
+fn load_catalog(state: &AppState) -> Result<Catalog, AppError> {
+    let path = state.config.engine.catalog.as_ref().ok_or_else(|| {
+        AppError::BadRequest(
+            "No voice catalog configured (set engine.catalog or VOICE_CATALOG)".into(),
+        )
+    })?;
+    Catalog::load(path)
+}
+
+pub async fn voice_catalog(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<CatalogResponse>, AppError> {
+    let catalog = load_catalog(&state)?;
+    Ok(Json(CatalogResponse {
+        voices: catalog.entries(state.tts.voices_dir()),
+    }))
+}
+
+pub async fn install_voices(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<InstallRequest>,
+) -> Result<Json<InstallResponse>, AppError> {
+    if request.voices.is_empty() {
+        return Err(AppError::BadRequest("No voices to install".into()));
+    }
+
+    let installed = tokio::task::spawn_blocking(move || {
+        let catalog = load_catalog(&state)?;
+        let mut installed = Vec::new();
+        for voice in &request.voices {
+            let existed = state
+                .tts
+                .voices_dir()
+                .join(format!("{}.onnx", voice))
+                .exists();
+            installed.push(catalog.install(voice, state.tts.voices_dir())?);
+            // Don't wait for the watcher (which may be disabled) to reload
+            let kind = if existed {
+                VoiceEventKind::Changed
+            } else {
+                VoiceEventKind::Added
+            };
+            state.tts.apply_voice_change(voice, kind);
+        }
+        Ok::<_, AppError>(installed)
+    })
+    .await
+    .map_err(|e| AppError::TtsError(format!("Install task failed: {}", e)))??;
+
+    Ok(Json(InstallResponse { installed }))
+}
+
+pub async fn audio_cache_stats(State(state): State<Arc<AppState>>) -> Json<AudioCacheStats> {
+    Json(state.tts.audio_cache_stats())
+}
+
+pub async fn purge_audio_cache(State(state): State<Arc<AppState>>) -> Json<PurgeReport> {
+    Json(state.tts.purge_audio_cache())
+}
+
+pub async fn engine_stats(State(state): State<Arc<AppState>>) -> Json<EngineCacheStats> {
+    Json(state.tts.engine_stats())
+}
+
+/// Today's requests and characters for the caller's key, or every key for
+/// the admin token
+pub async fn usage(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+) -> Json<UsageResponse> {
+    Json(UsageResponse {
+        keys: state.limiter.usage(&caller),
+    })
 }
 
 pub async fn health() -> Json<HealthResponse> {
@@ -104,3 +943,41 @@ pub async fn health() -> Json<HealthResponse> {
         version: env!("CARGO_PKG_VERSION").to_string(),
     })
 }
+
+/// Readiness probe: 503 until startup preloading has finished
+pub async fn ready(State(state): State<Arc<AppState>>) -> Response {
+    let ready = state.ready.load(Ordering::Acquire);
+    let loaded_voices = state
+        .tts
+        .engine_stats()
+        .loaded
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+    let failed_voices = state
+        .preload_errors
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(id, error)| FailedVoice {
+            id: id.clone(),
+            error: error.clone(),
+        })
+        .collect();
+
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(ReadyResponse {
+            ready,
+            loaded_voices,
+            failed_voices,
+        }),
+    )
+        .into_response()
+}
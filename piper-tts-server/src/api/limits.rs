@@ -0,0 +1,558 @@
+//! Rate limits and daily quotas, checked before synthesis starts.
+//!
+//! Requests with an API key draw from a token bucket per key, others from
+//! one per client IP; each holds `limits.burst` tokens and refills at the
+//! per-minute rate. Keys with `daily_chars` also have a character quota
+//! that resets at local midnight; requests that fail give their
+//! characters back. Counts live in memory, so a restart resets them.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use axum::extract::ConnectInfo;
+use axum::http::{HeaderMap, Request};
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use serde::Serialize;
+
+use crate::api::auth::Caller;
+use crate::config::{ApiKey, Config, LimitsConfig};
+use crate::error::AppError;
+
+/// Buckets kept at most; refilled ones go first, then the longest idle
+const MAX_BUCKETS: usize = 4096;
+
+/// The address a request came from: the peer, or the last
+/// `X-Forwarded-For` entry when `limits.trust_forwarded_for` is set.
+/// Proxies append the address they saw, so earlier entries are whatever
+/// the client sent.
+pub fn client_ip<B>(limits: &LimitsConfig, request: &Request<B>) -> Option<IpAddr> {
+    if limits.trust_forwarded_for {
+        if let Some(ip) = forwarded_for(request.headers()) {
+            return Some(ip);
+        }
+    }
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+}
+
+fn forwarded_for(headers: &HeaderMap) -> Option<IpAddr> {
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .next_back()?
+        .to_str()
+        .ok()?
+        .rsplit(',')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyUsage {
+    pub name: String,
+    /// Synthesis requests accepted today
+    pub requests: u64,
+    /// Characters synthesized today
+    pub chars: u64,
+    pub daily_chars: Option<u64>,
+    pub remaining_chars: Option<u64>,
+    /// `None` when unlimited
+    pub requests_per_minute: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Client {
+    Key(String),
+    Ip(IpAddr),
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// Requests per minute it refills at
+    rate: u32,
+}
+
+impl Bucket {
+    /// Tokens held at `now`, up to `burst`
+    fn tokens_at(&self, now: Instant, burst: f64) -> f64 {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        (self.tokens + elapsed * self.rate as f64 / 60.0).min(burst)
+    }
+}
+
+struct DailyUsage {
+    day: NaiveDate,
+    requests: u64,
+    chars: u64,
+}
+
+#[derive(Default)]
+struct LimiterState {
+    buckets: HashMap<Client, Bucket>,
+    usage: HashMap<String, DailyUsage>,
+}
+
+pub struct RateLimiter {
+    limits: LimitsConfig,
+    keys: Vec<ApiKey>,
+    state: Mutex<LimiterState>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            limits: config.limits.clone(),
+            keys: config.auth.keys.clone(),
+            state: Mutex::new(LimiterState::default()),
+        }
+    }
+
+    /// Count a request to synthesize `chars` characters, or refuse it with
+    /// `RateLimited`. The admin token is never limited.
+    pub fn admit(&self, caller: &Caller, chars: usize) -> Result<(), AppError> {
+        self.admit_at(caller, chars as u64, Instant::now(), &Local::now())
+    }
+
+    fn admit_at<Tz: TimeZone>(
+        &self,
+        caller: &Caller,
+        chars: u64,
+        now: Instant,
+        local: &DateTime<Tz>,
+    ) -> Result<(), AppError> {
+        if caller.is_admin() {
+            return Ok(());
+        }
+        let key = caller
+            .key
+            .as_deref()
+            .and_then(|name| self.keys.iter().find(|key| key.name == name));
+        let (client, rate) = match (key, caller.ip) {
+            (Some(key), _) => (Client::Key(key.name.clone()), self.key_rate(key)),
+            (None, Some(ip)) => (Client::Ip(ip), self.limits.ip_requests_per_minute),
+            (None, None) => return Ok(()),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let today = local.date_naive();
+        if let Some(key) = key {
+            let used = state
+                .usage
+                .get(&key.name)
+                .filter(|usage| usage.day == today)
+                .map_or(0, |usage| usage.chars);
+            if let Some(quota) = key.daily_chars {
+                if used + chars > quota {
+                    return Err(AppError::RateLimited {
+                        message: format!(
+                            "API key '{}' has used {} of its {} characters for today",
+                            key.name, used, quota
+                        ),
+                        retry_after_secs: secs_until_midnight(local),
+                    });
+                }
+            }
+        }
+
+        if rate > 0 {
+            let burst = self.limits.burst.max(1) as f64;
+            if !state.buckets.contains_key(&client) && state.buckets.len() >= MAX_BUCKETS {
+                prune(&mut state.buckets, now, burst);
+            }
+            let bucket = state.buckets.entry(client).or_insert(Bucket {
+                tokens: burst,
+                updated: now,
+                rate,
+            });
+            bucket.rate = rate;
+            bucket.tokens = bucket.tokens_at(now, burst);
+            bucket.updated = now;
+            if bucket.tokens < 1.0 {
+                let wait = (1.0 - bucket.tokens) * 60.0 / rate as f64;
+                return Err(AppError::RateLimited {
+                    message: format!("Rate limit of {} requests per minute exceeded", rate),
+                    retry_after_secs: (wait.ceil() as u64).max(1),
+                });
+            }
+            bucket.tokens -= 1.0;
+        }
+
+        if let Some(key) = key {
+            let usage = state.usage.entry(key.name.clone()).or_insert(DailyUsage {
+                day: today,
+                requests: 0,
+                chars: 0,
+            });
+            if usage.day != today {
+                *usage = DailyUsage {
+                    day: today,
+                    requests: 0,
+                    chars: 0,
+                };
+            }
+            usage.requests += 1;
+            usage.chars += chars;
+        }
+        Ok(())
+    }
+
+    /// Pass `result` through, first giving back the characters an admitted
+    /// request was charged if it failed. Its rate-limit token stays spent.
+    pub fn refund_on_error<T>(
+        &self,
+        caller: &Caller,
+        chars: usize,
+        result: Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        if result.is_err() {
+            self.refund_on(caller, chars as u64, Local::now().date_naive());
+        }
+        result
+    }
+
+    fn refund_on(&self, caller: &Caller, chars: u64, today: NaiveDate) {
+        let Some(name) = caller.key.as_deref() else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        if let Some(usage) = state.usage.get_mut(name).filter(|usage| usage.day == today) {
+            usage.requests = usage.requests.saturating_sub(1);
+            usage.chars = usage.chars.saturating_sub(chars);
+        }
+    }
+
+    /// Today's usage for every key the caller may see: its own, or all of
+    /// them for the admin token
+    pub fn usage(&self, caller: &Caller) -> Vec<KeyUsage> {
+        self.usage_on(caller, Local::now().date_naive())
+    }
+
+    fn usage_on(&self, caller: &Caller, today: NaiveDate) -> Vec<KeyUsage> {
+        let state = self.state.lock().unwrap();
+        self.keys
+            .iter()
+            .filter(|key| caller.owns(Some(&key.name)))
+            .map(|key| {
+                let (requests, chars) = state
+                    .usage
+                    .get(&key.name)
+                    .filter(|usage| usage.day == today)
+                    .map_or((0, 0), |usage| (usage.requests, usage.chars));
+                let rate = self.key_rate(key);
+                KeyUsage {
+                    name: key.name.clone(),
+                    requests,
+                    chars,
+                    daily_chars: key.daily_chars,
+                    remaining_chars: key.daily_chars.map(|quota| quota.saturating_sub(chars)),
+                    requests_per_minute: (rate > 0).then_some(rate),
+                }
+            })
+            .collect()
+    }
+
+    fn key_rate(&self, key: &ApiKey) -> u32 {
+        key.requests_per_minute
+            .unwrap_or(self.limits.key_requests_per_minute)
+    }
+}
+
+/// Make room for a new bucket: drop the ones that have refilled, which
+/// would start over full anyway, or failing that the longest idle one
+fn prune(buckets: &mut HashMap<Client, Bucket>, now: Instant, burst: f64) {
+    buckets.retain(|_, bucket| bucket.tokens_at(now, burst) < burst);
+    if buckets.len() >= MAX_BUCKETS {
+        let oldest = buckets
+            .iter()
+            .min_by_key(|(_, bucket)| bucket.updated)
+            .map(|(client, _)| client.clone());
+        if let Some(oldest) = oldest {
+            buckets.remove(&oldest);
+        }
+    }
+}
+
+/// Seconds until the next local midnight. A DST change on the way makes
+/// the day an hour shorter or longer; where it skips midnight, the day
+/// starts at the first hour that exists.
+fn secs_until_midnight<Tz: TimeZone>(now: &DateTime<Tz>) -> u64 {
+    let Some(tomorrow) = now.date_naive().succ_opt() else {
+        return 1;
+    };
+    (0..3)
+        .find_map(|hour| {
+            let start = tomorrow.and_hms_opt(hour, 0, 0)?;
+            now.timezone().from_local_datetime(&start).earliest()
+        })
+        .map_or(1, |midnight| {
+            midnight.signed_duration_since(now).num_seconds().max(1) as u64
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{authorize, Scope};
+    use axum::http::Method;
+    use chrono::{FixedOffset, MappedLocalTime, NaiveDateTime, Utc};
+    use std::time::Duration;
+
+    fn limiter(limits: LimitsConfig) -> RateLimiter {
+        let mut config = Config {
+            limits,
+            ..Config::default()
+        };
+        config.auth.admin_token = Some("admin-secret".into());
+        config.auth.keys = vec![
+            ApiKey {
+                name: "kiosk".into(),
+                key: "kiosk-key".into(),
+                scopes: vec![Scope::Synthesize],
+                voices: None,
+                requests_per_minute: None,
+                daily_chars: Some(100),
+            },
+            ApiKey {
+                name: "batch".into(),
+                key: "batch-key".into(),
+                scopes: vec![Scope::Synthesize],
+                voices: None,
+                requests_per_minute: Some(0),
+                daily_chars: None,
+            },
+        ];
+        RateLimiter::new(&config)
+    }
+
+    fn caller(limiter: &RateLimiter, key: Option<&str>) -> Caller {
+        let mut config = Config::default();
+        config.auth.admin_token = Some("admin-secret".into());
+        config.auth.keys = limiter.keys.clone();
+        let mut headers = HeaderMap::new();
+        if let Some(key) = key {
+            headers.insert("x-api-key", key.parse().unwrap());
+        }
+        let mut caller = authorize(
+            &config.auth,
+            false,
+            &Method::POST,
+            &"/api/speak".parse().unwrap(),
+            &headers,
+        )
+        .unwrap_or_default();
+        caller.ip = Some("192.0.2.7".parse().unwrap());
+        caller
+    }
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.from_utc_datetime(
+            &NaiveDate::from_ymd_opt(2026, 10, 18)
+                .unwrap()
+                .and_hms_opt(hour, minute, 0)
+                .unwrap(),
+        )
+    }
+
+    fn retry_after(result: Result<(), AppError>) -> u64 {
+        result.unwrap_err().retry_after().unwrap()
+    }
+
+    #[test]
+    fn refills_buckets() {
+        let limiter = limiter(LimitsConfig {
+            key_requests_per_minute: 6,
+            burst: 2,
+            ..LimitsConfig::default()
+        });
+        let kiosk = caller(&limiter, Some("kiosk-key"));
+        let start = Instant::now();
+        assert!(limiter.admit_at(&kiosk, 1, start, &at(9, 0)).is_ok());
+        assert!(limiter.admit_at(&kiosk, 1, start, &at(9, 0)).is_ok());
+        assert_eq!(
+            retry_after(limiter.admit_at(&kiosk, 1, start, &at(9, 0))),
+            10
+        );
+
+        let later = start + Duration::from_secs(4);
+        assert_eq!(
+            retry_after(limiter.admit_at(&kiosk, 1, later, &at(9, 0))),
+            6
+        );
+        let later = start + Duration::from_secs(11);
+        assert!(limiter.admit_at(&kiosk, 1, later, &at(9, 0)).is_ok());
+
+        // Other keys, the admin token and unlimited keys are unaffected
+        let batch = caller(&limiter, Some("batch-key"));
+        let admin = caller(&limiter, Some("admin-secret"));
+        for _ in 0..10 {
+            assert!(limiter.admit_at(&batch, 1, start, &at(9, 0)).is_ok());
+            assert!(limiter.admit_at(&admin, 1, start, &at(9, 0)).is_ok());
+        }
+    }
+
+    #[test]
+    fn limits_clients_without_keys_by_ip() {
+        let limiter = limiter(LimitsConfig {
+            ip_requests_per_minute: 60,
+            burst: 1,
+            ..LimitsConfig::default()
+        });
+        let mut anonymous = caller(&limiter, None);
+        let start = Instant::now();
+        assert!(limiter.admit_at(&anonymous, 1, start, &at(9, 0)).is_ok());
+        assert_eq!(
+            retry_after(limiter.admit_at(&anonymous, 1, start, &at(9, 0))),
+            1
+        );
+
+        anonymous.ip = Some("192.0.2.8".parse().unwrap());
+        assert!(limiter.admit_at(&anonymous, 1, start, &at(9, 0)).is_ok());
+        anonymous.ip = None;
+        assert!(limiter.admit_at(&anonymous, 1, start, &at(9, 0)).is_ok());
+    }
+
+    #[test]
+    fn trusts_only_the_address_the_proxy_added() {
+        let mut headers = HeaderMap::new();
+        headers.append("x-forwarded-for", "198.51.100.1".parse().unwrap());
+        headers.append(
+            "x-forwarded-for",
+            "203.0.113.9, 192.0.2.44".parse().unwrap(),
+        );
+        assert_eq!(forwarded_for(&headers), "192.0.2.44".parse().ok());
+    }
+
+    #[test]
+    fn caps_the_number_of_buckets() {
+        let limiter = limiter(LimitsConfig {
+            ip_requests_per_minute: 1,
+            burst: 1,
+            ..LimitsConfig::default()
+        });
+        let mut anonymous = caller(&limiter, None);
+        let start = Instant::now();
+        let ip = |i: u32| Some(IpAddr::from(std::net::Ipv4Addr::from(0x0a00_0000 + i)));
+        // Every bucket is still refilling, so the longest idle one goes
+        for i in 0..=MAX_BUCKETS as u32 {
+            anonymous.ip = ip(i);
+            let now = start + Duration::from_millis(i as u64);
+            assert!(limiter.admit_at(&anonymous, 1, now, &at(9, 0)).is_ok());
+        }
+        let state = limiter.state.lock().unwrap();
+        assert_eq!(state.buckets.len(), MAX_BUCKETS);
+        assert!(!state.buckets.contains_key(&Client::Ip(ip(0).unwrap())));
+        assert!(state.buckets.contains_key(&Client::Ip(ip(1).unwrap())));
+    }
+
+    #[test]
+    fn enforces_daily_quotas() {
+        let limiter = limiter(LimitsConfig::default());
+        let kiosk = caller(&limiter, Some("kiosk-key"));
+        let now = Instant::now();
+        assert!(limiter.admit_at(&kiosk, 60, now, &at(9, 0)).is_ok());
+        assert!(limiter.admit_at(&kiosk, 30, now, &at(9, 0)).is_ok());
+        let refused = limiter.admit_at(&kiosk, 20, now, &at(23, 30));
+        assert!(matches!(refused, Err(AppError::RateLimited { .. })));
+        assert_eq!(retry_after(refused), 1800);
+
+        let usage = limiter.usage_on(&kiosk, at(9, 0).date_naive());
+        assert_eq!(usage.len(), 1);
+        assert_eq!((usage[0].requests, usage[0].chars), (2, 90));
+        assert_eq!(usage[0].remaining_chars, Some(10));
+
+        // Failed requests give their characters back
+        limiter.refund_on(&kiosk, 30, at(9, 0).date_naive());
+        assert!(limiter.admit_at(&kiosk, 40, now, &at(9, 0)).is_ok());
+        let usage = limiter.usage_on(&kiosk, at(9, 0).date_naive());
+        assert_eq!((usage[0].requests, usage[0].chars), (2, 100));
+
+        // Only the admin token sees other keys
+        let anonymous = caller(&limiter, None);
+        assert!(limiter
+            .usage_on(&anonymous, at(9, 0).date_naive())
+            .is_empty());
+
+        // A new day starts from zero
+        let tomorrow = at(9, 0) + chrono::Duration::days(1);
+        assert!(limiter.admit_at(&kiosk, 100, now, &tomorrow).is_ok());
+        let admin = caller(&limiter, Some("admin-secret"));
+        let usage = limiter.usage_on(&admin, tomorrow.date_naive());
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].remaining_chars, Some(0));
+        assert_eq!(usage[1].requests_per_minute, None);
+    }
+
+    /// Central European time around the end of summer time, when clocks
+    /// go back from 03:00 to 02:00 on 2026-10-25
+    #[derive(Debug, Clone, Copy)]
+    struct Cet;
+
+    fn cet_switch() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 25)
+            .unwrap()
+            .and_hms_opt(1, 0, 0)
+            .unwrap()
+    }
+
+    impl TimeZone for Cet {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            Cet
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> MappedLocalTime<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(
+            &self,
+            local: &NaiveDateTime,
+        ) -> MappedLocalTime<FixedOffset> {
+            let offsets: Vec<FixedOffset> = [2, 1]
+                .into_iter()
+                .map(|hours| FixedOffset::east_opt(hours * 3600).unwrap())
+                .filter(|offset| self.offset_from_utc_datetime(&(*local - *offset)) == *offset)
+                .collect();
+            match offsets[..] {
+                [offset] => MappedLocalTime::Single(offset),
+                [summer, winter] => MappedLocalTime::Ambiguous(summer, winter),
+                _ => MappedLocalTime::None,
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let hours = if *utc < cet_switch() { 2 } else { 1 };
+            FixedOffset::east_opt(hours * 3600).unwrap()
+        }
+    }
+
+    #[test]
+    fn quota_resets_at_local_midnight_across_dst() {
+        let local = |day, hour, minute| {
+            Cet.from_local_datetime(
+                &NaiveDate::from_ymd_opt(2026, 10, day)
+                    .unwrap()
+                    .and_hms_opt(hour, minute, 0)
+                    .unwrap(),
+            )
+            .earliest()
+            .unwrap()
+        };
+        assert_eq!(secs_until_midnight(&local(24, 23, 30)), 1800);
+        // The day summer time ends has 25 hours
+        assert_eq!(secs_until_midnight(&local(25, 0, 30)), 88200);
+        assert_eq!(secs_until_midnight(&at(23, 30)), 1800);
+    }
+}
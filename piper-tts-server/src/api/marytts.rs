@@ -0,0 +1,228 @@
+//! MaryTTS-compatible HTTP API (`/process`, `/voices`, `/locales`) for
+//! legacy clients and Home Assistant's MaryTTS integration.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Form, Router,
+};
+
+use crate::api::auth::Caller;
+use crate::api::routes::AppState;
+use crate::error::AppError;
+use crate::tts::{AudioFormat, VoiceInfo};
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/process", get(process_get).post(process_post))
+        .route("/voices", get(voices))
+        .route("/locales", get(locales))
+}
+
+async fn process_get(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, MaryError> {
+    process(state, caller, params).await
+}
+
+async fn process_post(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Query(query): Query<HashMap<String, String>>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Result<Response, MaryError> {
+    let mut params = query;
+    params.extend(form);
+    process(state, caller, params).await
+}
+
+/// Synthesize `INPUT_TEXT` with `VOICE`, or the first voice for `LOCALE`
+async fn process(
+    state: Arc<AppState>,
+    caller: Caller,
+    params: HashMap<String, String>,
+) -> Result<Response, MaryError> {
+    let params: HashMap<String, String> = params
+        .into_iter()
+        .map(|(key, value)| (key.to_ascii_uppercase(), value))
+        .collect();
+    let param = |name: &str| params.get(name).map(|v| v.trim()).filter(|v| !v.is_empty());
+
+    let text = param("INPUT_TEXT")
+        .ok_or_else(|| AppError::BadRequest("Missing INPUT_TEXT".into()))?
+        .to_string();
+    for (name, supported) in [
+        ("INPUT_TYPE", &["TEXT"][..]),
+        ("OUTPUT_TYPE", &["AUDIO"][..]),
+        ("AUDIO", &["WAVE", "WAVE_FILE"][..]),
+    ] {
+        if let Some(value) = param(name) {
+            if !supported.iter().any(|s| s.eq_ignore_ascii_case(value)) {
+                return Err(AppError::BadRequest(format!(
+                    "Unsupported {} '{}' (supported: {})",
+                    name,
+                    value,
+                    supported.join(", ")
+                ))
+                .into());
+            }
+        }
+    }
+    let max = state.config.server.max_text_length;
+    if text.chars().count() > max {
+        return Err(AppError::BadRequest(format!("Text too long (max {} chars)", max)).into());
+    }
+
+    let chars = text.chars().count();
+    state.limiter.admit(&caller, chars)?;
+
+    let voice = param("VOICE").map(str::to_string);
+    let locale = param("LOCALE").map(str::to_string);
+    let (task_state, task_caller) = (Arc::clone(&state), caller.clone());
+    let result = tokio::task::spawn_blocking(move || {
+        let voice = match (voice, locale) {
+            (Some(voice), _) => Some(voice),
+            (None, Some(locale)) => {
+                Some(voice_for_locale(&task_state.tts.list_voices()?, &locale)?)
+            }
+            (None, None) => None,
+        };
+        task_caller.check_voice(&task_state.tts, voice.as_deref())?;
+        task_state
+            .tts
+            .speak_cached(&text, voice.as_deref(), AudioFormat::Wav)
+    })
+    .await
+    .map_err(|e| AppError::TtsError(format!("Task join error: {}", e)))
+    .and_then(|result| result);
+    let audio = state.limiter.refund_on_error(&caller, chars, result)?;
+
+    Ok(([(header::CONTENT_TYPE, "audio/x-wav")], audio.audio).into_response())
+}
+
+/// Every voice's metadata; reads each voice config, so off the executor
+async fn installed_voices(state: Arc<AppState>) -> Result<Vec<VoiceInfo>, AppError> {
+    tokio::task::spawn_blocking(move || state.tts.list_voices())
+        .await
+        .map_err(|e| AppError::TtsError(format!("Task join error: {}", e)))?
+}
+
+/// One voice per line: `name locale gender type`
+async fn voices(State(state): State<Arc<AppState>>) -> Result<String, MaryError> {
+    let voices = installed_voices(state).await?;
+    Ok(voices
+        .iter()
+        .map(|voice| format!("{} {} unknown piper\n", voice.id, voice.language_code))
+        .collect())
+}
+
+/// One locale per line
+async fn locales(State(state): State<Arc<AppState>>) -> Result<String, MaryError> {
+    let mut locales: Vec<String> = installed_voices(state)
+        .await?
+        .into_iter()
+        .map(|voice| voice.language_code)
+        .collect();
+    locales.sort();
+    locales.dedup();
+    Ok(locales.into_iter().map(|l| l + "\n").collect())
+}
+
+/// The first voice for `locale` (`en_US`, `en-US` or just `en`), preferring
+/// an exact match over a language-only one
+fn voice_for_locale(voices: &[VoiceInfo], locale: &str) -> Result<String, AppError> {
+    let locale = locale.replace('-', "_");
+    let language = locale.split('_').next().unwrap_or_default();
+    voices
+        .iter()
+        .find(|voice| voice.language_code.eq_ignore_ascii_case(&locale))
+        .or_else(|| {
+            voices.iter().find(|voice| {
+                voice
+                    .language_code
+                    .split('_')
+                    .next()
+                    .is_some_and(|l| l.eq_ignore_ascii_case(language))
+            })
+        })
+        .map(|voice| voice.id.clone())
+        .ok_or_else(|| AppError::NotFound(format!("No voice installed for locale {}", locale)))
+}
+
+/// Errors as plain text, as MaryTTS clients expect
+#[derive(Debug)]
+pub struct MaryError(AppError);
+
+impl From<AppError> for MaryError {
+    fn from(error: AppError) -> Self {
+        Self(error)
+    }
+}
+
+impl IntoResponse for MaryError {
+    fn into_response(self) -> Response {
+        let (status, code, message) = self.0.parts();
+        tracing::error!("Request failed: {} - {}", code, message);
+        let mut response = (status, message).into_response();
+        if let Some(secs) = self.0.retry_after() {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, secs.into());
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(id: &str, language: &str) -> VoiceInfo {
+        VoiceInfo {
+            id: id.to_string(),
+            name: id.to_string(),
+            language: "en".to_string(),
+            language_code: language.to_string(),
+            language_family: None,
+            language_region: None,
+            language_name: None,
+            language_name_native: None,
+            country: None,
+            espeak_voice: None,
+            dataset: None,
+            quality: None,
+            sample_rate: 22050,
+            num_speakers: 1,
+            speakers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn picks_voices_by_locale() {
+        let voices = [
+            voice("en_GB-alba-medium", "en_GB"),
+            voice("en_US-lessac-medium", "en_US"),
+            voice("de_DE-thorsten-medium", "de_DE"),
+        ];
+        assert_eq!(
+            voice_for_locale(&voices, "en-US").unwrap(),
+            "en_US-lessac-medium"
+        );
+        assert_eq!(
+            voice_for_locale(&voices, "en").unwrap(),
+            "en_GB-alba-medium"
+        );
+        assert_eq!(
+            voice_for_locale(&voices, "de_AT").unwrap(),
+            "de_DE-thorsten-medium"
+        );
+        assert!(voice_for_locale(&voices, "fr_FR").is_err());
+    }
+}
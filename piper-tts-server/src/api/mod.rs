@@ -1,14 +1,90 @@
+pub mod auth;
 pub mod handlers;
+pub mod limits;
+pub mod marytts;
+pub mod openai;
 pub mod routes;
+pub mod signed_url;
 
 use serde::{Deserialize, Serialize};
 
-use crate::tts::VoiceInfo;
+use std::collections::HashMap;
+
+use crate::api::limits::KeyUsage;
+use crate::tts::alias::AliasTarget;
+use crate::tts::playback::{Backend, DeviceInfo, PlayOptions, QueuedItem};
+use crate::tts::{
+    AudioFormat, AudiobookOptions, BatchItem, CatalogEntry, InstallReport, Job, Prompt,
+    PromptStatus, Schedule, ScheduleStatus, Template, TemplateInfo, VoiceInfo,
+};
 
 #[derive(Debug, Deserialize)]
 pub struct SpeakRequest {
     pub text: String,
-    pub voice: String,
+    /// Voice id or alias; the `default` alias when omitted
+    #[serde(default)]
+    pub voice: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SpeakAloudRequest {
+    pub text: String,
+    #[serde(default)]
+    pub voice: Option<String>,
+    #[serde(flatten)]
+    pub options: PlayOptions,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VolumeRequest {
+    /// 0.0 to 1.0
+    pub volume: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DevicesResponse {
+    pub backend: Backend,
+    pub devices: Vec<DeviceInfo>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkipResponse {
+    pub skipped: Option<QueuedItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClearResponse {
+    pub removed: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SignUrlRequest {
+    pub text: String,
+    #[serde(default)]
+    pub voice: Option<String>,
+    #[serde(default)]
+    pub format: AudioFormat,
+    /// How long the URL stays valid
+    #[serde(default = "default_url_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+fn default_url_ttl_secs() -> u64 {
+    3600
+}
+
+#[derive(Debug, Serialize)]
+pub struct SignUrlResponse {
+    pub url: String,
+    /// Unix seconds
+    pub expires: u64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct VoicesQuery {
+    /// Language code (`en_GB`) or family (`en`)
+    pub language: Option<String>,
+    pub quality: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -16,8 +92,111 @@ pub struct VoicesResponse {
     pub voices: Vec<VoiceInfo>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct AliasesResponse {
+    pub aliases: HashMap<String, AliasTarget>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageResponse {
+    pub keys: Vec<KeyUsage>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct HealthResponse {
     pub status: String,
     pub version: String,
 }
+
+#[derive(Debug, Serialize)]
+pub struct ReadyResponse {
+    pub ready: bool,
+    pub loaded_voices: Vec<String>,
+    pub failed_voices: Vec<FailedVoice>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailedVoice {
+    pub id: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CatalogResponse {
+    pub voices: Vec<CatalogEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InstallRequest {
+    pub voices: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstallResponse {
+    pub installed: Vec<InstallReport>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PromptsRequest {
+    pub prompts: HashMap<String, Prompt>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PromptsResponse {
+    pub prompts: Vec<PromptStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SchedulesRequest {
+    pub schedules: HashMap<String, Schedule>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchedulesResponse {
+    pub schedules: Vec<ScheduleStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TemplatesRequest {
+    pub templates: HashMap<String, Template>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TemplatesResponse {
+    pub templates: Vec<TemplateInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TemplateSpeakRequest {
+    #[serde(default)]
+    pub variables: HashMap<String, serde_json::Value>,
+    /// Overrides the template's voice
+    #[serde(default)]
+    pub voice: Option<String>,
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JobRequest {
+    pub text: String,
+    #[serde(default)]
+    pub voice: Option<String>,
+    #[serde(default)]
+    pub format: AudioFormat,
+    /// Narrate as an audiobook, split into chapters
+    #[serde(default)]
+    pub audiobook: Option<AudiobookOptions>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchRequest {
+    pub items: Vec<BatchItem>,
+    #[serde(default)]
+    pub format: AudioFormat,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JobsResponse {
+    pub jobs: Vec<Job>,
+}
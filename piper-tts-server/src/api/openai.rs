@@ -0,0 +1,267 @@
+//! OpenAI-compatible text-to-speech API, so existing OpenAI SDKs and tools
+//! can use this server by changing their base URL.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{rejection::JsonRejection, Extension, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::api::auth::Caller;
+use crate::api::routes::AppState;
+use crate::error::AppError;
+use crate::tts::AudioFormat;
+
+/// Sample rate OpenAI clients expect for `pcm` output
+const PCM_SAMPLE_RATE: u32 = 24000;
+
+/// Model names OpenAI clients send; all use the requested voice as is
+const MODELS: [&str; 3] = ["tts-1", "tts-1-hd", "gpt-4o-mini-tts"];
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/audio/speech", post(speech))
+        .route("/models", get(models))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SpeechRequest {
+    /// Accepted for compatibility; the voice alone selects the model
+    #[allow(dead_code)]
+    pub model: String,
+    pub input: String,
+    /// Voice id or alias, e.g. an `alloy` alias in `aliases.json`
+    pub voice: String,
+    #[serde(default)]
+    pub response_format: Option<String>,
+    #[serde(default)]
+    pub speed: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelList {
+    pub object: &'static str,
+    pub data: Vec<Model>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Model {
+    pub id: String,
+    pub object: &'static str,
+    pub created: u64,
+    pub owned_by: &'static str,
+}
+
+/// Map `response_format` onto the server's encoders: every [`AudioFormat`]
+/// by its name, with `pcm` resampled to the 24 kHz OpenAI clients expect.
+/// Formats the server can't encode (`mp3`, `opus`, `aac`, `flac`) are a 400.
+fn response_format(format: Option<&str>) -> Result<AudioFormat, OpenAiError> {
+    let name = format.unwrap_or("wav");
+    AudioFormat::ALL
+        .into_iter()
+        .find(|format| format.extension() == name)
+        .ok_or_else(|| {
+            OpenAiError::param(
+                format!(
+                    "Unsupported response_format '{}' (supported: {})",
+                    name,
+                    AudioFormat::ALL.map(|format| format.extension()).join(", ")
+                ),
+                "response_format",
+            )
+        })
+}
+
+async fn speech(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    request: Result<Json<SpeechRequest>, JsonRejection>,
+) -> Result<Response, OpenAiError> {
+    let Json(request) =
+        request.map_err(|e| OpenAiError::from(AppError::BadRequest(e.body_text())))?;
+
+    let format = response_format(request.response_format.as_deref())?;
+    let speed = request.speed.unwrap_or(1.0);
+    if !(0.25..=4.0).contains(&speed) {
+        return Err(OpenAiError::param(
+            "speed must be between 0.25 and 4.0".to_string(),
+            "speed",
+        ));
+    }
+    if request.input.trim().is_empty() {
+        return Err(OpenAiError::param(
+            "input cannot be empty".to_string(),
+            "input",
+        ));
+    }
+    let max = state.config.server.max_text_length;
+    if request.input.chars().count() > max {
+        return Err(OpenAiError::param(
+            format!("input is too long (max {} characters)", max),
+            "input",
+        ));
+    }
+    caller.check_voice(&state.tts, Some(&request.voice))?;
+    let chars = request.input.chars().count();
+    state.limiter.admit(&caller, chars)?;
+
+    let task_state = Arc::clone(&state);
+    let result = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, AppError> {
+        let audio =
+            task_state
+                .tts
+                .synthesize_at_speed(&request.input, Some(&request.voice), speed)?;
+        match format {
+            AudioFormat::Pcm => audio.resample(PCM_SAMPLE_RATE).encode(format),
+            _ => audio.encode(format),
+        }
+    })
+    .await
+    .map_err(|e| AppError::TtsError(format!("Task join error: {}", e)))
+    .and_then(|result| result);
+    let body = state.limiter.refund_on_error(&caller, chars, result)?;
+
+    let content_type = match format {
+        AudioFormat::Pcm => "audio/pcm",
+        _ => format.content_type(),
+    };
+    Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+}
+
+/// The OpenAI model names, then every installed voice and alias
+async fn models(State(state): State<Arc<AppState>>) -> Result<Json<ModelList>, OpenAiError> {
+    let mut ids: Vec<String> = MODELS.iter().map(|m| m.to_string()).collect();
+    // Reads every voice config, so off the executor
+    let voices_state = Arc::clone(&state);
+    let voices = tokio::task::spawn_blocking(move || voices_state.tts.list_voices())
+        .await
+        .map_err(|e| AppError::TtsError(format!("Task join error: {}", e)))??;
+    ids.extend(voices.into_iter().map(|voice| voice.id));
+    let mut aliases: Vec<String> = state.tts.aliases().entries().keys().cloned().collect();
+    aliases.sort();
+    ids.extend(aliases);
+
+    Ok(Json(ModelList {
+        object: "list",
+        data: ids
+            .into_iter()
+            .map(|id| Model {
+                id,
+                object: "model",
+                created: 0,
+                owned_by: "piper",
+            })
+            .collect(),
+    }))
+}
+
+/// An error rendered in OpenAI's shape:
+/// `{"error": {"message", "type", "param", "code"}}`
+#[derive(Debug)]
+pub struct OpenAiError {
+    error: AppError,
+    param: Option<&'static str>,
+}
+
+impl OpenAiError {
+    fn param(message: String, param: &'static str) -> Self {
+        Self {
+            error: AppError::BadRequest(message),
+            param: Some(param),
+        }
+    }
+}
+
+impl From<AppError> for OpenAiError {
+    fn from(error: AppError) -> Self {
+        let param = matches!(error, AppError::VoiceNotFound(_)).then_some("voice");
+        Self { error, param }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Serialize)]
+struct ErrorDetail {
+    message: String,
+    r#type: &'static str,
+    param: Option<&'static str>,
+    code: String,
+}
+
+impl IntoResponse for OpenAiError {
+    fn into_response(self) -> Response {
+        let (status, code, message) = self.error.parts();
+        // OpenAI reports unknown voices as invalid requests
+        let status = if matches!(self.error, AppError::VoiceNotFound(_)) {
+            StatusCode::BAD_REQUEST
+        } else {
+            status
+        };
+        let kind = if status.is_server_error() {
+            "server_error"
+        } else {
+            "invalid_request_error"
+        };
+
+        tracing::error!("Request failed: {} - {}", code, message);
+
+        let mut response = (
+            status,
+            Json(ErrorBody {
+                error: ErrorDetail {
+                    message,
+                    r#type: kind,
+                    param: self.param,
+                    code: code.to_ascii_lowercase(),
+                },
+            }),
+        )
+            .into_response();
+        if let Some(secs) = self.error.retry_after() {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, secs.into());
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_response_formats() {
+        assert_eq!(response_format(None).unwrap(), AudioFormat::Wav);
+        for format in AudioFormat::ALL {
+            assert_eq!(response_format(Some(format.extension())).unwrap(), format);
+        }
+        for unsupported in ["mp3", "opus", "aac", "flac"] {
+            let error = response_format(Some(unsupported)).unwrap_err();
+            assert_eq!(error.param, Some("response_format"));
+            assert!(error.error.to_string().contains("wav, pcm"), "{:?}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn renders_openai_errors() {
+        let response = OpenAiError::from(AppError::VoiceNotFound("nova".into())).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["type"], "invalid_request_error");
+        assert_eq!(json["error"]["param"], "voice");
+        assert_eq!(json["error"]["code"], "voice_not_found");
+    }
+}
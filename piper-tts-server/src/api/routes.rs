@@ -1,44 +1,238 @@
 use axum::{
-    http::{header, Method},
+    extract::{DefaultBodyLimit, Request, State},
+    http::{header, HeaderName, HeaderValue, Method},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Router,
 };
-use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
 use tower_http::{
-    cors::{Any, CorsLayer},
+    cors::{AllowOrigin, Any, CorsLayer},
     services::ServeDir,
     trace::TraceLayer,
 };
 
-use super::handlers;
-use crate::tts::TtsService;
+use super::limits::{self, RateLimiter};
+use super::signed_url::UrlSigner;
+use super::{auth, handlers, marytts, openai};
+use crate::config::Config;
+use crate::error::AppError;
+use crate::tts::{JobQueue, PlaybackQueue, PromptLibrary, Scheduler, TemplateLibrary, TtsService};
 
 pub struct AppState {
     pub tts: TtsService,
+    /// Set once startup preloading has finished
+    pub ready: AtomicBool,
+    /// Voices that failed to preload, with the reason
+    pub preload_errors: Mutex<Vec<(String, String)>>,
+    pub config: Config,
+    /// Named, pre-rendered prompts (when `prompts.dir` is set)
+    pub prompts: Option<PromptLibrary>,
+    /// Background synthesis jobs (when `jobs.dir` is set)
+    pub jobs: Option<JobQueue>,
+    /// Timed announcements (when `schedules.dir` is set)
+    pub schedules: Option<Scheduler>,
+    /// Announcement templates (when `templates.dir` is set)
+    pub templates: Option<TemplateLibrary>,
+    /// Signs and checks `GET /api/speak` URLs (when `auth.url_signing_key` is set)
+    pub url_signer: Option<UrlSigner>,
+    /// Per-key and per-IP rate limits and daily quotas
+    pub limiter: RateLimiter,
+    /// Server-side playback (when enabled and an output device opened)
+    pub playback: Option<Arc<PlaybackQueue>>,
+}
+
+impl AppState {
+    pub fn new(
+        tts: TtsService,
+        config: Config,
+        prompts: Option<PromptLibrary>,
+        jobs: Option<JobQueue>,
+        schedules: Option<Scheduler>,
+        templates: Option<TemplateLibrary>,
+        playback: Option<Arc<PlaybackQueue>>,
+    ) -> Self {
+        let url_signer = config.auth.url_signing_key.as_deref().map(UrlSigner::new);
+        let limiter = RateLimiter::new(&config);
+        Self {
+            tts,
+            ready: AtomicBool::new(false),
+            preload_errors: Mutex::new(Vec::new()),
+            config,
+            prompts,
+            jobs,
+            schedules,
+            templates,
+            url_signer,
+            limiter,
+            playback,
+        }
+    }
+}
+
+/// Check the request's API key against its route (see `auth`) and pass
+/// the caller, with its address for rate limits, on to handlers. Errors
+/// use the format of the API the route belongs to.
+async fn authenticate(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let result = auth::authorize(
+        &state.config.auth,
+        state.url_signer.is_some(),
+        request.method(),
+        request.uri(),
+        request.headers(),
+    );
+    let error = match result {
+        Ok(mut caller) => {
+            caller.ip = limits::client_ip(&state.config.limits, &request);
+            request.extensions_mut().insert(caller);
+            return next.run(request).await;
+        }
+        Err(error) => error,
+    };
+    let unauthorized = matches!(error, AppError::Unauthorized(_));
+    let path = request.uri().path();
+    let mut response = if path.starts_with("/v1/") {
+        openai::OpenAiError::from(error).into_response()
+    } else if matches!(path, "/process" | "/voices" | "/locales") {
+        marytts::MaryError::from(error).into_response()
+    } else {
+        error.into_response()
+    };
+    if unauthorized {
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    }
+    response
 }
 
 pub fn create_router(state: Arc<AppState>) -> Router {
+    let server = &state.config.server;
+    let origins = if server.cors_origins.iter().any(|o| o == "*") {
+        AllowOrigin::from(Any)
+    } else {
+        AllowOrigin::list(
+            server
+                .cors_origins
+                .iter()
+                .filter_map(|o| HeaderValue::from_str(o).ok()),
+        )
+    };
     let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([Method::GET, Method::POST])
-        .allow_headers([header::CONTENT_TYPE]);
+        .allow_origin(origins)
+        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            header::IF_NONE_MATCH,
+            HeaderName::from_static(auth::API_KEY_HEADER),
+        ])
+        .expose_headers([header::ETAG, header::CACHE_CONTROL]);
 
-    #[cfg(feature = "audio-playback")]
     let api_routes = Router::new()
-        .route("/speak", post(handlers::speak))
+        .route("/speak", get(handlers::speak_url).post(handlers::speak))
         .route("/voices", get(handlers::list_voices))
+        .route("/voices/events", get(handlers::voice_events))
+        .route("/voices/:id", get(handlers::voice_detail))
+        .route("/voices/:id/status", get(handlers::voice_status))
+        .route("/aliases", get(handlers::list_aliases))
+        .route(
+            "/prompts",
+            get(handlers::list_prompts).post(handlers::register_prompts),
+        )
+        .route(
+            "/prompts/:id",
+            get(handlers::get_prompt).delete(handlers::delete_prompt),
+        )
+        .route(
+            "/schedules",
+            get(handlers::list_schedules).post(handlers::put_schedules),
+        )
+        .route(
+            "/schedules/:id",
+            get(handlers::get_schedule).delete(handlers::delete_schedule),
+        )
+        .route("/schedules/:id/run", post(handlers::run_schedule))
+        .route(
+            "/templates",
+            get(handlers::list_templates).post(handlers::put_templates),
+        )
+        .route(
+            "/templates/:name",
+            get(handlers::get_template).delete(handlers::delete_template),
+        )
+        .route("/templates/:name/speak", post(handlers::speak_template))
+        .route(
+            "/jobs",
+            get(handlers::list_jobs)
+                .post(handlers::submit_job)
+                // Room for the longest job text as UTF-8 plus form overhead
+                .layer(DefaultBodyLimit::max(
+                    state.config.jobs.max_text_length * 4 + 64 * 1024,
+                )),
+        )
+        .route(
+            "/batch",
+            post(handlers::batch)
+                // Room for a full batch of maximum-length items
+                .layer(DefaultBodyLimit::max(
+                    state.config.batch.max_items * (state.config.server.max_text_length * 4 + 1024),
+                )),
+        )
+        .route("/jobs/:id", get(handlers::job_status))
+        .route("/jobs/:id/result", get(handlers::job_result))
+        .route("/jobs/:id/chapters/:index", get(handlers::job_chapter))
+        .route("/jobs/:id/cancel", post(handlers::cancel_job))
         .route("/health", get(handlers::health))
-        .route("/speak-aloud", post(handlers::speak_aloud));
+        .route("/ready", get(handlers::ready))
+        .route("/engines", get(handlers::engine_stats))
+        .route("/usage", get(handlers::usage));
 
-    #[cfg(not(feature = "audio-playback"))]
-    let api_routes = Router::new()
-        .route("/speak", post(handlers::speak))
-        .route("/voices", get(handlers::list_voices))
-        .route("/health", get(handlers::health));
+    let admin_routes = Router::new()
+        .route("/catalog", get(handlers::voice_catalog))
+        .route("/voices/install", post(handlers::install_voices))
+        .route("/speak-url", post(handlers::sign_speak_url))
+        .route(
+            "/cache",
+            get(handlers::audio_cache_stats).delete(handlers::purge_audio_cache),
+        );
+
+    let api_routes = api_routes.nest("/admin", admin_routes);
+
+    let api_routes = if state.playback.is_some() {
+        api_routes
+            .route("/speak-aloud", post(handlers::speak_aloud))
+            .route(
+                "/playback",
+                get(handlers::playback_status).delete(handlers::clear_playback),
+            )
+            .route("/playback/devices", get(handlers::playback_devices))
+            .route("/playback/volume", post(handlers::set_playback_volume))
+            .route("/playback/skip", post(handlers::skip_playback))
+            .route("/playback/pause", post(handlers::pause_playback))
+            .route("/playback/resume", post(handlers::resume_playback))
+    } else {
+        api_routes
+    };
+
+    let static_files =
+        ServeDir::new(&state.config.server.static_dir).append_index_html_on_directories(true);
 
     Router::new()
         .nest("/api", api_routes)
-        .nest_service("/", ServeDir::new("static").append_index_html_on_directories(true))
+        .nest("/v1", openai::router())
+        .merge(marytts::router())
+        .nest_service("/", static_files)
+        .layer(middleware::from_fn_with_state(
+            Arc::clone(&state),
+            authenticate,
+        ))
         .layer(cors)
         .layer(TraceLayer::new_for_http())
         .with_state(state)
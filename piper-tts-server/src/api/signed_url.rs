@@ -0,0 +1,251 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::api::auth::Caller;
+use crate::error::AppError;
+use crate::tts::AudioFormat;
+
+const BLOCK_SIZE: usize = 64;
+
+/// Query string of `GET /api/speak`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SpeakQuery {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Unix seconds after which a signed URL stops working
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
+    /// Hex HMAC-SHA256 of the other parameters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sig: Option<String>,
+}
+
+impl SpeakQuery {
+    pub fn format(&self) -> Result<AudioFormat, AppError> {
+        self.format
+            .as_deref()
+            .map_or(Ok(AudioFormat::default()), str::parse)
+    }
+}
+
+/// Signs and checks `GET /api/speak` URLs so they can be handed to
+/// devices without opening the endpoint to everyone
+pub struct UrlSigner {
+    key: Vec<u8>,
+}
+
+impl UrlSigner {
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: key.as_bytes().to_vec(),
+        }
+    }
+
+    /// A relative URL for `text` that stays valid for `ttl_secs`
+    pub fn sign(
+        &self,
+        text: &str,
+        voice: Option<&str>,
+        format: AudioFormat,
+        ttl_secs: u64,
+    ) -> Result<(String, u64), AppError> {
+        let expires = unix_now().saturating_add(ttl_secs);
+        let query = SpeakQuery {
+            text: text.to_string(),
+            voice: voice.map(str::to_string),
+            format: Some(format.extension().to_string()),
+            expires: Some(expires),
+            sig: Some(self.signature(text, voice, format, expires)),
+        };
+        let query = serde_urlencoded::to_string(&query)
+            .map_err(|e| AppError::BadRequest(format!("Failed to encode URL: {}", e)))?;
+        Ok((format!("/api/speak?{}", query), expires))
+    }
+
+    /// Let a request through: callers with an API key were checked like
+    /// any other route, the rest need a valid signature
+    pub fn admit(&self, caller: &Caller, query: &SpeakQuery) -> Result<(), AppError> {
+        if caller.key.is_some() {
+            return Ok(());
+        }
+        self.verify(query)
+    }
+
+    /// Reject missing, expired or forged signatures
+    pub fn verify(&self, query: &SpeakQuery) -> Result<(), AppError> {
+        let (Some(expires), Some(sig)) = (query.expires, query.sig.as_deref()) else {
+            return Err(AppError::Forbidden("This URL must be signed".into()));
+        };
+        if expires < unix_now() {
+            return Err(AppError::Forbidden("This URL has expired".into()));
+        }
+        let expected = self.signature(
+            &query.text,
+            query.voice.as_deref(),
+            query.format()?,
+            expires,
+        );
+        if !constant_time_eq(expected.as_bytes(), sig.to_ascii_lowercase().as_bytes()) {
+            return Err(AppError::Forbidden("Invalid URL signature".into()));
+        }
+        Ok(())
+    }
+
+    /// HMAC of the request. Every field is length-prefixed, so no choice
+    /// of text and voice can reproduce another request's message.
+    fn signature(
+        &self,
+        text: &str,
+        voice: Option<&str>,
+        format: AudioFormat,
+        expires: u64,
+    ) -> String {
+        let field = |value: &str| format!("{}:{}", value.len(), value);
+        let message = format!(
+            "GET /api/speak\n{}\n{}\n{}\n{}",
+            field(text),
+            voice.map_or_else(|| "-".to_string(), field),
+            field(format.extension()),
+            expires
+        );
+        hmac_sha256(&self.key, message.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Seconds until a signed URL expires, or `None` for unsigned ones
+pub fn remaining_secs(query: &SpeakQuery) -> Option<u64> {
+    query
+        .expires
+        .map(|expires| expires.saturating_sub(unix_now()))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(url: &str) -> SpeakQuery {
+        serde_urlencoded::from_str(url.split_once('?').unwrap().1).unwrap()
+    }
+
+    #[test]
+    fn computes_hmac_sha256() {
+        // RFC 4231 test case 2
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        let hex: String = mac.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(
+            hex,
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn verifies_signed_urls() {
+        let signer = UrlSigner::new("secret");
+        let (url, expires) = signer
+            .sign("Dinner & drinks?", Some("announcer"), AudioFormat::Wav, 60)
+            .unwrap();
+        let query = parse(&url);
+        assert_eq!(query.text, "Dinner & drinks?");
+        assert_eq!(query.expires, Some(expires));
+        signer.verify(&query).unwrap();
+
+        let mut tampered = query.clone();
+        tampered.text = "Something else".into();
+        assert!(matches!(
+            signer.verify(&tampered),
+            Err(AppError::Forbidden(_))
+        ));
+        assert!(UrlSigner::new("other").verify(&query).is_err());
+
+        let mut unsigned = query.clone();
+        unsigned.sig = None;
+        assert!(signer.verify(&unsigned).is_err());
+    }
+
+    #[test]
+    fn signatures_are_only_needed_without_a_key() {
+        use crate::api::auth::{authorize, Scope, API_KEY_HEADER};
+        use crate::config::{ApiKey, AuthConfig};
+        use axum::http::{HeaderMap, Method};
+
+        let auth = AuthConfig {
+            url_signing_key: Some("secret".into()),
+            keys: vec![ApiKey {
+                name: "kiosk".into(),
+                key: "kiosk-key-0123456789".into(),
+                scopes: vec![Scope::Synthesize],
+                voices: None,
+                requests_per_minute: None,
+                daily_chars: None,
+            }],
+            ..AuthConfig::default()
+        };
+        let url = "/api/speak?text=Hi";
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, "kiosk-key-0123456789".parse().unwrap());
+        let keyed = authorize(&auth, true, &Method::GET, &url.parse().unwrap(), &headers).unwrap();
+
+        let signer = UrlSigner::new("secret");
+        let unsigned = parse(url);
+        signer.admit(&keyed, &unsigned).unwrap();
+        assert!(matches!(
+            signer.admit(&Caller::default(), &unsigned),
+            Err(AppError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn fields_cannot_be_shifted_between_each_other() {
+        let signer = UrlSigner::new("secret");
+        let sign =
+            |text: &str, voice: Option<&str>| signer.signature(text, voice, AudioFormat::Wav, 100);
+        assert_ne!(sign("Hi\nannouncer", None), sign("Hi", Some("announcer")));
+        assert_ne!(sign("Hi\n", Some("x")), sign("Hi", Some("\nx")));
+        assert_ne!(sign("Hi", None), sign("Hi", Some("")));
+    }
+
+    #[test]
+    fn rejects_expired_urls() {
+        let signer = UrlSigner::new("secret");
+        let mut query = parse(&signer.sign("Hi", None, AudioFormat::Pcm, 0).unwrap().0);
+        query.expires = Some(1);
+        query.sig = Some(signer.signature("Hi", None, AudioFormat::Pcm, 1));
+        assert!(signer.verify(&query).is_err());
+    }
+}
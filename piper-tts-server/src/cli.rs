@@ -0,0 +1,309 @@
+use std::path::PathBuf;
+
+use clap::builder::BoolishValueParser;
+use clap::{Args, Parser, Subcommand};
+
+use crate::config::Config;
+use crate::tts::playback::Backend;
+use crate::tts::AudioFormat;
+
+#[derive(Debug, Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// TOML config file (default: ./config.toml if present)
+    #[arg(long, env = "CONFIG_FILE", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Server options when no subcommand is given
+    #[command(flatten)]
+    pub serve: ServeArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Overrides for the config file; flags win over environment variables
+#[derive(Debug, Clone, Args)]
+pub struct ServeArgs {
+    /// Print the effective configuration as TOML and exit
+    #[arg(long)]
+    pub print_config: bool,
+
+    #[arg(long, env = "HOST")]
+    host: Option<String>,
+
+    #[arg(long, env = "PORT")]
+    port: Option<u16>,
+
+    #[arg(long, env = "VOICES_DIR")]
+    voices_dir: Option<PathBuf>,
+
+    #[arg(long, env = "STATIC_DIR")]
+    static_dir: Option<PathBuf>,
+
+    /// Comma-separated origins, or *
+    #[arg(long, env = "CORS_ORIGINS", value_delimiter = ',')]
+    cors_origins: Option<Vec<String>>,
+
+    #[arg(long, env = "MAX_TEXT_LENGTH")]
+    max_text_length: Option<usize>,
+
+    #[arg(long, env = "VOICE_ALIASES")]
+    voice_aliases: Option<PathBuf>,
+
+    #[arg(long, env = "VOICE_CATALOG")]
+    voice_catalog: Option<PathBuf>,
+
+    /// Comma-separated voices to warm up, or all
+    #[arg(long, env = "PRELOAD_VOICES", value_delimiter = ',')]
+    preload_voices: Option<Vec<String>>,
+
+    #[arg(long, env = "WATCH_VOICES", value_parser = BoolishValueParser::new())]
+    watch_voices: Option<bool>,
+
+    #[arg(long, env = "ORT_THREADS")]
+    ort_threads: Option<usize>,
+
+    #[arg(long, env = "MAX_LOADED_VOICES")]
+    max_loaded_voices: Option<usize>,
+
+    #[arg(long, env = "MAX_ENGINE_MEMORY_MB")]
+    max_engine_memory_mb: Option<u64>,
+
+    #[arg(long, env = "PINNED_VOICES", value_delimiter = ',')]
+    pinned_voices: Option<Vec<String>>,
+
+    #[arg(long, env = "AUDIO_CACHE_DIR")]
+    audio_cache_dir: Option<PathBuf>,
+
+    #[arg(long, env = "AUDIO_CACHE_MAX_MB")]
+    audio_cache_max_mb: Option<u64>,
+
+    #[arg(long, env = "AUDIO_CACHE_MEMORY_MB")]
+    audio_cache_memory_mb: Option<u64>,
+
+    #[arg(long, env = "PROMPTS_DIR")]
+    prompts_dir: Option<PathBuf>,
+
+    #[arg(long, env = "SCHEDULES_DIR")]
+    schedules_dir: Option<PathBuf>,
+
+    #[arg(long, env = "TEMPLATES_DIR")]
+    templates_dir: Option<PathBuf>,
+
+    #[arg(long, env = "JOBS_DIR")]
+    jobs_dir: Option<PathBuf>,
+
+    #[arg(long, env = "JOB_WORKERS")]
+    job_workers: Option<usize>,
+
+    #[arg(long, env = "BATCH_WORKERS")]
+    batch_workers: Option<usize>,
+
+    #[arg(long, env = "WYOMING_PORT")]
+    wyoming_port: Option<u16>,
+
+    #[arg(long, env = "MQTT_BROKER")]
+    mqtt_broker: Option<String>,
+
+    #[arg(long, env = "MQTT_USERNAME")]
+    mqtt_username: Option<String>,
+
+    #[arg(long, env = "MQTT_PASSWORD", hide_env_values = true)]
+    mqtt_password: Option<String>,
+
+    #[arg(long, env = "DSL_ENABLED", value_parser = BoolishValueParser::new())]
+    dsl_enabled: Option<bool>,
+
+    #[arg(long, env = "ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
+    #[arg(long, env = "URL_SIGNING_KEY", hide_env_values = true)]
+    url_signing_key: Option<String>,
+
+    #[arg(long, env = "PLAYBACK_ENABLED", value_parser = BoolishValueParser::new())]
+    playback_enabled: Option<bool>,
+
+    /// device or null
+    #[arg(long, env = "PLAYBACK_BACKEND")]
+    playback_backend: Option<Backend>,
+
+    #[arg(long, env = "PLAYBACK_DEVICE")]
+    playback_device: Option<String>,
+}
+
+impl ServeArgs {
+    pub fn apply(self, config: &mut Config) {
+        fn list(values: Vec<String>) -> Vec<String> {
+            values
+                .into_iter()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .collect()
+        }
+
+        let server = &mut config.server;
+        if let Some(host) = self.host {
+            server.host = host;
+        }
+        server.port = self.port.unwrap_or(server.port);
+        if let Some(dir) = self.static_dir {
+            server.static_dir = dir;
+        }
+        if let Some(origins) = self.cors_origins {
+            server.cors_origins = list(origins);
+        }
+        server.max_text_length = self.max_text_length.unwrap_or(server.max_text_length);
+
+        let engine = &mut config.engine;
+        if let Some(dir) = self.voices_dir {
+            engine.voices_dir = dir;
+        }
+        engine.aliases = self.voice_aliases.or(engine.aliases.take());
+        engine.catalog = self.voice_catalog.or(engine.catalog.take());
+        if let Some(preload) = self.preload_voices {
+            engine.preload = list(preload);
+        }
+        engine.watch = self.watch_voices.unwrap_or(engine.watch);
+        engine.intra_threads = self.ort_threads.unwrap_or(engine.intra_threads);
+
+        let cache = &mut config.cache;
+        cache.max_loaded_voices = self.max_loaded_voices.or(cache.max_loaded_voices);
+        cache.max_memory_mb = self.max_engine_memory_mb.or(cache.max_memory_mb);
+        if let Some(pinned) = self.pinned_voices {
+            cache.pinned_voices = list(pinned);
+        }
+        cache.audio_dir = self.audio_cache_dir.or(cache.audio_dir.take());
+        cache.audio_max_mb = self.audio_cache_max_mb.unwrap_or(cache.audio_max_mb);
+        cache.audio_memory_mb = self.audio_cache_memory_mb.unwrap_or(cache.audio_memory_mb);
+
+        config.prompts.dir = self.prompts_dir.or(config.prompts.dir.take());
+        config.schedules.dir = self.schedules_dir.or(config.schedules.dir.take());
+        config.templates.dir = self.templates_dir.or(config.templates.dir.take());
+        config.jobs.dir = self.jobs_dir.or(config.jobs.dir.take());
+        config.jobs.workers = self.job_workers.unwrap_or(config.jobs.workers);
+        config.batch.workers = self.batch_workers.unwrap_or(config.batch.workers);
+        config.wyoming.port = self.wyoming_port.or(config.wyoming.port);
+        config.mqtt.broker = self.mqtt_broker.or(config.mqtt.broker.take());
+        config.mqtt.username = self.mqtt_username.or(config.mqtt.username.take());
+        config.mqtt.password = self.mqtt_password.or(config.mqtt.password.take());
+        config.dsl.enabled = self.dsl_enabled.unwrap_or(config.dsl.enabled);
+        config.auth.admin_token = self.admin_token.or(config.auth.admin_token.take());
+        config.auth.url_signing_key = self.url_signing_key.or(config.auth.url_signing_key.take());
+        config.playback.enabled = self.playback_enabled.unwrap_or(config.playback.enabled);
+        config.playback.backend = self.playback_backend.unwrap_or(config.playback.backend);
+        config.playback.device = self.playback_device.or(config.playback.device.take());
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the HTTP server (the default)
+    Serve(Box<ServeArgs>),
+
+    /// Synthesize text straight to a file or stdout without the server
+    Synth {
+        /// Voice id or alias (default: the "default" alias)
+        #[arg(long)]
+        voice: Option<String>,
+
+        /// Text file to read, DSL tags included (default: stdin)
+        #[arg(long = "in", value_name = "FILE")]
+        input: Option<PathBuf>,
+
+        /// File to write (default: raw PCM on stdout)
+        #[arg(long = "out", value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// wav or pcm (default: wav for files, pcm for stdout)
+        #[arg(long)]
+        format: Option<AudioFormat>,
+
+        #[arg(long, env = "VOICES_DIR")]
+        voices_dir: Option<PathBuf>,
+
+        /// Alias file (default: aliases.json in the voices directory)
+        #[arg(long, env = "VOICE_ALIASES")]
+        aliases: Option<PathBuf>,
+    },
+
+    /// List the voices in the voices directory
+    Voices {
+        #[arg(long, env = "VOICES_DIR")]
+        voices_dir: Option<PathBuf>,
+
+        /// Only voices with this language code or family, e.g. en_GB or en
+        #[arg(long)]
+        language: Option<String>,
+
+        /// Print the full voice metadata as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Install voices from a local Piper voices.json catalog
+    InstallVoice {
+        /// Catalog file; paths inside it are relative to its directory
+        #[arg(long, env = "VOICE_CATALOG")]
+        catalog: Option<PathBuf>,
+
+        /// Directory to install into
+        #[arg(long, env = "VOICES_DIR")]
+        voices_dir: Option<PathBuf>,
+
+        /// List the voices in the catalog instead of installing
+        #[arg(long)]
+        list: bool,
+
+        /// Voice keys to install, e.g. en_GB-alba-medium
+        #[arg(required_unless_present = "list")]
+        voices: Vec<String>,
+    },
+
+    /// Check every voice loads, matches the engine and can synthesize
+    ValidateVoices {
+        #[arg(long, env = "VOICES_DIR")]
+        voices_dir: Option<PathBuf>,
+
+        /// Voices to check (default: all voices in the directory)
+        voices: Vec<String>,
+    },
+}
+
+impl Command {
+    /// Apply the command's flags to the config, ahead of validation
+    pub fn apply(&self, config: &mut Config) {
+        let voices_dir = match self {
+            Command::Serve(args) => {
+                args.as_ref().clone().apply(config);
+                return;
+            }
+            Command::Synth {
+                voices_dir,
+                aliases,
+                ..
+            } => {
+                if aliases.is_some() {
+                    config.engine.aliases = aliases.clone();
+                }
+                voices_dir
+            }
+            Command::InstallVoice {
+                voices_dir,
+                catalog,
+                ..
+            } => {
+                if catalog.is_some() {
+                    config.engine.catalog = catalog.clone();
+                }
+                voices_dir
+            }
+            Command::Voices { voices_dir, .. } | Command::ValidateVoices { voices_dir, .. } => {
+                voices_dir
+            }
+        };
+        if let Some(dir) = voices_dir {
+            config.engine.voices_dir = dir.clone();
+        }
+    }
+}
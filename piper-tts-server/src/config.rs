@@ -0,0 +1,909 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::auth::{Scope, ADMIN_KEY_NAME};
+use crate::tts::playback::{Backend, PlaybackSettings};
+use crate::tts::{AudioCacheConfig, AudioFormat, EngineCacheConfig, EngineOptions, JobConfig};
+
+/// Loaded from the working directory when no `--config` is given
+pub const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Failed to read {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid config file {}: {source}", path.display())]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("Invalid configuration:\n  {}", .0.join("\n  "))]
+    Invalid(Vec<String>),
+}
+
+/// Server configuration: defaults, overlaid by the TOML file, then
+/// environment variables and command line flags.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub engine: EngineConfig,
+    pub cache: CacheConfig,
+    pub dsl: DslConfig,
+    pub auth: AuthConfig,
+    pub limits: LimitsConfig,
+    pub playback: PlaybackConfig,
+    pub prompts: PromptsConfig,
+    pub schedules: SchedulesConfig,
+    pub templates: TemplatesConfig,
+    pub jobs: JobsConfig,
+    pub batch: BatchConfig,
+    pub wyoming: WyomingConfig,
+    pub mqtt: MqttConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    /// Directory served at `/` (the web UI)
+    pub static_dir: PathBuf,
+    /// Allowed CORS origins; `["*"]` allows any
+    pub cors_origins: Vec<String>,
+    /// Longest text accepted by the synthesis endpoints, in characters
+    pub max_text_length: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 3000,
+            static_dir: PathBuf::from("static"),
+            cors_origins: vec!["*".to_string()],
+            max_text_length: 10000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig {
+    pub voices_dir: PathBuf,
+    /// Alias file (default: `aliases.json` in the voices directory)
+    pub aliases: Option<PathBuf>,
+    /// Piper `voices.json` catalog for offline installs
+    pub catalog: Option<PathBuf>,
+    /// Voices to warm up at startup, or `["all"]`
+    pub preload: Vec<String>,
+    /// Reload engines when voice files change
+    pub watch: bool,
+    /// ONNX Runtime intra-op threads per voice session
+    pub intra_threads: usize,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            voices_dir: PathBuf::from("./voices"),
+            aliases: None,
+            catalog: None,
+            preload: Vec::new(),
+            watch: true,
+            intra_threads: 4,
+        }
+    }
+}
+
+impl EngineConfig {
+    pub fn aliases_path(&self) -> PathBuf {
+        self.aliases
+            .clone()
+            .unwrap_or_else(|| self.voices_dir.join("aliases.json"))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Maximum number of voice engines kept loaded
+    pub max_loaded_voices: Option<usize>,
+    /// Memory budget for loaded engines, estimated from model size
+    pub max_memory_mb: Option<u64>,
+    /// Voices that are never evicted
+    pub pinned_voices: Vec<String>,
+    /// Directory for cached synthesis results (unset = memory only)
+    pub audio_dir: Option<PathBuf>,
+    /// Size bound for `audio_dir`
+    pub audio_max_mb: u64,
+    /// Size bound for the in-memory tier of the synthesis cache (0 = off)
+    pub audio_memory_mb: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_loaded_voices: None,
+            max_memory_mb: None,
+            pinned_voices: Vec::new(),
+            audio_dir: None,
+            audio_max_mb: 512,
+            audio_memory_mb: 32,
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn audio_cache(&self) -> AudioCacheConfig {
+        AudioCacheConfig {
+            dir: self.audio_dir.clone(),
+            max_disk_bytes: self.audio_max_mb * 1024 * 1024,
+            max_memory_bytes: self.audio_memory_mb * 1024 * 1024,
+        }
+    }
+
+    pub fn engine_cache(&self) -> EngineCacheConfig {
+        EngineCacheConfig {
+            max_engines: self.max_loaded_voices,
+            max_bytes: self.max_memory_mb.map(|mb| mb * 1024 * 1024),
+            pinned: self.pinned_voices.iter().cloned().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DslConfig {
+    /// Interpret `[tags]`; when false text is spoken as written
+    pub enabled: bool,
+}
+
+impl Default for DslConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Bearer token required by `/api/admin` (unset = admin API open);
+    /// also accepted as an API key with every scope
+    pub admin_token: Option<String>,
+    /// Key for signing `GET /api/speak` URLs; when set, unsigned URLs are refused
+    pub url_signing_key: Option<String>,
+    /// API keys; once any are defined, every API route needs one
+    pub keys: Vec<ApiKey>,
+    /// Paths (e.g. `/api/voices`) reachable without a key; routes below
+    /// them still need one
+    pub public_routes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKey {
+    /// Shown in logs and errors instead of the key
+    pub name: String,
+    /// Sent as `X-API-Key` or `Authorization: Bearer`
+    pub key: String,
+    pub scopes: Vec<Scope>,
+    /// Voice ids or aliases the key may use (unset = any)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voices: Option<Vec<String>>,
+    /// Overrides `limits.key_requests_per_minute` (0 = unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+    /// Characters the key may synthesize per day, reset at local midnight
+    /// (unset = unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_chars: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Synthesis requests per minute for each API key (0 = unlimited)
+    pub key_requests_per_minute: u32,
+    /// Synthesis requests per minute for each client IP, for requests
+    /// without a key (0 = unlimited)
+    pub ip_requests_per_minute: u32,
+    /// Requests accepted back to back before the per-minute rate applies
+    pub burst: u32,
+    /// Take the client IP from the last `X-Forwarded-For` entry, the one
+    /// the proxy added; only behind a single proxy that sets it
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            key_requests_per_minute: 0,
+            ip_requests_per_minute: 0,
+            burst: 10,
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlaybackConfig {
+    /// Expose `/api/speak-aloud` and `/api/playback`
+    pub enabled: bool,
+    /// `device` (needs the `audio-playback` feature) or `null`
+    pub backend: Backend,
+    /// Output device name; the system default when unset
+    pub device: Option<String>,
+    /// Global volume, 0.0 to 1.0
+    pub volume: f32,
+    /// WAV played before announcements that ask for a chime
+    pub chime: Option<PathBuf>,
+    /// Announcements waiting to play before new ones are refused
+    pub max_queued: usize,
+}
+
+impl Default for PlaybackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            backend: Backend::Device,
+            device: None,
+            volume: 1.0,
+            chime: None,
+            max_queued: 100,
+        }
+    }
+}
+
+impl PlaybackConfig {
+    pub fn playback_settings(&self) -> PlaybackSettings {
+        PlaybackSettings {
+            backend: self.backend,
+            max_queued: self.max_queued,
+            volume: self.volume,
+            chime: self.chime.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PromptsConfig {
+    /// Where the prompt registry and rendered WAVs live (unset = disabled)
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchedulesConfig {
+    /// Where the schedule registry lives (unset = disabled)
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemplatesConfig {
+    /// Where the template registry lives (unset = disabled)
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobsConfig {
+    /// Where job manifests, inputs and results live (unset = jobs disabled)
+    pub dir: Option<PathBuf>,
+    /// Jobs synthesized concurrently
+    pub workers: usize,
+    /// Queued jobs before submissions are refused with 503
+    pub max_queued: usize,
+    /// Hours finished jobs are kept
+    pub retention_hours: u64,
+    /// Longest text accepted for a job, in characters
+    pub max_text_length: usize,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            workers: 2,
+            max_queued: 100,
+            retention_hours: 24,
+            max_text_length: 1_000_000,
+        }
+    }
+}
+
+impl JobsConfig {
+    pub fn job_config(&self) -> Option<JobConfig> {
+        self.dir.as_ref().map(|dir| JobConfig {
+            dir: dir.clone(),
+            max_queued: self.max_queued,
+            retention: Duration::from_secs(self.retention_hours * 3600),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BatchConfig {
+    /// Items synthesized concurrently within one batch request
+    pub workers: usize,
+    /// Most items accepted in one batch request
+    pub max_items: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            workers: 4,
+            max_items: 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WyomingConfig {
+    /// TCP port for the Wyoming protocol on `server.host` (unset = disabled)
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
+    /// Broker as `host` or `host:port` (unset = disabled; needs the `mqtt` feature)
+    pub broker: Option<String>,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Prefix for URLs published by `url` topics, e.g. `http://tts.local:3000`
+    pub base_url: Option<String>,
+    /// How long published URLs stay valid when `auth.url_signing_key` is set
+    pub url_ttl_secs: u64,
+    /// Requests handled at once
+    pub workers: usize,
+    /// Requests waiting for a worker before more are turned away
+    pub max_queued: usize,
+    pub topics: Vec<MqttTopic>,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            broker: None,
+            client_id: "piper-tts-server".to_string(),
+            username: None,
+            password: None,
+            base_url: None,
+            url_ttl_secs: 3600,
+            workers: 2,
+            max_queued: 100,
+            topics: Vec::new(),
+        }
+    }
+}
+
+impl MqttConfig {
+    /// Broker host and port (1883 unless given)
+    pub fn broker_addr(&self) -> Option<(String, u16)> {
+        let broker = self.broker.as_deref()?;
+        match broker.rsplit_once(':') {
+            Some((host, port)) => Some((host.to_string(), port.parse().ok()?)),
+            None => Some((broker.to_string(), 1883)),
+        }
+    }
+}
+
+/// A subscription and what to do with each `SpeakRequest` published to it
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MqttTopic {
+    /// Topic filter; `+` and `#` wildcards are allowed
+    pub topic: String,
+    #[serde(default)]
+    pub action: MqttAction,
+    /// Where results are published, and errors to `{reply_topic}/error`;
+    /// required for `audio` and `url`
+    #[serde(default)]
+    pub reply_topic: Option<String>,
+    /// Encoding for `audio` and `url`
+    #[serde(default)]
+    pub format: AudioFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MqttAction {
+    /// Queue for playback on the server's speakers
+    #[default]
+    Play,
+    /// Publish the encoded audio
+    Audio,
+    /// Publish a URL that serves the audio
+    Url,
+}
+
+impl Config {
+    /// Load `path`, or `config.toml` if it exists, or the defaults
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Path::new(DEFAULT_CONFIG_FILE),
+            None => return Ok(Self::default()),
+        };
+
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        toml::from_str(&text).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Check values that serde can't, reporting every problem at once
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if format!("{}:{}", self.server.host, self.server.port)
+            .parse::<SocketAddr>()
+            .is_err()
+        {
+            problems.push(format!(
+                "server.host '{}' is not a valid IP address",
+                self.server.host
+            ));
+        }
+        if self.server.max_text_length == 0 {
+            problems.push("server.max_text_length must be at least 1".to_string());
+        }
+        if self.server.cors_origins.iter().any(|o| o == "*") {
+            if self.server.cors_origins.len() > 1 {
+                problems.push("server.cors_origins can't mix \"*\" with origins".to_string());
+            }
+        } else {
+            for origin in &self.server.cors_origins {
+                if !(origin.starts_with("http://") || origin.starts_with("https://"))
+                    || origin.ends_with('/')
+                {
+                    problems.push(format!(
+                        "server.cors_origins entry '{}' must look like https://host[:port]",
+                        origin
+                    ));
+                }
+            }
+        }
+
+        if !self.engine.voices_dir.is_dir() {
+            problems.push(format!(
+                "engine.voices_dir {} is not a directory",
+                self.engine.voices_dir.display()
+            ));
+        }
+        if self.engine.intra_threads == 0 {
+            problems.push("engine.intra_threads must be at least 1".to_string());
+        }
+        if let Some(catalog) = &self.engine.catalog {
+            if !catalog.is_file() {
+                problems.push(format!(
+                    "engine.catalog {} does not exist",
+                    catalog.display()
+                ));
+            }
+        }
+
+        if self.cache.max_loaded_voices == Some(0) {
+            problems.push("cache.max_loaded_voices must be at least 1".to_string());
+        }
+        if self.cache.max_memory_mb == Some(0) {
+            problems.push("cache.max_memory_mb must be at least 1".to_string());
+        }
+        if self.cache.audio_dir.is_some() && self.cache.audio_max_mb == 0 {
+            problems
+                .push("cache.audio_max_mb must be at least 1 when audio_dir is set".to_string());
+        }
+
+        if self.jobs.workers == 0 {
+            problems.push("jobs.workers must be at least 1".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.playback.volume) {
+            problems.push("playback.volume must be between 0.0 and 1.0".to_string());
+        }
+        if self.playback.max_queued == 0 {
+            problems.push("playback.max_queued must be at least 1".to_string());
+        }
+        if self.jobs.max_queued == 0 {
+            problems.push("jobs.max_queued must be at least 1".to_string());
+        }
+        if self.jobs.max_text_length == 0 {
+            problems.push("jobs.max_text_length must be at least 1".to_string());
+        }
+        if self.wyoming.port == Some(self.server.port) {
+            problems.push("wyoming.port must differ from server.port".to_string());
+        }
+        if self.batch.workers == 0 {
+            problems.push("batch.workers must be at least 1".to_string());
+        }
+        if self.batch.max_items == 0 {
+            problems.push("batch.max_items must be at least 1".to_string());
+        }
+
+        if matches!(&self.auth.admin_token, Some(token) if token.trim().is_empty()) {
+            problems.push("auth.admin_token must not be empty".to_string());
+        }
+        if matches!(&self.auth.url_signing_key, Some(key) if key.len() < 16) {
+            problems.push("auth.url_signing_key must be at least 16 characters".to_string());
+        }
+        for (i, key) in self.auth.keys.iter().enumerate() {
+            if key.name.trim().is_empty() {
+                problems.push("auth.keys: name must not be empty".to_string());
+            } else if self.auth.keys[..i]
+                .iter()
+                .any(|other| other.name == key.name)
+            {
+                problems.push(format!("auth.keys: name '{}' is used twice", key.name));
+            } else if key.name == ADMIN_KEY_NAME {
+                problems.push(format!(
+                    "auth.keys: name '{}' is reserved for auth.admin_token",
+                    ADMIN_KEY_NAME
+                ));
+            }
+            if key.key.len() < 16 {
+                problems.push(format!(
+                    "auth.keys: key for '{}' must be at least 16 characters",
+                    key.name
+                ));
+            } else if self.auth.keys[..i].iter().any(|other| other.key == key.key)
+                || self.auth.admin_token.as_deref() == Some(key.key.as_str())
+            {
+                problems.push(format!("auth.keys: key for '{}' is not unique", key.name));
+            }
+            if key.scopes.is_empty() {
+                problems.push(format!("auth.keys: '{}' has no scopes", key.name));
+            }
+        }
+        if self.limits.burst == 0 {
+            problems.push("limits.burst must be at least 1".to_string());
+        }
+        for route in &self.auth.public_routes {
+            if !route.starts_with('/') {
+                problems.push(format!(
+                    "auth.public_routes: '{}' must start with '/'",
+                    route
+                ));
+            }
+        }
+
+        if self.mqtt.broker.is_some() {
+            if self.mqtt.broker_addr().is_none() {
+                problems.push("mqtt.broker must be host or host:port".to_string());
+            }
+            if self.mqtt.topics.is_empty() {
+                problems.push("mqtt.topics must list at least one topic".to_string());
+            }
+            if self.mqtt.workers == 0 {
+                problems.push("mqtt.workers must be at least 1".to_string());
+            }
+        }
+        for topic in &self.mqtt.topics {
+            if topic.topic.is_empty() {
+                problems.push("mqtt.topics: topic must not be empty".to_string());
+            }
+            match &topic.reply_topic {
+                Some(reply) if reply.contains(['+', '#']) => problems.push(format!(
+                    "mqtt.topics: reply_topic '{}' must not contain wildcards",
+                    reply
+                )),
+                None if topic.action != MqttAction::Play => problems.push(format!(
+                    "mqtt.topics: '{}' needs a reply_topic",
+                    topic.topic
+                )),
+                _ => {}
+            }
+            if topic.action == MqttAction::Url && self.mqtt.base_url.is_none() {
+                problems.push(format!(
+                    "mqtt.topics: '{}' publishes URLs, which needs mqtt.base_url",
+                    topic.topic
+                ));
+            }
+            // Unsigned URLs would be refused once API keys are required
+            if topic.action == MqttAction::Url
+                && !self.auth.keys.is_empty()
+                && self.auth.url_signing_key.is_none()
+            {
+                problems.push(format!(
+                    "mqtt.topics: '{}' publishes URLs, which needs auth.url_signing_key \
+                     when auth.keys are set",
+                    topic.topic
+                ));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    /// Bind address; only meaningful once `validate` has passed
+    pub fn socket_addr(&self) -> SocketAddr {
+        format!("{}:{}", self.server.host, self.server.port)
+            .parse()
+            .expect("Invalid address")
+    }
+
+    pub fn engine_options(&self) -> EngineOptions {
+        EngineOptions {
+            intra_threads: self.engine.intra_threads,
+            dsl: self.dsl.enabled,
+        }
+    }
+
+    /// The effective configuration as TOML, with secrets masked
+    pub fn to_toml(&self) -> String {
+        let mut config = self.clone();
+        for secret in [
+            &mut config.auth.admin_token,
+            &mut config.auth.url_signing_key,
+            &mut config.mqtt.password,
+        ] {
+            if secret.is_some() {
+                *secret = Some("<redacted>".to_string());
+            }
+        }
+        for key in &mut config.auth.keys {
+            key.key = "<redacted>".to_string();
+        }
+        toml::to_string_pretty(&config).expect("config serializes to TOML")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Config {
+        toml::from_str(text).unwrap()
+    }
+
+    fn valid() -> Config {
+        let mut config = Config::default();
+        config.engine.voices_dir = std::env::temp_dir();
+        config
+    }
+
+    #[test]
+    fn missing_sections_use_defaults() {
+        let config = parse("[server]\nport = 8080\n");
+        assert_eq!(config.server.port, 8080);
+        assert_eq!(config.server.host, "0.0.0.0");
+        assert_eq!(config.server.max_text_length, 10000);
+        assert_eq!(config.engine.intra_threads, 4);
+        assert!(config.dsl.enabled);
+    }
+
+    #[test]
+    fn parses_all_sections() {
+        let config = parse(
+            r#"
+            [engine]
+            voices_dir = "/srv/voices"
+            preload = ["all"]
+            watch = false
+
+            [cache]
+            max_loaded_voices = 2
+            max_memory_mb = 512
+            pinned_voices = ["en_GB-alba-medium"]
+            audio_dir = "/var/cache/piper"
+            audio_memory_mb = 0
+
+            [dsl]
+            enabled = false
+
+            [auth]
+            admin_token = "secret"
+
+            [playback]
+            enabled = false
+            backend = "null"
+            device = "PA system"
+            volume = 0.8
+            "#,
+        );
+        assert_eq!(config.engine.voices_dir, PathBuf::from("/srv/voices"));
+        assert_eq!(
+            config.engine.aliases_path(),
+            PathBuf::from("/srv/voices/aliases.json")
+        );
+        assert!(!config.engine.watch);
+
+        let cache = config.cache.engine_cache();
+        assert_eq!(cache.max_engines, Some(2));
+        assert_eq!(cache.max_bytes, Some(512 * 1024 * 1024));
+        assert!(cache.pinned.contains("en_GB-alba-medium"));
+
+        let audio = config.cache.audio_cache();
+        assert_eq!(audio.dir, Some(PathBuf::from("/var/cache/piper")));
+        assert_eq!(audio.max_disk_bytes, 512 * 1024 * 1024);
+        assert_eq!(audio.max_memory_bytes, 0);
+
+        assert!(!config.engine_options().dsl);
+        assert!(!config.playback.enabled);
+        assert_eq!(config.playback.backend, Backend::Null);
+        assert_eq!(config.playback.device.as_deref(), Some("PA system"));
+        assert_eq!(config.playback.playback_settings().volume, 0.8);
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(toml::from_str::<Config>("[server]\nprot = 1\n").is_err());
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let mut config = valid();
+        config.server.host = "not an address".into();
+        config.engine.intra_threads = 0;
+        config.cache.max_loaded_voices = Some(0);
+        config.server.cors_origins = vec!["*".into(), "https://a.example".into()];
+
+        match config.validate() {
+            Err(ConfigError::Invalid(problems)) => assert_eq!(problems.len(), 4),
+            other => panic!("expected invalid config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn accepts_explicit_origins() {
+        let mut config = valid();
+        config.server.cors_origins =
+            vec!["https://a.example".into(), "http://localhost:8080".into()];
+        assert!(config.validate().is_ok());
+
+        config.server.cors_origins = vec!["a.example".into()];
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn redacts_secrets() {
+        let mut config = valid();
+        config.auth.admin_token = Some("secret".into());
+        config.auth.url_signing_key = Some("secret-signing-key".into());
+        config.mqtt.password = Some("secret-password".into());
+        config.auth.keys = vec![ApiKey {
+            name: "kiosk".into(),
+            key: "secret-kiosk-key".into(),
+            scopes: vec![Scope::Synthesize],
+            voices: None,
+            requests_per_minute: None,
+            daily_chars: None,
+        }];
+        let toml = config.to_toml();
+        assert!(!toml.contains("secret"));
+        assert!(toml.contains("<redacted>"));
+    }
+
+    #[test]
+    fn validates_api_keys() {
+        let mut config = valid();
+        config.auth = parse(
+            r#"
+            [auth]
+            public_routes = ["/api/voices"]
+
+            [[auth.keys]]
+            name = "lobby-panel"
+            key = "0123456789abcdef"
+            scopes = ["speak-aloud", "voices:read"]
+            voices = ["announcer"]
+            daily_chars = 50000
+            "#,
+        )
+        .auth;
+        assert_eq!(
+            config.auth.keys[0].scopes,
+            [Scope::SpeakAloud, Scope::VoicesRead]
+        );
+        assert_eq!(config.auth.keys[0].daily_chars, Some(50000));
+        assert!(config.validate().is_ok());
+
+        config.auth.keys.push(ApiKey {
+            name: "lobby-panel".into(),
+            key: "short".into(),
+            scopes: vec![],
+            voices: None,
+            requests_per_minute: None,
+            daily_chars: None,
+        });
+        config.auth.public_routes.push("api/health".into());
+        match config.validate() {
+            Err(ConfigError::Invalid(problems)) => assert_eq!(problems.len(), 4),
+            other => panic!("expected invalid config, got {:?}", other),
+        }
+
+        // The admin token's name can't be shared with a key
+        config.auth.keys[1].name = ADMIN_KEY_NAME.into();
+        config.auth.keys[1].key = "fedcba9876543210".into();
+        config.auth.keys[1].scopes = vec![Scope::Synthesize];
+        config.auth.public_routes.pop();
+        match config.validate() {
+            Err(ConfigError::Invalid(problems)) => {
+                assert_eq!(problems.len(), 1);
+                assert!(problems[0].contains("reserved"), "{}", problems[0]);
+            }
+            other => panic!("expected invalid config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validates_mqtt_topics() {
+        let mut config = valid();
+        config.mqtt = parse(
+            r#"
+            [mqtt]
+            broker = "mosquitto.local"
+
+            [[mqtt.topics]]
+            topic = "building/+/announce"
+
+            [[mqtt.topics]]
+            topic = "tts/say"
+            action = "audio"
+            reply_topic = "tts/audio"
+            format = "pcm"
+            "#,
+        )
+        .mqtt;
+        assert_eq!(
+            config.mqtt.broker_addr(),
+            Some(("mosquitto.local".into(), 1883))
+        );
+        assert_eq!(config.mqtt.topics[1].format, AudioFormat::Pcm);
+        assert!(config.validate().is_ok());
+
+        config.mqtt.topics[1].reply_topic = None;
+        config.mqtt.topics[0].action = MqttAction::Url;
+        config.mqtt.broker = Some("mosquitto.local:port".into());
+        match config.validate() {
+            Err(ConfigError::Invalid(problems)) => assert_eq!(problems.len(), 4),
+            other => panic!("expected invalid config, got {:?}", other),
+        }
+
+        // URL topics need signed URLs once keys are required
+        config.mqtt.broker = Some("mosquitto.local".into());
+        config.mqtt.base_url = Some("http://tts.local:3000".into());
+        config.mqtt.topics[0].reply_topic = Some("speaker/play".into());
+        config.mqtt.topics[1].reply_topic = Some("tts/audio".into());
+        assert!(config.validate().is_ok());
+        config.auth.keys.push(ApiKey {
+            name: "panel".into(),
+            key: "panel-key-0123456789".into(),
+            scopes: vec![Scope::Synthesize],
+            voices: None,
+            requests_per_minute: None,
+            daily_chars: None,
+        });
+        match config.validate() {
+            Err(ConfigError::Invalid(problems)) => {
+                assert_eq!(problems.len(), 1);
+                assert!(problems[0].contains("url_signing_key"), "{}", problems[0]);
+            }
+            other => panic!("expected invalid config, got {:?}", other),
+        }
+        config.auth.url_signing_key = Some("mqtt-signing-secret".into());
+        assert!(config.validate().is_ok());
+    }
+}
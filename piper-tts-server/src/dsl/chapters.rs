@@ -0,0 +1,229 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref ATX_HEADING: Regex = Regex::new(r"^#{1,6}\s+(.*?)(?:\s+#+)?\s*$").unwrap();
+    static ref SETEXT_UNDERLINE: Regex = Regex::new(r"^=+\s*$").unwrap();
+    /// `Chapter 3`, `Part II: Winter`, `Book Twenty-One` — the keyword
+    /// needs a number after it, and a title after that mustn't end like a
+    /// sentence, so "Part of me wanted to go." stays narration
+    static ref PLAIN_HEADING: Regex = Regex::new(
+        r"(?ix)^(?:
+            (?:chapter|part|book)\s+
+            (?:
+                \d+
+                | [ivxlcdm]+
+                | one|two|three|four|five|six|seven|eight|nine|ten|eleven|twelve
+                | thirteen|fourteen|fifteen|sixteen|seventeen|eighteen|nineteen
+                | (?:twenty|thirty|forty|fifty|sixty|seventy|eighty|ninety)(?:-[a-z]+)?
+                | hundred
+            )
+            (?:[.:]|\s*[.:\-–—]?\s+.{0,59}[^.,;])?
+            | (?:prologue|epilogue|preface|introduction|afterword)\b.{0,60}
+        )$"
+    )
+    .unwrap();
+    static ref RULE: Regex = Regex::new(r"^(?:(?:\*\s*){3,}|(?:-\s*){3,}|(?:_\s*){3,})$").unwrap();
+    static ref IMAGE: Regex = Regex::new(r"!\[[^\]]*\]\([^)]*\)").unwrap();
+    static ref LINK: Regex = Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap();
+    static ref LINE_MARKER: Regex = Regex::new(r"^(?:>\s*)*(?:(?:[-*+]|\d+[.)])\s+)?").unwrap();
+    static ref EMPHASIS: Regex = Regex::new(r"\*{1,3}|_{2,3}|`+").unwrap();
+}
+
+/// A chapter of a long document: an optional heading and its paragraphs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chapter {
+    pub title: Option<String>,
+    pub paragraphs: Vec<String>,
+}
+
+impl Chapter {
+    fn is_empty(&self) -> bool {
+        self.title.is_none() && self.paragraphs.is_empty()
+    }
+}
+
+/// Split Markdown or plain text into chapters.
+///
+/// Markdown headings (`# Title` or a line underlined with `===`) start a
+/// new chapter, as do stand-alone plain-text lines such as `Chapter 3` or
+/// `Epilogue`. Text before the first heading forms an untitled chapter.
+/// Paragraphs are separated by blank lines; emphasis, links, list markers
+/// and images are stripped so they aren't read aloud.
+pub fn parse(input: &str) -> Vec<Chapter> {
+    let mut chapters = Vec::new();
+    let mut current = Chapter::default();
+    let mut paragraph: Vec<String> = Vec::new();
+
+    let mut start_chapter = |current: &mut Chapter, title: String| {
+        let previous = std::mem::replace(
+            current,
+            Chapter {
+                title: Some(title),
+                paragraphs: Vec::new(),
+            },
+        );
+        if !previous.is_empty() {
+            chapters.push(previous);
+        }
+    };
+
+    for block in paragraphs(input) {
+        let lines: Vec<&str> = block
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect();
+
+        if let [line] = lines.as_slice() {
+            if !line.starts_with('#') && PLAIN_HEADING.is_match(line) {
+                start_chapter(&mut current, clean(line));
+                continue;
+            }
+        }
+
+        for line in lines {
+            if let Some(cap) = ATX_HEADING.captures(line) {
+                flush(&mut current, &mut paragraph);
+                let title = clean(&cap[1]);
+                if !title.is_empty() {
+                    start_chapter(&mut current, title);
+                }
+            } else if SETEXT_UNDERLINE.is_match(line) && paragraph.len() == 1 {
+                let title = paragraph.pop().unwrap();
+                start_chapter(&mut current, title);
+            } else if RULE.is_match(line) {
+                flush(&mut current, &mut paragraph);
+            } else {
+                let line = clean(line);
+                if !line.is_empty() {
+                    paragraph.push(line);
+                }
+            }
+        }
+        flush(&mut current, &mut paragraph);
+    }
+
+    if !current.is_empty() {
+        chapters.push(current);
+    }
+    chapters
+}
+
+/// Blocks of text separated by blank (or whitespace-only) lines
+fn paragraphs(input: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            parts.push(&input[start..offset]);
+            start = offset + line.len();
+        }
+        offset += line.len();
+    }
+    parts.push(&input[start..]);
+    parts
+}
+
+fn flush(chapter: &mut Chapter, paragraph: &mut Vec<String>) {
+    if !paragraph.is_empty() {
+        chapter.paragraphs.push(paragraph.join(" "));
+        paragraph.clear();
+    }
+}
+
+/// Strip Markdown syntax that shouldn't be spoken
+fn clean(line: &str) -> String {
+    let line = LINE_MARKER.replace(line, "");
+    let line = IMAGE.replace_all(&line, "");
+    let line = LINK.replace_all(&line, "$1");
+    let line = EMPHASIS.replace_all(&line, "");
+    line.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_markdown_headings() {
+        let chapters = parse(
+            "Front matter.\n\n# Chapter One\n\nIt was a **dark** night.\nVery dark.\n\n\
+             The end.\n\n## Two ##\nShort.",
+        );
+        assert_eq!(
+            chapters,
+            vec![
+                Chapter {
+                    title: None,
+                    paragraphs: vec!["Front matter.".into()],
+                },
+                Chapter {
+                    title: Some("Chapter One".into()),
+                    paragraphs: vec!["It was a dark night. Very dark.".into(), "The end.".into()],
+                },
+                Chapter {
+                    title: Some("Two".into()),
+                    paragraphs: vec!["Short.".into()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn recognizes_plain_text_and_setext_headings() {
+        let chapters =
+            parse("Prologue\n\nOnce.\n\nChapter 2\n\nTwice.\n  \nAgain.\n\nFinale\n======\nDone.");
+        let titles: Vec<_> = chapters.iter().map(|c| c.title.as_deref()).collect();
+        assert_eq!(
+            titles,
+            vec![Some("Prologue"), Some("Chapter 2"), Some("Finale")]
+        );
+        assert_eq!(chapters[1].paragraphs, vec!["Twice.", "Again."]);
+    }
+
+    #[test]
+    fn ignores_heading_words_in_running_text() {
+        let chapters = parse(
+            "Chapter two of the saga begins here and goes on for quite a while \
+             longer than any heading would.",
+        );
+        assert_eq!(chapters.len(), 1);
+        assert!(chapters[0].title.is_none());
+
+        for line in [
+            "Part of me wanted to go.",
+            "Book it now.",
+            "Part two of the plan was simple.",
+            "Chapter and verse",
+        ] {
+            assert!(!PLAIN_HEADING.is_match(line), "{}", line);
+        }
+    }
+
+    #[test]
+    fn recognizes_numbered_headings() {
+        for line in [
+            "Chapter 12",
+            "Chapter II: The Storm",
+            "Part Three",
+            "Book Twenty-One",
+            "Book 4 — Winter",
+            "Chapter One.",
+        ] {
+            assert!(PLAIN_HEADING.is_match(line), "{}", line);
+        }
+    }
+
+    #[test]
+    fn strips_markdown_syntax() {
+        let chapters = parse(
+            "- a [link](http://x) and `code`\n> quoted ![img](a.png)\n\n---\n\n1. [pause] kept",
+        );
+        assert_eq!(
+            chapters[0].paragraphs,
+            vec!["a link and code quoted", "[pause] kept"]
+        );
+    }
+}
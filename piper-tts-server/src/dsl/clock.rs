@@ -0,0 +1,345 @@
+//! `[time]` and `[date]` tags, spoken the way people say them in the
+//! voice's language: "twenty-five to three", "Sunday the eighteenth of
+//! October". Languages without phrasing rules get digits, which espeak
+//! reads in the voice's language.
+
+use std::borrow::Cow;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref CLOCK_TAG: Regex = Regex::new(r"\[(time|date)\]").unwrap();
+}
+
+const ENGLISH_ONES: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+
+const ENGLISH_TENS: [&str; 6] = ["", "", "twenty", "thirty", "forty", "fifty"];
+
+const ENGLISH_ORDINALS: [&str; 20] = [
+    "zeroth",
+    "first",
+    "second",
+    "third",
+    "fourth",
+    "fifth",
+    "sixth",
+    "seventh",
+    "eighth",
+    "ninth",
+    "tenth",
+    "eleventh",
+    "twelfth",
+    "thirteenth",
+    "fourteenth",
+    "fifteenth",
+    "sixteenth",
+    "seventeenth",
+    "eighteenth",
+    "nineteenth",
+];
+
+const ENGLISH_WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+const ENGLISH_MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const GERMAN_ONES: [&str; 20] = [
+    "null",
+    "eins",
+    "zwei",
+    "drei",
+    "vier",
+    "fünf",
+    "sechs",
+    "sieben",
+    "acht",
+    "neun",
+    "zehn",
+    "elf",
+    "zwölf",
+    "dreizehn",
+    "vierzehn",
+    "fünfzehn",
+    "sechzehn",
+    "siebzehn",
+    "achtzehn",
+    "neunzehn",
+];
+
+const GERMAN_WEEKDAYS: [&str; 7] = [
+    "Montag",
+    "Dienstag",
+    "Mittwoch",
+    "Donnerstag",
+    "Freitag",
+    "Samstag",
+    "Sonntag",
+];
+
+const GERMAN_MONTHS: [&str; 12] = [
+    "Januar",
+    "Februar",
+    "März",
+    "April",
+    "Mai",
+    "Juni",
+    "Juli",
+    "August",
+    "September",
+    "Oktober",
+    "November",
+    "Dezember",
+];
+
+pub fn has_tags(text: &str) -> bool {
+    CLOCK_TAG.is_match(text)
+}
+
+/// Replace `[time]` and `[date]` with `now`, phrased for `language`
+/// (`en_GB`, `de_DE`, or an espeak voice such as `en-us`)
+pub fn expand<'a>(text: &'a str, language: &str, now: NaiveDateTime) -> Cow<'a, str> {
+    CLOCK_TAG.replace_all(text, |caps: &regex::Captures| match &caps[1] {
+        "time" => time_phrase(language, now.hour(), now.minute()),
+        _ => date_phrase(language, now.date()),
+    })
+}
+
+fn split_language(language: &str) -> (String, String) {
+    let mut parts = language.split(['_', '-']);
+    let family = parts.next().unwrap_or_default().to_ascii_lowercase();
+    let region = parts.next().unwrap_or_default().to_ascii_uppercase();
+    (family, region)
+}
+
+pub fn time_phrase(language: &str, hour: u32, minute: u32) -> String {
+    match split_language(language).0.as_str() {
+        "en" => english_time(hour, minute),
+        "de" => german_time(hour, minute),
+        _ => format!("{}:{:02}", hour, minute),
+    }
+}
+
+pub fn date_phrase(language: &str, date: NaiveDate) -> String {
+    let weekday = date.weekday().num_days_from_monday() as usize;
+    let month = date.month0() as usize;
+    match split_language(language) {
+        (family, region) if family == "en" && region == "US" => format!(
+            "{}, {} {}",
+            ENGLISH_WEEKDAYS[weekday],
+            ENGLISH_MONTHS[month],
+            english_ordinal(date.day())
+        ),
+        (family, _) if family == "en" => format!(
+            "{} the {} of {}",
+            ENGLISH_WEEKDAYS[weekday],
+            english_ordinal(date.day()),
+            ENGLISH_MONTHS[month]
+        ),
+        // espeak reads "18." as an ordinal in German
+        (family, _) if family == "de" => format!(
+            "{}, der {}. {}",
+            GERMAN_WEEKDAYS[weekday],
+            date.day(),
+            GERMAN_MONTHS[month]
+        ),
+        _ => date.format("%Y-%m-%d").to_string(),
+    }
+}
+
+/// 1-12 on a twelve-hour clock
+fn clock_hour(hour: u32) -> u32 {
+    match hour % 12 {
+        0 => 12,
+        h => h,
+    }
+}
+
+fn english_number(n: u32) -> String {
+    match n {
+        0..=19 => ENGLISH_ONES[n as usize].to_string(),
+        _ if n.is_multiple_of(10) => ENGLISH_TENS[(n / 10) as usize].to_string(),
+        _ => format!(
+            "{}-{}",
+            ENGLISH_TENS[(n / 10) as usize],
+            ENGLISH_ONES[(n % 10) as usize]
+        ),
+    }
+}
+
+fn english_ordinal(n: u32) -> String {
+    match n {
+        0..=19 => ENGLISH_ORDINALS[n as usize].to_string(),
+        _ if n.is_multiple_of(10) => format!(
+            "{}ieth",
+            ENGLISH_TENS[(n / 10) as usize].trim_end_matches('y')
+        ),
+        _ => format!(
+            "{}-{}",
+            ENGLISH_TENS[(n / 10) as usize],
+            ENGLISH_ORDINALS[(n % 10) as usize]
+        ),
+    }
+}
+
+fn english_time(hour: u32, minute: u32) -> String {
+    let this_hour = english_number(clock_hour(hour));
+    let next_hour = english_number(clock_hour(hour + 1));
+    let minutes = |n: u32| match n {
+        1 => "one minute".to_string(),
+        n => english_number(n),
+    };
+    match minute {
+        0 if hour == 0 => "midnight".to_string(),
+        0 if hour == 12 => "midday".to_string(),
+        0 => format!("{} o'clock", this_hour),
+        15 => format!("quarter past {}", this_hour),
+        30 => format!("half past {}", this_hour),
+        45 => format!("quarter to {}", next_hour),
+        1..=29 => format!("{} past {}", minutes(minute), this_hour),
+        _ => format!("{} to {}", minutes(60 - minute), next_hour),
+    }
+}
+
+fn german_number(n: u32) -> String {
+    match n {
+        0..=19 => GERMAN_ONES[n as usize].to_string(),
+        20 => "zwanzig".to_string(),
+        // "einundzwanzig", not "einsundzwanzig"
+        _ => format!(
+            "{}undzwanzig",
+            GERMAN_ONES[(n - 20) as usize].trim_end_matches('s')
+        ),
+    }
+}
+
+fn german_time(hour: u32, minute: u32) -> String {
+    let this_hour = german_number(clock_hour(hour));
+    let next_hour = german_number(clock_hour(hour + 1));
+    let minutes = |n: u32| match n {
+        1 => "eine Minute".to_string(),
+        n => german_number(n),
+    };
+    match minute {
+        0 if hour == 0 => "Mitternacht".to_string(),
+        0 if clock_hour(hour) == 1 => "ein Uhr".to_string(),
+        0 => format!("{} Uhr", this_hour),
+        15 => format!("Viertel nach {}", this_hour),
+        30 => format!("halb {}", next_hour),
+        45 => format!("Viertel vor {}", next_hour),
+        1..=24 => format!("{} nach {}", minutes(minute), this_hour),
+        25..=29 => format!("{} vor halb {}", minutes(30 - minute), next_hour),
+        31..=35 => format!("{} nach halb {}", minutes(minute - 30), next_hour),
+        _ => format!("{} vor {}", minutes(60 - minute), next_hour),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn speaks_british_time() {
+        let cases = [
+            ((14, 35), "twenty-five to three"),
+            ((14, 0), "two o'clock"),
+            ((0, 0), "midnight"),
+            ((12, 0), "midday"),
+            ((9, 1), "one minute past nine"),
+            ((9, 15), "quarter past nine"),
+            ((9, 22), "twenty-two past nine"),
+            ((9, 30), "half past nine"),
+            ((23, 45), "quarter to twelve"),
+            ((11, 59), "one minute to twelve"),
+        ];
+        for ((hour, minute), expected) in cases {
+            assert_eq!(time_phrase("en_GB", hour, minute), expected);
+        }
+    }
+
+    #[test]
+    fn speaks_german_time() {
+        assert_eq!(time_phrase("de_DE", 14, 30), "halb drei");
+        assert_eq!(time_phrase("de_DE", 14, 25), "fünf vor halb drei");
+        assert_eq!(time_phrase("de_DE", 14, 40), "zwanzig vor drei");
+        assert_eq!(time_phrase("de", 13, 0), "ein Uhr");
+        assert_eq!(time_phrase("de", 12, 21), "einundzwanzig nach zwölf");
+    }
+
+    #[test]
+    fn speaks_dates_by_region() {
+        let date = at(9, 0).date();
+        assert_eq!(
+            date_phrase("en_GB", date),
+            "Sunday the eighteenth of October"
+        );
+        assert_eq!(date_phrase("en-us", date), "Sunday, October eighteenth");
+        assert_eq!(date_phrase("de_DE", date), "Sonntag, der 18. Oktober");
+        assert_eq!(date_phrase("fr_FR", date), "2026-10-18");
+        assert_eq!(english_ordinal(30), "thirtieth");
+        assert_eq!(english_ordinal(21), "twenty-first");
+    }
+
+    #[test]
+    fn expands_tags() {
+        assert_eq!(
+            expand("The time is [time] on [date].", "en_GB", at(14, 35)),
+            "The time is twenty-five to three on Sunday the eighteenth of October."
+        );
+        assert_eq!(expand("Il est [time]", "fr_FR", at(9, 5)), "Il est 9:05");
+        assert!(matches!(
+            expand("No tags", "en_GB", at(9, 5)),
+            Cow::Borrowed(_)
+        ));
+        assert!(has_tags("[date]") && !has_tags("[pause]"));
+    }
+}
@@ -1,16 +1,38 @@
+pub mod chapters;
+pub mod clock;
 pub mod parser;
+pub mod sentences;
 pub mod transforms;
 
+use std::borrow::Cow;
+
 /// Process DSL text into Piper-friendly plain text
 pub fn process(input: &str) -> String {
     let tokens = parser::parse(input);
     transforms::transform(tokens)
 }
 
+/// Make arbitrary text safe to splice into DSL: square brackets become
+/// parentheses, so nothing in it is read as a tag
+pub fn escape(text: &str) -> Cow<'_, str> {
+    if text.contains(['[', ']']) {
+        Cow::Owned(text.replace('[', "(").replace(']', ")"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_escape() {
+        let escaped = escape("Gate [pause] 4 [emphasis]now");
+        assert_eq!(process(&escaped), "Gate (pause) 4 (emphasis)now");
+        assert!(matches!(escape("Platform 4"), Cow::Borrowed(_)));
+    }
+
     #[test]
     fn test_simple_pause() {
         assert_eq!(process("Hello [pause] world"), "Hello ... world");
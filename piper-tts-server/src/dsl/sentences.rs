@@ -0,0 +1,104 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref SPAN_TAG_REGEX: Regex =
+        Regex::new(r"\[(/?)(slow|fast|emphasis|spell|whisper)\]").unwrap();
+}
+
+/// Split DSL text into sentences that can be synthesized independently.
+///
+/// Breaks after `.`, `!` or `?` followed by whitespace, and at blank lines,
+/// but never inside an open span tag such as `[slow]...[/slow]`.
+pub fn split(input: &str) -> Vec<String> {
+    // Byte ranges covered by span tags, and the nesting depth after each
+    let mut depth_changes: Vec<(usize, usize, i32)> = SPAN_TAG_REGEX
+        .captures_iter(input)
+        .map(|cap| {
+            let m = cap.get(0).unwrap();
+            let delta = if &cap[1] == "/" { -1 } else { 1 };
+            (m.start(), m.end(), delta)
+        })
+        .collect();
+    depth_changes.reverse();
+
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut depth = 0i32;
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        while let Some(&(tag_start, _, delta)) = depth_changes.last() {
+            if tag_start > i {
+                break;
+            }
+            depth = (depth + delta).max(0);
+            depth_changes.pop();
+        }
+
+        let next = chars.peek().map(|&(_, next)| next);
+        let boundary = match c {
+            '.' | '!' | '?' => next.is_none_or(char::is_whitespace),
+            '\n' => next == Some('\n'),
+            _ => false,
+        };
+
+        if boundary && depth == 0 {
+            let end = i + c.len_utf8();
+            push_sentence(&mut sentences, &input[start..end]);
+            start = end;
+        }
+    }
+    push_sentence(&mut sentences, &input[start..]);
+
+    sentences
+}
+
+fn push_sentence(sentences: &mut Vec<String>, text: &str) {
+    let text = text.trim();
+    if !text.is_empty() {
+        sentences.push(text.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_sentence_punctuation() {
+        assert_eq!(
+            split("Hello there. How are you? Fine!"),
+            vec!["Hello there.", "How are you?", "Fine!"]
+        );
+    }
+
+    #[test]
+    fn keeps_decimals_and_trailing_text() {
+        assert_eq!(
+            split("It costs 3.50 today. No trailing stop"),
+            vec!["It costs 3.50 today.", "No trailing stop"]
+        );
+    }
+
+    #[test]
+    fn splits_paragraphs() {
+        assert_eq!(
+            split("Chapter one\n\nIt was a dark night"),
+            vec!["Chapter one", "It was a dark night"]
+        );
+    }
+
+    #[test]
+    fn never_splits_inside_span_tags() {
+        assert_eq!(
+            split("[slow]One. Two.[/slow] Three. [pause] Four."),
+            vec!["[slow]One. Two.[/slow] Three.", "[pause] Four."]
+        );
+    }
+
+    #[test]
+    fn ignores_blank_input() {
+        assert!(split("  \n\n ").is_empty());
+    }
+}
@@ -69,10 +69,7 @@ fn apply_state(text: &str, state: &TransformState) -> String {
 
     // Apply slow (insert ellipses between words)
     if state.slow {
-        result = result
-            .split_whitespace()
-            .collect::<Vec<_>>()
-            .join("... ");
+        result = result.split_whitespace().collect::<Vec<_>>().join("... ");
         if !result.is_empty() {
             result.push_str("...");
         }
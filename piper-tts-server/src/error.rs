@@ -1,5 +1,5 @@
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -10,9 +10,30 @@ pub enum AppError {
     #[error("Voice not found: {0}")]
     VoiceNotFound(String),
 
+    #[error("{0}")]
+    NotFound(String),
+
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    #[error("{0}")]
+    Unauthorized(String),
+
+    #[error("{0}")]
+    Forbidden(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("Server busy: {0}")]
+    Busy(String),
+
+    #[error("{message}")]
+    RateLimited {
+        message: String,
+        retry_after_secs: u64,
+    },
+
     #[error("TTS generation failed: {0}")]
     TtsError(String),
 
@@ -33,42 +54,69 @@ pub struct ErrorResponse {
     pub code: String,
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, code, message) = match &self {
+impl AppError {
+    /// HTTP status, machine-readable code and client-facing message
+    pub fn parts(&self) -> (StatusCode, &'static str, String) {
+        match self {
             AppError::VoiceNotFound(v) => (
                 StatusCode::NOT_FOUND,
                 "VOICE_NOT_FOUND",
                 format!("Voice '{}' not found", v),
             ),
+            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, "NOT_FOUND", msg.clone()),
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "BAD_REQUEST", msg.clone()),
-            AppError::TtsError(msg) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "TTS_ERROR",
-                msg.clone(),
-            ),
-            AppError::IoError(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "IO_ERROR",
-                e.to_string(),
+            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, "UNAUTHORIZED", msg.clone()),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, "FORBIDDEN", msg.clone()),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, "CONFLICT", msg.clone()),
+            AppError::Busy(msg) => (StatusCode::SERVICE_UNAVAILABLE, "BUSY", msg.clone()),
+            AppError::RateLimited { message, .. } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "RATE_LIMITED",
+                message.clone(),
             ),
+            AppError::TtsError(msg) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "TTS_ERROR", msg.clone())
+            }
+            AppError::IoError(e) => (StatusCode::INTERNAL_SERVER_ERROR, "IO_ERROR", e.to_string()),
             AppError::DslError(msg) => (StatusCode::BAD_REQUEST, "DSL_ERROR", msg.clone()),
             AppError::JsonError(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "JSON_ERROR",
                 e.to_string(),
             ),
-        };
+        }
+    }
+
+    /// `Retry-After` seconds for errors a client should retry later
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            AppError::RateLimited {
+                retry_after_secs, ..
+            } => Some(*retry_after_secs),
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, code, message) = self.parts();
 
         tracing::error!("Request failed: {} - {}", code, message);
 
-        (
+        let mut response = (
             status,
             Json(ErrorResponse {
                 error: message,
                 code: code.to_string(),
             }),
         )
-            .into_response()
+            .into_response();
+        if let Some(secs) = self.retry_after() {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, secs.into());
+        }
+        response
     }
 }
@@ -1,4 +1,8 @@
 pub mod api;
+pub mod config;
 pub mod dsl;
 pub mod error;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
 pub mod tts;
+pub mod wyoming;
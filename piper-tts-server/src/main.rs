@@ -1,57 +1,661 @@
-use std::net::SocketAddr;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
+use clap::Parser;
+use tokio::sync::broadcast;
 use tracing_subscriber::EnvFilter;
 
 mod api;
+mod cli;
+mod config;
 mod dsl;
 mod error;
+#[cfg(feature = "mqtt")]
+mod mqtt;
 mod tts;
+mod wyoming;
 
 use api::routes::{create_router, AppState};
-use tts::TtsService;
+use cli::{Cli, Command};
+use config::Config;
+use error::AppError;
+#[cfg(feature = "audio-playback")]
+use tts::playback::DeviceOutput;
+use tts::playback::{Backend, NullOutput};
+use tts::{
+    AudioCache, AudioFormat, Catalog, Check, EngineCacheConfig, JobQueue, PlaybackQueue,
+    PromptLibrary, Scheduler, TemplateLibrary, TtsService, VoiceAliases, VoiceWatcher,
+};
 
 #[tokio::main]
-async fn main() {
-    // Initialize logging
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    // Initialize logging; stderr keeps stdout free for `synth` audio
     tracing_subscriber::fmt()
         .with_env_filter(
-            EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| EnvFilter::new("info")),
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
         )
+        .with_writer(std::io::stderr)
         .init();
 
-    // Configuration from environment
-    let host = std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port: u16 = std::env::var("PORT")
-        .unwrap_or_else(|_| "3000".to_string())
-        .parse()
-        .expect("PORT must be a number");
-    let voices_dir = std::env::var("VOICES_DIR").unwrap_or_else(|_| "./voices".to_string());
+    let mut config = match Config::load(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let command = cli.command.unwrap_or(Command::Serve(Box::new(cli.serve)));
+    command.apply(&mut config);
+    match &command {
+        Command::Serve(args) if args.print_config => print!("{}", config.to_toml()),
+        // Installing creates the voices directory
+        Command::InstallVoice { list: false, .. } => {
+            let _ = std::fs::create_dir_all(&config.engine.voices_dir);
+        }
+        _ => {}
+    }
+    // Every command shares the server's checks on the effective config
+    if let Err(e) = config.validate() {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
 
-    // Start server
-    let addr: SocketAddr = format!("{}:{}", host, port)
-        .parse()
-        .expect("Invalid address");
+    match command {
+        Command::Serve(args) => {
+            if !args.print_config {
+                serve(config).await;
+            }
+            ExitCode::SUCCESS
+        }
+        Command::Synth {
+            voice,
+            input,
+            output,
+            format,
+            ..
+        } => synth(
+            &config,
+            voice.as_deref(),
+            input.as_deref(),
+            output.as_deref(),
+            format,
+        ),
+        Command::Voices { language, json, .. } => list_voices(&config, language.as_deref(), json),
+        Command::InstallVoice { list, voices, .. } => {
+            let Some(catalog) = &config.engine.catalog else {
+                eprintln!("No catalog given (use --catalog, VOICE_CATALOG or engine.catalog)");
+                return ExitCode::FAILURE;
+            };
+            install_voices(catalog, &config.engine.voices_dir, list, &voices)
+        }
+        Command::ValidateVoices { voices, .. } => validate_voices(&config, &voices),
+    }
+}
+
+async fn serve(config: Config) {
+    let addr = config.socket_addr();
 
     tracing::info!("Piper TTS Server v{}", env!("CARGO_PKG_VERSION"));
     tracing::info!("Starting server on http://{}", addr);
-    tracing::info!("Voices directory: {}", voices_dir);
+    tracing::info!("Voices directory: {}", config.engine.voices_dir.display());
+
+    // Stable voice names; defaults to aliases.json in the voices directory
+    let aliases = load_aliases(&config.engine.aliases_path()).unwrap_or_else(|e| panic!("{}", e));
 
     // Create TTS service
-    let tts = TtsService::new(voices_dir.into());
+    let tts = TtsService::new(
+        config.engine.voices_dir.clone(),
+        config.cache.engine_cache(),
+        aliases,
+        config.engine_options(),
+    )
+    .with_audio_cache(AudioCache::new(config.cache.audio_cache()));
+
+    // Voices to load and warm up before reporting ready
+    let preload = config.engine.preload.clone();
+    let watch_voices = config.engine.watch;
+
+    // Named prompts, rendered in the background
+    let prompts = config.prompts.dir.as_ref().map(|dir| {
+        PromptLibrary::open(dir)
+            .unwrap_or_else(|e| panic!("Failed to open prompt library {}: {}", dir.display(), e))
+    });
+
+    // Long documents, synthesized by a pool of workers
+    let jobs = config.jobs.job_config().map(|job_config| {
+        let dir = job_config.dir.clone();
+        JobQueue::open(job_config)
+            .unwrap_or_else(|e| panic!("Failed to open job queue {}: {}", dir.display(), e))
+    });
+    let job_workers = config.jobs.workers;
+
+    // Timed announcements, spoken through the playback queue
+    let schedules = config.schedules.dir.as_ref().map(|dir| {
+        Scheduler::open(dir)
+            .unwrap_or_else(|e| panic!("Failed to open schedules {}: {}", dir.display(), e))
+    });
+
+    // Announcement templates with variables
+    let templates = config.templates.dir.as_ref().map(|dir| {
+        TemplateLibrary::open(dir)
+            .unwrap_or_else(|e| panic!("Failed to open templates {}: {}", dir.display(), e))
+    });
+
+    let playback = start_playback(&config);
 
     // Create app state
-    let state = Arc::new(AppState { tts });
+    let state = Arc::new(AppState::new(
+        tts, config, prompts, jobs, schedules, templates, playback,
+    ));
+
+    if state.prompts.is_some() {
+        start_prompt_renderer(&state);
+    }
+
+    if state.schedules.is_some() {
+        if state.playback.is_some() {
+            let scheduler_state = Arc::clone(&state);
+            std::thread::spawn(move || {
+                if let (Some(schedules), Some(playback)) =
+                    (&scheduler_state.schedules, &scheduler_state.playback)
+                {
+                    schedules.run(&scheduler_state.tts, playback);
+                }
+            });
+        } else {
+            tracing::warn!("Schedules won't be announced: server-side playback is disabled");
+        }
+    }
+
+    if state.jobs.is_some() {
+        for _ in 0..job_workers {
+            let worker_state = Arc::clone(&state);
+            std::thread::spawn(move || {
+                if let Some(jobs) = &worker_state.jobs {
+                    jobs.run_worker(&worker_state.tts);
+                }
+            });
+        }
+    }
+
+    // Keep the watcher alive for the lifetime of the server
+    let _voice_watcher = if watch_voices {
+        let watcher_state = Arc::clone(&state);
+        match VoiceWatcher::start(state.tts.voices_dir(), move |voice_id, kind| {
+            watcher_state.tts.apply_voice_change(voice_id, kind);
+        }) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                tracing::warn!("Voice hot-reload disabled: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    if let Some(port) = state.config.wyoming.port {
+        let wyoming_ip = wyoming::listen_ip(&state.config.auth, addr.ip());
+        if wyoming_ip != addr.ip() {
+            tracing::warn!(
+                "API keys are set, so the Wyoming server only listens on {}",
+                wyoming_ip
+            );
+        }
+        let wyoming_addr = std::net::SocketAddr::new(wyoming_ip, port);
+        let listener = tokio::net::TcpListener::bind(wyoming_addr)
+            .await
+            .expect("Failed to bind Wyoming address");
+        let wyoming_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            if let Err(e) = wyoming::serve(listener, wyoming_state).await {
+                tracing::error!("Wyoming server stopped: {}", e);
+            }
+        });
+    }
+
+    if state.config.mqtt.broker.is_some() {
+        start_mqtt(&state);
+    }
 
     // Create router
-    let app = create_router(state);
+    let app = create_router(Arc::clone(&state));
 
     let listener = tokio::net::TcpListener::bind(addr)
         .await
         .expect("Failed to bind to address");
 
-    axum::serve(listener, app)
-        .await
-        .expect("Server error");
+    // Warm up voices in the background; /api/ready reports 503 until done
+    let preload_state = Arc::clone(&state);
+    tokio::task::spawn_blocking(move || preload_voices(&preload_state, &preload));
+
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await
+    .expect("Server error");
+}
+
+/// Render queued prompts, and re-check them at startup and whenever a
+/// voice changes so stale audio gets re-rendered
+fn start_prompt_renderer(state: &Arc<AppState>) {
+    let renderer_state = Arc::clone(state);
+    std::thread::spawn(move || {
+        if let Some(prompts) = &renderer_state.prompts {
+            prompts.run(&renderer_state.tts);
+        }
+    });
+
+    let refresh_state = Arc::clone(state);
+    let mut voice_events = state.tts.subscribe_voice_events();
+    std::thread::spawn(move || {
+        let Some(prompts) = &refresh_state.prompts else {
+            return;
+        };
+        prompts.refresh(&refresh_state.tts);
+        // A lagged receiver still means something changed
+        while let Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) = voice_events.blocking_recv()
+        {
+            prompts.refresh(&refresh_state.tts);
+        }
+    });
+}
+
+#[cfg(feature = "mqtt")]
+fn start_mqtt(state: &Arc<AppState>) {
+    tokio::spawn(mqtt::run(state.config.mqtt.clone(), Arc::clone(state)));
+}
+
+#[cfg(not(feature = "mqtt"))]
+fn start_mqtt(_state: &Arc<AppState>) {
+    tracing::warn!(
+        "mqtt.broker is set, but this build has no MQTT support (enable the `mqtt` feature)"
+    );
+}
+
+/// Start the playback worker; `None` when disabled or the output device
+/// can't be opened
+fn start_playback(config: &Config) -> Option<Arc<PlaybackQueue>> {
+    if !config.playback.enabled {
+        return None;
+    }
+    let queue = match PlaybackQueue::new(&config.playback.playback_settings()) {
+        Ok(queue) => Arc::new(queue),
+        Err(e) => {
+            tracing::warn!("Server-side playback disabled: {}", e);
+            return None;
+        }
+    };
+
+    match config.playback.backend {
+        Backend::Null => {
+            let worker = Arc::clone(&queue);
+            std::thread::spawn(move || worker.run_worker(NullOutput::default()));
+        }
+        #[cfg(feature = "audio-playback")]
+        Backend::Device => {
+            // The device has to be opened on the thread that plays on it
+            let worker = Arc::clone(&queue);
+            let device = config.playback.device.clone();
+            let (opened_tx, opened_rx) = std::sync::mpsc::channel();
+            std::thread::spawn(move || match DeviceOutput::open(device) {
+                Ok(output) => {
+                    let _ = opened_tx.send(Ok(()));
+                    worker.run_worker(output);
+                }
+                Err(e) => {
+                    let _ = opened_tx.send(Err(e));
+                }
+            });
+            match opened_rx.recv() {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    tracing::warn!("Server-side playback disabled: {}", e);
+                    return None;
+                }
+                Err(_) => return None,
+            }
+        }
+        #[cfg(not(feature = "audio-playback"))]
+        Backend::Device => {
+            tracing::info!("Playing on sound cards needs the audio-playback feature");
+            return None;
+        }
+    }
+    Some(queue)
+}
+
+/// Load the alias file, if there is one
+fn load_aliases(path: &Path) -> Result<VoiceAliases, AppError> {
+    if !path.exists() {
+        return Ok(VoiceAliases::default());
+    }
+
+    let aliases = VoiceAliases::load(path).map_err(|e| {
+        AppError::TtsError(format!(
+            "Failed to load voice aliases from {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    tracing::info!(
+        "Loaded {} voice aliases from {}",
+        aliases.entries().len(),
+        path.display()
+    );
+    Ok(aliases)
+}
+
+fn preload_voices(state: &AppState, spec: &[String]) {
+    let voice_ids: Vec<String> = if spec.iter().any(|v| v.eq_ignore_ascii_case("all")) {
+        match state.tts.list_voices() {
+            Ok(voices) => voices.into_iter().map(|v| v.id).collect(),
+            Err(e) => {
+                tracing::error!("Failed to list voices for preloading: {}", e);
+                Vec::new()
+            }
+        }
+    } else {
+        spec.to_vec()
+    };
+    let voice_ids = cap_preload(voice_ids, &state.config.cache);
+
+    for voice_id in &voice_ids {
+        match state.tts.warm_up(voice_id) {
+            Ok(elapsed) => {
+                tracing::info!(
+                    "Warmed up voice '{}' in {} ms",
+                    voice_id,
+                    elapsed.as_millis()
+                )
+            }
+            Err(e) => {
+                tracing::error!("Failed to preload voice '{}': {}", voice_id, e);
+                state
+                    .preload_errors
+                    .lock()
+                    .unwrap()
+                    .push((voice_id.clone(), e.to_string()));
+            }
+        }
+    }
+
+    // A memory budget can still push out voices loaded earlier in the list
+    let loaded = state.tts.engine_stats().loaded;
+    let failed = state.preload_errors.lock().unwrap().clone();
+    let evicted: Vec<&str> = voice_ids
+        .iter()
+        .filter(|id| !loaded.iter().any(|engine| &engine.id == *id))
+        .filter(|id| !failed.iter().any(|(failed, _)| failed == *id))
+        .map(String::as_str)
+        .collect();
+    if !evicted.is_empty() {
+        tracing::warn!(
+            "Preloaded voices evicted again to stay within cache.max_memory_mb: {}",
+            evicted.join(", ")
+        );
+    }
+
+    state.ready.store(true, Ordering::Release);
+    tracing::info!("Server ready");
+}
+
+/// Keep the preload list within `cache.max_loaded_voices`, pinned voices
+/// first, so preloading doesn't evict the voices it just loaded
+fn cap_preload(mut voice_ids: Vec<String>, cache: &config::CacheConfig) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    voice_ids.retain(|id| seen.insert(id.clone()));
+    let Some(max) = cache.max_loaded_voices else {
+        return voice_ids;
+    };
+    if voice_ids.len() <= max {
+        return voice_ids;
+    }
+    voice_ids.sort_by_key(|id| !cache.pinned_voices.contains(id));
+    let skipped = voice_ids.split_off(max);
+    tracing::warn!(
+        "Preloading {} of {} voices (cache.max_loaded_voices = {}); skipped: {}",
+        max,
+        max + skipped.len(),
+        max,
+        skipped.join(", ")
+    );
+    voice_ids
+}
+
+fn install_voices(catalog: &Path, voices_dir: &Path, list: bool, voices: &[String]) -> ExitCode {
+    let catalog = match Catalog::load(catalog) {
+        Ok(catalog) => catalog,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if list {
+        for entry in catalog.entries(voices_dir) {
+            println!(
+                "{:<40} {:>8} MB{}",
+                entry.key,
+                entry.size_bytes / (1024 * 1024),
+                if entry.installed { "  (installed)" } else { "" }
+            );
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let mut failed = false;
+    for voice in voices {
+        match catalog.install(voice, voices_dir) {
+            Ok(report) => println!("Installed {} ({} bytes)", report.voice, report.bytes),
+            Err(e) => {
+                eprintln!("Failed to install {}: {}", voice, e);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn validate_voices(config: &Config, voices: &[String]) -> ExitCode {
+    // Keep one engine resident at a time; each voice is only needed once
+    let cache_config = EngineCacheConfig {
+        max_engines: Some(1),
+        ..Default::default()
+    };
+    let tts = TtsService::new(
+        config.engine.voices_dir.clone(),
+        cache_config,
+        VoiceAliases::default(),
+        config.engine_options(),
+    );
+
+    let voice_ids: Vec<String> = if voices.is_empty() {
+        match tts.voice_ids() {
+            Ok(ids) => ids,
+            Err(e) => {
+                eprintln!("Failed to list voices: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        voices.to_vec()
+    };
+
+    if voice_ids.is_empty() {
+        eprintln!("No voices found in {}", tts.voices_dir().display());
+        return ExitCode::FAILURE;
+    }
+
+    let width = voice_ids.iter().map(|v| v.len()).max().unwrap_or(5).max(5);
+    print!("{:<width$}", "VOICE");
+    for check in Check::ALL {
+        print!("  {:<10}", check.label().to_uppercase());
+    }
+    println!();
+
+    let mut failures = Vec::new();
+    for voice_id in &voice_ids {
+        let status = tts.validate_voice(voice_id);
+
+        print!("{:<width$}", voice_id);
+        for check in Check::ALL {
+            let cell = match status.result(check) {
+                Some(result) if result.ok => "ok",
+                Some(_) => "FAIL",
+                None => "-",
+            };
+            print!("  {:<10}", cell);
+        }
+        println!();
+
+        if !status.ok {
+            failures.push(status);
+        }
+    }
+
+    for status in &failures {
+        for result in status.checks.iter().filter(|c| !c.ok) {
+            println!(
+                "\n{} [{}]: {}",
+                status.voice,
+                result.check.label(),
+                result.detail.as_deref().unwrap_or("failed")
+            );
+        }
+    }
+
+    if failures.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn synth(
+    config: &Config,
+    voice: Option<&str>,
+    input: Option<&Path>,
+    output: Option<&Path>,
+    format: Option<AudioFormat>,
+) -> ExitCode {
+    let result = (|| -> Result<(), AppError> {
+        let aliases = load_aliases(&config.engine.aliases_path())?;
+        let tts = TtsService::new(
+            config.engine.voices_dir.clone(),
+            EngineCacheConfig::default(),
+            aliases,
+            config.engine_options(),
+        );
+
+        let text = match input {
+            Some(path) => std::fs::read_to_string(path)?,
+            None => {
+                let mut text = String::new();
+                std::io::stdin().read_to_string(&mut text)?;
+                text
+            }
+        };
+        if text.trim().is_empty() {
+            return Err(AppError::BadRequest("No text to synthesize".into()));
+        }
+
+        let audio = tts.synthesize(&text, voice)?;
+        let format = format.unwrap_or(match output {
+            Some(_) => AudioFormat::Wav,
+            None => AudioFormat::Pcm,
+        });
+        let bytes = audio.encode(format)?;
+
+        match output {
+            Some(path) => std::fs::write(path, &bytes)?,
+            None => {
+                let mut stdout = std::io::stdout().lock();
+                stdout.write_all(&bytes)?;
+                stdout.flush()?;
+            }
+        }
+
+        eprintln!(
+            "Synthesized {:.2}s of audio at {} Hz",
+            audio.duration().as_secs_f64(),
+            audio.sample_rate
+        );
+        if format == AudioFormat::Pcm {
+            eprintln!(
+                "Raw s16le mono; play with: aplay -f S16_LE -c 1 -r {}",
+                audio.sample_rate
+            );
+        }
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn list_voices(config: &Config, language: Option<&str>, json: bool) -> ExitCode {
+    let tts = TtsService::new(
+        config.engine.voices_dir.clone(),
+        EngineCacheConfig::default(),
+        VoiceAliases::default(),
+        config.engine_options(),
+    );
+
+    let mut voices = match tts.list_voices() {
+        Ok(voices) => voices,
+        Err(e) => {
+            eprintln!("Failed to list voices: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(language) = language {
+        voices.retain(|v| v.matches_language(language));
+    }
+
+    if json {
+        match serde_json::to_string_pretty(&voices) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let width = voices.iter().map(|v| v.id.len()).max().unwrap_or(5).max(5);
+    println!(
+        "{:<width$}  {:<8}  {:<8}  {:>6}  SPEAKERS",
+        "VOICE", "LANGUAGE", "QUALITY", "RATE"
+    );
+    for voice in &voices {
+        println!(
+            "{:<width$}  {:<8}  {:<8}  {:>6}  {}",
+            voice.id,
+            voice.language_code,
+            voice.quality.as_deref().unwrap_or("-"),
+            voice.sample_rate,
+            voice.num_speakers
+        );
+    }
+
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,228 @@
+//! MQTT client, so sensors and automations can trigger announcements by
+//! publishing to a broker.
+//!
+//! Each configured topic takes `SpeakRequest` JSON (`{"text", "voice"}`)
+//! and either queues it for playback or publishes the audio, or a URL
+//! serving it, to the topic's reply topic. Failures are published as
+//! `{"error", "code"}` to `{reply_topic}/error`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Bytes;
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, Publish, QoS};
+use serde_json::json;
+use tokio::sync::Semaphore;
+
+use crate::api::routes::AppState;
+use crate::api::signed_url::SpeakQuery;
+use crate::api::{handlers, SpeakAloudRequest, SpeakRequest};
+use crate::config::{MqttAction, MqttConfig, MqttTopic};
+use crate::error::AppError;
+use crate::tts::playback::QueuedItem;
+use crate::tts::AudioFormat;
+
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const MAX_INCOMING_BYTES: usize = 256 * 1024;
+const MAX_OUTGOING_BYTES: usize = 64 * 1024 * 1024;
+
+/// What the MQTT client needs from the server
+pub trait Announcer: Send + Sync + 'static {
+    /// Queue for playback on the server's speakers
+    fn speak_aloud(&self, request: SpeakAloudRequest) -> Result<QueuedItem, AppError>;
+
+    /// Synthesize and encode
+    fn speak(&self, request: &SpeakRequest, format: AudioFormat) -> Result<Bytes, AppError>;
+
+    /// A path (under `mqtt.base_url`) that serves the audio
+    fn speak_url(&self, request: &SpeakRequest, format: AudioFormat) -> Result<String, AppError>;
+}
+
+impl Announcer for AppState {
+    fn speak_aloud(&self, request: SpeakAloudRequest) -> Result<QueuedItem, AppError> {
+        handlers::validate_text(self, &request.text)?;
+        let playback = self
+            .playback
+            .as_deref()
+            .ok_or_else(|| AppError::BadRequest("Playback is not enabled".into()))?;
+        let audio = self
+            .tts
+            .synthesize(&request.text, request.voice.as_deref())?;
+        playback.enqueue(
+            &request.text,
+            request.voice.as_deref(),
+            audio,
+            &request.options,
+        )
+    }
+
+    fn speak(&self, request: &SpeakRequest, format: AudioFormat) -> Result<Bytes, AppError> {
+        handlers::validate_text(self, &request.text)?;
+        Ok(self
+            .tts
+            .speak_cached(&request.text, request.voice.as_deref(), format)?
+            .audio)
+    }
+
+    /// Synthesizes first, so errors are reported here rather than to
+    /// whatever fetches the URL, and the fetch is served from the cache
+    fn speak_url(&self, request: &SpeakRequest, format: AudioFormat) -> Result<String, AppError> {
+        self.speak(request, format)?;
+        if let Some(signer) = &self.url_signer {
+            let ttl = self.config.mqtt.url_ttl_secs;
+            return Ok(signer
+                .sign(&request.text, request.voice.as_deref(), format, ttl)?
+                .0);
+        }
+        let query = SpeakQuery {
+            text: request.text.clone(),
+            voice: request.voice.clone(),
+            format: Some(format.extension().to_string()),
+            expires: None,
+            sig: None,
+        };
+        let query = serde_urlencoded::to_string(&query)
+            .map_err(|e| AppError::BadRequest(format!("Failed to encode URL: {}", e)))?;
+        Ok(format!("/api/speak?{}", query))
+    }
+}
+
+/// Connect to the broker and handle requests until the process exits,
+/// reconnecting (and re-subscribing) whenever the connection drops
+pub async fn run<A: Announcer>(config: MqttConfig, announcer: Arc<A>) {
+    let Some((host, port)) = config.broker_addr() else {
+        return;
+    };
+    let mut options = MqttOptions::new(&config.client_id, &host, port);
+    options.set_keep_alive(KEEP_ALIVE);
+    options.set_max_packet_size(MAX_INCOMING_BYTES, MAX_OUTGOING_BYTES);
+    if let Some(username) = &config.username {
+        options.set_credentials(username, config.password.clone().unwrap_or_default());
+    }
+
+    let (client, mut eventloop) = AsyncClient::new(options, 64);
+    // As with jobs: `workers` requests run at once, `max_queued` more wait,
+    // and the rest are turned away
+    let running = Arc::new(Semaphore::new(config.workers));
+    let admitted = Arc::new(Semaphore::new(config.workers + config.max_queued));
+    let config = Arc::new(config);
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                tracing::info!("Connected to MQTT broker {}:{}", host, port);
+                for topic in &config.topics {
+                    if let Err(e) = client.try_subscribe(&topic.topic, QoS::AtLeastOnce) {
+                        tracing::warn!("Failed to subscribe to {}: {}", topic.topic, e);
+                    }
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                let Some(topic) = config
+                    .topics
+                    .iter()
+                    .find(|topic| rumqttc::matches(&publish.topic, &topic.topic))
+                else {
+                    continue;
+                };
+                let Ok(slot) = Arc::clone(&admitted).try_acquire_owned() else {
+                    let busy = AppError::Busy("Too many MQTT requests in progress".into());
+                    if let Some((reply_topic, payload)) = reply(topic, Err(busy)) {
+                        let _ = client.try_publish(reply_topic, QoS::AtLeastOnce, false, payload);
+                    }
+                    continue;
+                };
+                let running = Arc::clone(&running);
+                let handled = handle(
+                    client.clone(),
+                    Arc::clone(&config),
+                    topic.clone(),
+                    Arc::clone(&announcer),
+                    publish,
+                );
+                tokio::spawn(async move {
+                    let _slot = slot;
+                    if let Ok(_permit) = running.acquire().await {
+                        handled.await;
+                    }
+                });
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!(
+                    "MQTT connection to {}:{} failed: {}; retrying in {}s",
+                    host,
+                    port,
+                    e,
+                    RECONNECT_DELAY.as_secs()
+                );
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+async fn handle<A: Announcer>(
+    client: AsyncClient,
+    config: Arc<MqttConfig>,
+    topic: MqttTopic,
+    announcer: Arc<A>,
+    publish: Publish,
+) {
+    let format = topic.format;
+    let action = topic.action;
+    let result = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, AppError> {
+        match action {
+            MqttAction::Play => {
+                let request = serde_json::from_slice(&publish.payload).map_err(invalid_payload)?;
+                Ok(serde_json::to_vec(&announcer.speak_aloud(request)?)?)
+            }
+            MqttAction::Audio => {
+                let request = serde_json::from_slice(&publish.payload).map_err(invalid_payload)?;
+                Ok(announcer.speak(&request, format)?.to_vec())
+            }
+            MqttAction::Url => {
+                let request = serde_json::from_slice(&publish.payload).map_err(invalid_payload)?;
+                let path = announcer.speak_url(&request, format)?;
+                let base_url = config.base_url.as_deref().unwrap_or_default();
+                Ok(format!("{}{}", base_url.trim_end_matches('/'), path).into_bytes())
+            }
+        }
+    })
+    .await
+    .unwrap_or_else(|e| Err(AppError::TtsError(format!("MQTT request failed: {}", e))));
+
+    let Some((reply_topic, payload)) = reply(&topic, result) else {
+        return;
+    };
+    if let Err(e) = client
+        .publish(&reply_topic, QoS::AtLeastOnce, false, payload)
+        .await
+    {
+        tracing::warn!("Failed to publish to {}: {}", reply_topic, e);
+    }
+}
+
+/// Where to publish a request's outcome, and what; failures are logged
+fn reply(topic: &MqttTopic, result: Result<Vec<u8>, AppError>) -> Option<(String, Vec<u8>)> {
+    if let Err(e) = &result {
+        tracing::warn!("MQTT request on {} failed: {}", topic.topic, e);
+    }
+    let reply_topic = topic.reply_topic.as_deref()?;
+    Some(match result {
+        Ok(payload) => (reply_topic.to_string(), payload),
+        Err(e) => {
+            let (_, code, message) = e.parts();
+            (
+                format!("{}/error", reply_topic),
+                json!({ "error": message, "code": code })
+                    .to_string()
+                    .into_bytes(),
+            )
+        }
+    })
+}
+
+fn invalid_payload(e: serde_json::Error) -> AppError {
+    AppError::BadRequest(format!("Invalid request payload: {}", e))
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::tts::voice::InferenceParams;
+
+/// Alias used when a request doesn't name a voice
+pub const DEFAULT_ALIAS: &str = "default";
+
+/// Aliases may point at other aliases; stop following after this many hops
+const MAX_ALIAS_DEPTH: usize = 8;
+
+/// An alias target: either a bare voice id/alias or one with default params
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum AliasTarget {
+    Voice(String),
+    WithParams {
+        voice: String,
+        #[serde(flatten)]
+        params: InferenceParams,
+    },
+}
+
+impl AliasTarget {
+    fn voice(&self) -> &str {
+        match self {
+            AliasTarget::Voice(voice) => voice,
+            AliasTarget::WithParams { voice, .. } => voice,
+        }
+    }
+
+    fn params(&self) -> InferenceParams {
+        match self {
+            AliasTarget::Voice(_) => InferenceParams::default(),
+            AliasTarget::WithParams { params, .. } => params.clone(),
+        }
+    }
+}
+
+/// A voice name resolved to a concrete voice id
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedVoice {
+    pub voice_id: String,
+    pub params: InferenceParams,
+}
+
+/// Stable names ("announcer", "narrator", "default") mapped to voice ids.
+///
+/// Loaded from a JSON object, e.g.
+/// `{"default": "announcer", "announcer": {"voice": "en_GB-alba-medium", "length_scale": 1.1}}`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct VoiceAliases {
+    aliases: HashMap<String, AliasTarget>,
+}
+
+impl VoiceAliases {
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let aliases: Self = serde_json::from_reader(File::open(path)?)?;
+        Ok(aliases)
+    }
+
+    pub fn entries(&self) -> &HashMap<String, AliasTarget> {
+        &self.aliases
+    }
+
+    /// Resolve a requested voice name, falling back to the default alias.
+    ///
+    /// Names that aren't aliases are taken to be voice ids. Parameters set
+    /// by an outer alias win over those of the alias it points at.
+    pub fn resolve(&self, name: Option<&str>) -> Result<ResolvedVoice, AppError> {
+        let mut current = match name.map(str::trim).filter(|n| !n.is_empty()) {
+            Some(name) => name.to_string(),
+            None if self.aliases.contains_key(DEFAULT_ALIAS) => DEFAULT_ALIAS.to_string(),
+            None => {
+                return Err(AppError::BadRequest(
+                    "Voice cannot be empty (no default voice configured)".into(),
+                ))
+            }
+        };
+
+        let mut params = InferenceParams::default();
+        for _ in 0..MAX_ALIAS_DEPTH {
+            match self.aliases.get(&current) {
+                Some(target) => {
+                    params = params.or(&target.params());
+                    current = target.voice().to_string();
+                }
+                None => {
+                    return Ok(ResolvedVoice {
+                        voice_id: current,
+                        params,
+                    })
+                }
+            }
+        }
+
+        Err(AppError::BadRequest(format!(
+            "Voice alias '{}' is circular or nested too deeply",
+            name.unwrap_or(DEFAULT_ALIAS)
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aliases() -> VoiceAliases {
+        serde_json::from_str(
+            r#"{
+                "default": "announcer",
+                "announcer": { "voice": "en_GB-alba-medium", "length_scale": 1.2 },
+                "fast-announcer": { "voice": "announcer", "length_scale": 0.8, "noise_w": 0.5 },
+                "narrator": "en_GB-aru-medium",
+                "loop-a": "loop-b",
+                "loop-b": "loop-a"
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn resolves_plain_alias() {
+        let resolved = aliases().resolve(Some("narrator")).unwrap();
+        assert_eq!(resolved.voice_id, "en_GB-aru-medium");
+        assert_eq!(resolved.params, InferenceParams::default());
+    }
+
+    #[test]
+    fn passes_through_voice_ids() {
+        let resolved = aliases().resolve(Some("en_US-lessac-medium")).unwrap();
+        assert_eq!(resolved.voice_id, "en_US-lessac-medium");
+    }
+
+    #[test]
+    fn falls_back_to_default() {
+        let resolved = aliases().resolve(None).unwrap();
+        assert_eq!(resolved.voice_id, "en_GB-alba-medium");
+        assert_eq!(resolved.params.length_scale, Some(1.2));
+
+        assert_eq!(aliases().resolve(Some("  ")).unwrap(), resolved);
+    }
+
+    #[test]
+    fn outer_alias_params_win() {
+        let resolved = aliases().resolve(Some("fast-announcer")).unwrap();
+        assert_eq!(resolved.voice_id, "en_GB-alba-medium");
+        assert_eq!(resolved.params.length_scale, Some(0.8));
+        assert_eq!(resolved.params.noise_w, Some(0.5));
+    }
+
+    #[test]
+    fn rejects_missing_default_and_loops() {
+        assert!(VoiceAliases::default().resolve(None).is_err());
+        assert!(aliases().resolve(Some("loop-a")).is_err());
+    }
+}
@@ -0,0 +1,349 @@
+use std::borrow::Cow;
+use std::f64::consts::PI;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::tts::piper;
+
+/// Synthesized mono audio before encoding
+#[derive(Debug, Clone)]
+pub struct Audio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+impl Audio {
+    pub fn duration(&self) -> Duration {
+        if self.sample_rate == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(self.samples.len() as f64 / self.sample_rate as f64)
+    }
+
+    pub fn encode(&self, format: AudioFormat) -> Result<Vec<u8>, AppError> {
+        match format {
+            AudioFormat::Wav => piper::samples_to_wav(&self.samples, self.sample_rate),
+            AudioFormat::Pcm => Ok(piper::samples_to_pcm(&self.samples)),
+        }
+    }
+
+    /// Decode a WAV file, mixing multiple channels down to mono
+    pub fn read_wav(path: &Path) -> Result<Audio, AppError> {
+        let invalid = |e: hound::Error| {
+            AppError::BadRequest(format!("Invalid WAV file {}: {}", path.display(), e))
+        };
+        let mut reader = hound::WavReader::open(path).map_err(invalid)?;
+        let spec = reader.spec();
+        let interleaved: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>(),
+            hound::SampleFormat::Int => {
+                let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|sample| sample.map(|s| s as f32 / scale))
+                    .collect()
+            }
+        }
+        .map_err(invalid)?;
+
+        let channels = spec.channels.max(1) as usize;
+        Ok(Audio {
+            samples: interleaved
+                .chunks(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+                .collect(),
+            sample_rate: spec.sample_rate,
+        })
+    }
+
+    /// Resample to `sample_rate` by linear interpolation. Lowering the rate
+    /// low-pass filters first, so nothing above the new Nyquist frequency
+    /// folds back as aliasing.
+    pub fn resample(&self, sample_rate: u32) -> Audio {
+        if sample_rate == self.sample_rate || self.sample_rate == 0 || self.samples.is_empty() {
+            return Audio {
+                samples: self.samples.clone(),
+                sample_rate,
+            };
+        }
+
+        let ratio = self.sample_rate as f64 / sample_rate as f64;
+        let source = if ratio > 1.0 {
+            Cow::Owned(low_pass(&self.samples, 0.5 / ratio))
+        } else {
+            Cow::Borrowed(&self.samples)
+        };
+        let len = (source.len() as f64 / ratio).round() as usize;
+        let last = source.len() - 1;
+        let samples = (0..len)
+            .map(|i| {
+                let position = i as f64 * ratio;
+                let index = (position as usize).min(last);
+                let next = (index + 1).min(last);
+                let fraction = (position - index as f64) as f32;
+                source[index] + (source[next] - source[index]) * fraction
+            })
+            .collect();
+        Audio {
+            samples,
+            sample_rate,
+        }
+    }
+
+    /// Encode as WAV with a `cue ` chunk and `LIST`/`adtl` labels, which
+    /// audio editors and some players show as named markers
+    pub fn encode_wav_with_cues(&self, cues: &[Cue]) -> Result<Vec<u8>, AppError> {
+        let mut wav = piper::samples_to_wav(&self.samples, self.sample_rate)?;
+        if cues.is_empty() {
+            return Ok(wav);
+        }
+
+        let mut cue = (cues.len() as u32).to_le_bytes().to_vec();
+        let mut labels = b"adtl".to_vec();
+        for (i, point) in cues.iter().enumerate() {
+            let id = i as u32 + 1;
+            cue.extend_from_slice(&id.to_le_bytes());
+            cue.extend_from_slice(&point.sample.to_le_bytes());
+            cue.extend_from_slice(b"data");
+            cue.extend_from_slice(&0u32.to_le_bytes()); // chunk start
+            cue.extend_from_slice(&0u32.to_le_bytes()); // block start
+            cue.extend_from_slice(&point.sample.to_le_bytes());
+
+            let mut label = id.to_le_bytes().to_vec();
+            label.extend_from_slice(point.label.as_bytes());
+            label.push(0);
+            push_chunk(&mut labels, b"labl", &label);
+        }
+        push_chunk(&mut wav, b"cue ", &cue);
+        push_chunk(&mut wav, b"LIST", &labels);
+
+        let riff_size = (wav.len() - 8) as u32;
+        wav[4..8].copy_from_slice(&riff_size.to_le_bytes());
+        Ok(wav)
+    }
+}
+
+/// A named position in a WAV file, in samples from the start
+#[derive(Debug, Clone)]
+pub struct Cue {
+    pub sample: u32,
+    pub label: String,
+}
+
+/// Append a RIFF chunk, padded to an even length
+fn push_chunk(out: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// Half-length of the low-pass kernel used before downsampling
+const LOW_PASS_TAPS: usize = 32;
+
+/// Windowed-sinc (Blackman) low-pass; `cutoff` is a fraction of the sample
+/// rate, below 0.5
+fn low_pass(samples: &[f32], cutoff: f64) -> Vec<f32> {
+    // Start the transition band a little early so the new Nyquist
+    // frequency is already well attenuated
+    let cutoff = cutoff * 0.9;
+    let width = (2 * LOW_PASS_TAPS) as f64;
+    let mut kernel: Vec<f64> = (0..=2 * LOW_PASS_TAPS)
+        .map(|i| {
+            let t = i as f64 - LOW_PASS_TAPS as f64;
+            let sinc = if t == 0.0 {
+                2.0 * cutoff
+            } else {
+                (2.0 * PI * cutoff * t).sin() / (PI * t)
+            };
+            let window = 0.42 - 0.5 * (2.0 * PI * i as f64 / width).cos()
+                + 0.08 * (4.0 * PI * i as f64 / width).cos();
+            sinc * window
+        })
+        .collect();
+    let sum: f64 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|k| *k /= sum);
+
+    (0..samples.len())
+        .map(|n| {
+            kernel
+                .iter()
+                .enumerate()
+                .filter_map(|(i, k)| {
+                    let index = (n + i).checked_sub(LOW_PASS_TAPS)?;
+                    samples.get(index).map(|s| *s as f64 * k)
+                })
+                .sum::<f64>() as f32
+        })
+        .collect()
+}
+
+/// Output encodings the server can produce
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    #[default]
+    Wav,
+    /// Headerless 16-bit little-endian mono at the voice's sample rate
+    Pcm,
+}
+
+impl AudioFormat {
+    pub const ALL: [AudioFormat; 2] = [AudioFormat::Wav, AudioFormat::Pcm];
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "audio/wav",
+            AudioFormat::Pcm => "audio/L16",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Pcm => "pcm",
+        }
+    }
+}
+
+impl FromStr for AudioFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "wav" => Ok(AudioFormat::Wav),
+            "pcm" | "raw" => Ok(AudioFormat::Pcm),
+            other => Err(AppError::BadRequest(format!(
+                "Unsupported audio format '{}' (expected wav or pcm)",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_formats() {
+        assert_eq!("WAV".parse::<AudioFormat>().unwrap(), AudioFormat::Wav);
+        assert_eq!("raw".parse::<AudioFormat>().unwrap(), AudioFormat::Pcm);
+        assert!("mp3".parse::<AudioFormat>().is_err());
+    }
+
+    #[test]
+    fn computes_duration() {
+        let audio = Audio {
+            samples: vec![0.0; 11025],
+            sample_rate: 22050,
+        };
+        assert_eq!(audio.duration(), Duration::from_millis(500));
+        assert_eq!(audio.encode(AudioFormat::Pcm).unwrap().len(), 22050);
+    }
+
+    #[test]
+    fn resamples_linearly() {
+        let audio = Audio {
+            samples: vec![0.0, 1.0, 0.0, -1.0],
+            sample_rate: 1000,
+        };
+        let up = audio.resample(2000);
+        assert_eq!(up.sample_rate, 2000);
+        assert_eq!(up.samples, vec![0.0, 0.5, 1.0, 0.5, 0.0, -0.5, -1.0, -1.0]);
+        assert_eq!(audio.resample(1000).samples, audio.samples);
+    }
+
+    #[test]
+    fn filters_before_downsampling() {
+        let tone = |hz: f64| Audio {
+            samples: (0..8000)
+                .map(|i| (2.0 * PI * hz * i as f64 / 8000.0).sin() as f32)
+                .collect(),
+            sample_rate: 8000,
+        };
+        // RMS away from the edges, where the filter runs into silence
+        let rms = |audio: &Audio| {
+            let middle = &audio.samples[100..audio.samples.len() - 100];
+            (middle.iter().map(|s| s * s).sum::<f32>() / middle.len() as f32).sqrt()
+        };
+
+        let kept = tone(300.0).resample(4000);
+        assert_eq!(kept.samples.len(), 4000);
+        assert!((rms(&kept) - 0.707).abs() < 0.05, "{}", rms(&kept));
+
+        // 3 kHz is above the new 2 kHz Nyquist frequency and would alias
+        assert!(rms(&tone(3000.0).resample(4000)) < 0.05);
+    }
+
+    #[test]
+    fn reads_stereo_wav_as_mono() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chime.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for sample in [16384i16, 0, -16384, -16384] {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let audio = Audio::read_wav(&path).unwrap();
+        assert_eq!(audio.sample_rate, 8000);
+        assert_eq!(audio.samples, vec![0.25, -0.5]);
+        assert!(Audio::read_wav(&dir.path().join("missing.wav")).is_err());
+    }
+
+    #[test]
+    fn writes_cue_markers() {
+        let audio = Audio {
+            samples: vec![0.0; 100],
+            sample_rate: 22050,
+        };
+        let plain = audio.encode(AudioFormat::Wav).unwrap();
+        let wav = audio
+            .encode_wav_with_cues(&[
+                Cue {
+                    sample: 0,
+                    label: "One".into(),
+                },
+                Cue {
+                    sample: 50,
+                    label: "Two".into(),
+                },
+            ])
+            .unwrap();
+
+        assert_eq!(&wav[..plain.len()][8..], &plain[8..]);
+        let riff_size = u32::from_le_bytes(wav[4..8].try_into().unwrap()) as usize;
+        assert_eq!(riff_size, wav.len() - 8);
+
+        // cue chunk: count, then 24 bytes per point
+        let cue = &wav[plain.len()..];
+        assert_eq!(&cue[..4], b"cue ");
+        assert_eq!(
+            u32::from_le_bytes(cue[4..8].try_into().unwrap()),
+            4 + 2 * 24
+        );
+        assert_eq!(u32::from_le_bytes(cue[8..12].try_into().unwrap()), 2);
+        let list = &cue[8 + 4 + 2 * 24..];
+        assert_eq!(&list[..4], b"LIST");
+        assert_eq!(&list[8..12], b"adtl");
+        assert_eq!(&list[12..16], b"labl");
+        assert_eq!(&list[24..28], b"One\0");
+
+        // Still readable as a plain WAV
+        let reader = hound::WavReader::new(std::io::Cursor::new(wav)).unwrap();
+        assert_eq!(reader.len(), 100);
+    }
+}
@@ -0,0 +1,470 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use axum::body::Bytes;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::tts::audio::AudioFormat;
+use crate::tts::voice::InferenceConfig;
+use crate::tts::write_atomic;
+
+/// Limits for cached, encoded synthesis results
+#[derive(Debug, Clone, Default)]
+pub struct AudioCacheConfig {
+    /// Directory for the disk tier (None = memory only)
+    pub dir: Option<PathBuf>,
+    /// Size bound for the disk tier
+    pub max_disk_bytes: u64,
+    /// Size bound for the in-memory hot tier (0 = disabled)
+    pub max_memory_bytes: u64,
+}
+
+/// Everything that changes the audio produced for a request
+pub struct AudioKey<'a> {
+    pub text: &'a str,
+    pub voice_id: &'a str,
+    pub speaker_id: i64,
+    /// Effective scales after alias overrides and voice defaults
+    pub inference: &'a InferenceConfig,
+    pub format: AudioFormat,
+    pub model_checksum: &'a str,
+    pub dsl: bool,
+}
+
+impl AudioKey<'_> {
+    /// Hex SHA-256 of the key; whitespace differences in the text don't count
+    pub fn digest(&self) -> String {
+        let text = self.text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mut hasher = Sha256::new();
+        for part in [
+            text.as_str(),
+            self.voice_id,
+            &self.speaker_id.to_string(),
+            &format!("{:?}", self.inference),
+            self.format.extension(),
+            self.model_checksum,
+            if self.dsl { "dsl" } else { "plain" },
+        ] {
+            hasher.update(part.as_bytes());
+            hasher.update([0u8]);
+        }
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+struct DiskEntry {
+    bytes: u64,
+    last_used: u64,
+}
+
+struct MemoryEntry {
+    audio: Bytes,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    disk: HashMap<String, DiskEntry>,
+    disk_bytes: u64,
+    memory: HashMap<String, MemoryEntry>,
+    memory_bytes: u64,
+    clock: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioCacheStats {
+    pub disk_entries: usize,
+    pub disk_bytes: u64,
+    pub max_disk_bytes: u64,
+    pub memory_entries: usize,
+    pub memory_bytes: u64,
+    pub max_memory_bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PurgeReport {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+/// Two-tier LRU cache of encoded audio: a small hot set in memory in
+/// front of a larger, size-bounded directory of files named by key.
+pub struct AudioCache {
+    config: AudioCacheConfig,
+    state: Mutex<CacheState>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl AudioCache {
+    /// Open the cache, indexing files left in the directory by earlier runs
+    pub fn new(config: AudioCacheConfig) -> Self {
+        let mut state = CacheState::default();
+
+        if let Some(dir) = &config.dir {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                tracing::warn!("Audio cache directory {} unusable: {}", dir.display(), e);
+            }
+            // Oldest files first so their recency ranks lowest
+            let mut files: Vec<(String, u64, SystemTime)> = std::fs::read_dir(dir)
+                .map(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .filter_map(|entry| {
+                            let key = cache_key_from_path(&entry.path())?;
+                            let meta = entry.metadata().ok()?;
+                            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                            Some((key, meta.len(), modified))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            files.sort_by_key(|(_, _, modified)| *modified);
+
+            for (key, bytes, _) in files {
+                state.clock += 1;
+                state.disk_bytes += bytes;
+                state.disk.insert(
+                    key,
+                    DiskEntry {
+                        bytes,
+                        last_used: state.clock,
+                    },
+                );
+            }
+            if !state.disk.is_empty() {
+                tracing::info!(
+                    "Audio cache: {} entries ({} MB) in {}",
+                    state.disk.len(),
+                    state.disk_bytes / (1024 * 1024),
+                    dir.display()
+                );
+            }
+        }
+
+        let cache = Self {
+            config,
+            state: Mutex::new(state),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        };
+        let evicted = cache.trim_disk(&mut cache.state.lock().unwrap());
+        cache.remove_entries(&evicted);
+        cache
+    }
+
+    /// A cache that stores nothing
+    pub fn disabled() -> Self {
+        Self::new(AudioCacheConfig::default())
+    }
+
+    /// Look up an entry. Files are read without holding the lock, so
+    /// memory hits never wait on disk I/O.
+    pub fn get(&self, key: &str, format: AudioFormat) -> Option<Bytes> {
+        let dir = {
+            let mut state = self.state.lock().unwrap();
+            state.clock += 1;
+            let now = state.clock;
+
+            if let Some(entry) = state.memory.get_mut(key) {
+                entry.last_used = now;
+                let audio = entry.audio.clone();
+                if let Some(entry) = state.disk.get_mut(key) {
+                    entry.last_used = now;
+                }
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Some(audio);
+            }
+
+            let on_disk = state.disk.get_mut(key).map(|entry| entry.last_used = now);
+            match (on_disk, &self.config.dir) {
+                (Some(()), Some(dir)) => dir,
+                _ => {
+                    self.misses.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                }
+            }
+        };
+
+        let read = std::fs::read(entry_path(dir, key, format));
+        let mut state = self.state.lock().unwrap();
+        match read {
+            Ok(data) => {
+                let audio = Bytes::from(data);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                self.insert_memory(&mut state, key, audio.clone());
+                Some(audio)
+            }
+            Err(e) => {
+                tracing::warn!("Dropping unreadable audio cache entry {}: {}", key, e);
+                if let Some(entry) = state.disk.remove(key) {
+                    state.disk_bytes -= entry.bytes;
+                }
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Store an entry in both tiers; the file is written, and evicted
+    /// files removed, without holding the lock
+    pub fn insert(&self, key: &str, format: AudioFormat, audio: Bytes) {
+        let size = audio.len() as u64;
+        let dir = self.config.dir.as_deref().filter(|_| {
+            size <= self.config.max_disk_bytes && !self.state.lock().unwrap().disk.contains_key(key)
+        });
+        let written = dir.map(|dir| write_atomic(&entry_path(dir, key, format), &audio));
+
+        let mut evicted = Vec::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            state.clock += 1;
+            match written {
+                Some(Ok(())) if !state.disk.contains_key(key) => {
+                    let last_used = state.clock;
+                    state.disk_bytes += size;
+                    state.disk.insert(
+                        key.to_string(),
+                        DiskEntry {
+                            bytes: size,
+                            last_used,
+                        },
+                    );
+                    evicted = self.trim_disk(&mut state);
+                }
+                Some(Err(e)) => tracing::warn!("Failed to write audio cache entry {}: {}", key, e),
+                _ => {}
+            }
+            self.insert_memory(&mut state, key, audio);
+        }
+        self.remove_entries(&evicted);
+    }
+
+    /// Remove every entry from both tiers
+    pub fn purge(&self) -> PurgeReport {
+        let mut state = self.state.lock().unwrap();
+        let memory_only = state
+            .memory
+            .keys()
+            .filter(|key| !state.disk.contains_key(*key))
+            .count();
+        let report = PurgeReport {
+            entries: state.disk.len() + memory_only,
+            bytes: state.disk_bytes,
+        };
+
+        if let Some(dir) = &self.config.dir {
+            for key in state.disk.keys() {
+                remove_entry(dir, key);
+            }
+        }
+        *state = CacheState::default();
+
+        tracing::info!("Purged {} audio cache entries", report.entries);
+        report
+    }
+
+    pub fn stats(&self) -> AudioCacheStats {
+        let state = self.state.lock().unwrap();
+        AudioCacheStats {
+            disk_entries: state.disk.len(),
+            disk_bytes: state.disk_bytes,
+            max_disk_bytes: self.config.max_disk_bytes,
+            memory_entries: state.memory.len(),
+            memory_bytes: state.memory_bytes,
+            max_memory_bytes: self.config.max_memory_bytes,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    fn insert_memory(&self, state: &mut CacheState, key: &str, audio: Bytes) {
+        let size = audio.len() as u64;
+        if size > self.config.max_memory_bytes || state.memory.contains_key(key) {
+            return;
+        }
+
+        while state.memory_bytes + size > self.config.max_memory_bytes {
+            let victim = state
+                .memory
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match victim.and_then(|victim| state.memory.remove(&victim)) {
+                Some(entry) => state.memory_bytes -= entry.audio.len() as u64,
+                None => return,
+            }
+        }
+
+        state.memory_bytes += size;
+        let last_used = state.clock;
+        state
+            .memory
+            .insert(key.to_string(), MemoryEntry { audio, last_used });
+    }
+
+    /// Drop the least recently used disk entries until the tier fits;
+    /// returns their keys, whose files the caller removes after unlocking
+    fn trim_disk(&self, state: &mut CacheState) -> Vec<String> {
+        let mut evicted = Vec::new();
+        while state.disk_bytes > self.config.max_disk_bytes {
+            let victim = state
+                .disk
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            let Some(victim) = victim else {
+                break;
+            };
+            let entry = state.disk.remove(&victim).unwrap();
+            state.disk_bytes -= entry.bytes;
+            evicted.push(victim);
+        }
+        evicted
+    }
+
+    fn remove_entries(&self, keys: &[String]) {
+        if let Some(dir) = &self.config.dir {
+            for key in keys {
+                remove_entry(dir, key);
+            }
+        }
+    }
+}
+
+fn entry_path(dir: &Path, key: &str, format: AudioFormat) -> PathBuf {
+    dir.join(format!("{}.{}", key, format.extension()))
+}
+
+/// Keys are hex digests; anything else in the directory is left alone
+fn cache_key_from_path(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    let extension = path.extension()?.to_str()?;
+    let known = extension.parse::<AudioFormat>().is_ok();
+    (known && stem.len() == 64 && stem.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| stem.to_string())
+}
+
+/// The key embeds the format, so only one of these files can exist
+fn remove_entry(dir: &Path, key: &str) {
+    for format in AudioFormat::ALL {
+        let _ = std::fs::remove_file(entry_path(dir, key, format));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(text: &str) -> String {
+        AudioKey {
+            text,
+            voice_id: "en_GB-alba-medium",
+            speaker_id: 0,
+            inference: &InferenceConfig::default(),
+            format: AudioFormat::Wav,
+            model_checksum: "abc",
+            dsl: true,
+        }
+        .digest()
+    }
+
+    fn disk_cache(dir: &Path, max_disk_bytes: u64) -> AudioCache {
+        AudioCache::new(AudioCacheConfig {
+            dir: Some(dir.to_path_buf()),
+            max_disk_bytes,
+            max_memory_bytes: 0,
+        })
+    }
+
+    #[test]
+    fn key_ignores_whitespace_but_not_content() {
+        assert_eq!(key("Platform  2\n"), key(" Platform 2"));
+        assert_ne!(key("Platform 2"), key("Platform 3"));
+
+        let inference = InferenceConfig {
+            length_scale: 1.2,
+            ..Default::default()
+        };
+        let slower = AudioKey {
+            text: "Platform 2",
+            voice_id: "en_GB-alba-medium",
+            speaker_id: 0,
+            inference: &inference,
+            format: AudioFormat::Wav,
+            model_checksum: "abc",
+            dsl: true,
+        };
+        assert_ne!(slower.digest(), key("Platform 2"));
+    }
+
+    #[test]
+    fn memory_tier_evicts_least_recently_used() {
+        let cache = AudioCache::new(AudioCacheConfig {
+            max_memory_bytes: 10,
+            ..Default::default()
+        });
+        cache.insert("a", AudioFormat::Wav, Bytes::from_static(b"aaaa"));
+        cache.insert("b", AudioFormat::Wav, Bytes::from_static(b"bbbb"));
+        cache.get("a", AudioFormat::Wav);
+        cache.insert("c", AudioFormat::Wav, Bytes::from_static(b"cccc"));
+
+        assert!(cache.get("a", AudioFormat::Wav).is_some());
+        assert!(cache.get("b", AudioFormat::Wav).is_none());
+        assert!(cache.get("c", AudioFormat::Wav).is_some());
+    }
+
+    #[test]
+    fn disk_tier_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let k = key("Mind the gap");
+        disk_cache(dir.path(), 1024).insert(&k, AudioFormat::Wav, Bytes::from_static(b"RIFF"));
+
+        let reopened = disk_cache(dir.path(), 1024);
+        assert_eq!(
+            reopened.get(&k, AudioFormat::Wav).unwrap(),
+            Bytes::from_static(b"RIFF")
+        );
+        assert_eq!(reopened.stats().hits, 1);
+    }
+
+    #[test]
+    fn disk_tier_is_size_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = disk_cache(dir.path(), 10);
+        let (a, b, c) = (key("a"), key("b"), key("c"));
+        cache.insert(&a, AudioFormat::Wav, Bytes::from_static(b"aaaa"));
+        cache.insert(&b, AudioFormat::Wav, Bytes::from_static(b"bbbb"));
+        cache.get(&a, AudioFormat::Wav);
+        cache.insert(&c, AudioFormat::Wav, Bytes::from_static(b"cccc"));
+
+        assert!(cache.get(&b, AudioFormat::Wav).is_none());
+        assert!(!entry_path(dir.path(), &b, AudioFormat::Wav).exists());
+        assert!(cache.get(&a, AudioFormat::Wav).is_some());
+        assert!(cache.get(&c, AudioFormat::Wav).is_some());
+    }
+
+    #[test]
+    fn purge_empties_both_tiers() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = AudioCache::new(AudioCacheConfig {
+            dir: Some(dir.path().to_path_buf()),
+            max_disk_bytes: 1024,
+            max_memory_bytes: 1024,
+        });
+        let k = key("purge me");
+        cache.insert(&k, AudioFormat::Pcm, Bytes::from_static(b"pcm!"));
+
+        let report = cache.purge();
+        assert_eq!(report.entries, 1);
+        assert_eq!(report.bytes, 4);
+        assert!(cache.get(&k, AudioFormat::Pcm).is_none());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::tts::voice::InferenceParams;
+use crate::tts::zip::ZipWriter;
+use crate::tts::{validate_file_id, AudioFormat, TtsService};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// One phrase of a batch request
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchItem {
+    /// File name in the archive, without extension
+    pub id: String,
+    pub text: String,
+    /// Voice id or alias; the `default` alias when omitted
+    #[serde(default)]
+    pub voice: Option<String>,
+    /// Inference overrides on top of the voice's own
+    #[serde(default)]
+    pub params: InferenceParams,
+}
+
+/// How one item turned out, as listed in the archive's manifest
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchEntry {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// `manifest.json` at the root of a batch archive
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchManifest {
+    pub format: AudioFormat,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub items: Vec<BatchEntry>,
+}
+
+/// A rendered item and its encoded audio, or why it failed
+type ItemResult = Result<(BatchEntry, Vec<u8>), String>;
+
+/// Reject batches that can't be archived: empty, oversized, or with ids
+/// that are unsafe or repeated. Problems with single items are reported
+/// in the manifest instead.
+pub fn validate(items: &[BatchItem], max_items: usize) -> Result<(), AppError> {
+    if items.is_empty() {
+        return Err(AppError::BadRequest("Batch has no items".into()));
+    }
+    if items.len() > max_items {
+        return Err(AppError::BadRequest(format!(
+            "Batch has {} items (max {})",
+            items.len(),
+            max_items
+        )));
+    }
+    let mut seen = HashSet::new();
+    for item in items {
+        validate_file_id("item", &item.id)?;
+        if !seen.insert(item.id.as_str()) {
+            return Err(AppError::BadRequest(format!(
+                "Duplicate item id '{}'",
+                item.id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Synthesize every item on up to `workers` threads and pack the results
+/// into a ZIP with one `{id}.{ext}` per successful item plus the manifest
+pub fn run(
+    tts: &TtsService,
+    items: &[BatchItem],
+    format: AudioFormat,
+    workers: usize,
+    max_text_length: usize,
+) -> Result<Vec<u8>, AppError> {
+    let started = Instant::now();
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<ItemResult>> = vec![None; items.len()];
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers.clamp(1, items.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            break done;
+                        };
+                        done.push((index, render(tts, item, format, max_text_length)));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            for (index, result) in handle.join().unwrap_or_default() {
+                results[index] = Some(result);
+            }
+        }
+    });
+
+    let mut zip = ZipWriter::new();
+    let mut entries = Vec::with_capacity(items.len());
+    for (item, result) in items.iter().zip(results) {
+        match result.unwrap_or_else(|| Err("Synthesis worker panicked".to_string())) {
+            Ok((entry, audio)) => {
+                zip.add(entry.file.as_deref().unwrap_or(&item.id), &audio)?;
+                entries.push(entry);
+            }
+            Err(error) => entries.push(BatchEntry {
+                id: item.id.clone(),
+                file: None,
+                voice: None,
+                duration_ms: None,
+                error: Some(error),
+            }),
+        }
+    }
+
+    let succeeded = entries.iter().filter(|entry| entry.error.is_none()).count();
+    let manifest = BatchManifest {
+        format,
+        succeeded,
+        failed: entries.len() - succeeded,
+        items: entries,
+    };
+    zip.add(MANIFEST_FILE, &serde_json::to_vec_pretty(&manifest)?)?;
+
+    tracing::info!(
+        "Batch of {} items: {} succeeded, {} failed in {:.1}s",
+        items.len(),
+        manifest.succeeded,
+        manifest.failed,
+        started.elapsed().as_secs_f64()
+    );
+    zip.finish()
+}
+
+fn render(
+    tts: &TtsService,
+    item: &BatchItem,
+    format: AudioFormat,
+    max_text_length: usize,
+) -> ItemResult {
+    if item.text.trim().is_empty() {
+        return Err("Text cannot be empty".to_string());
+    }
+    if item.text.chars().count() > max_text_length {
+        return Err(format!("Text too long (max {} chars)", max_text_length));
+    }
+
+    let voice = tts
+        .resolve_voice(item.voice.as_deref())
+        .map_err(|e| e.to_string())?
+        .voice_id;
+    let audio = tts
+        .synthesize_with(&item.text, item.voice.as_deref(), &item.params)
+        .map_err(|e| e.to_string())?;
+    let encoded = audio.encode(format).map_err(|e| e.to_string())?;
+
+    Ok((
+        BatchEntry {
+            id: item.id.clone(),
+            file: Some(format!("{}.{}", item.id, format.extension())),
+            voice: Some(voice),
+            duration_ms: Some(audio.duration().as_millis() as u64),
+            error: None,
+        },
+        encoded,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str) -> BatchItem {
+        BatchItem {
+            id: id.to_string(),
+            text: "Hello.".to_string(),
+            voice: None,
+            params: InferenceParams::default(),
+        }
+    }
+
+    #[test]
+    fn validates_batches() {
+        assert!(validate(&[], 10).is_err());
+        assert!(validate(&[item("a"), item("b")], 1).is_err());
+        assert!(validate(&[item("a"), item("a")], 10).is_err());
+        assert!(validate(&[item("../x")], 10).is_err());
+        assert!(validate(&[item("welcome"), item("menu.1")], 10).is_ok());
+    }
+
+    #[test]
+    fn reports_failed_items_in_the_manifest() {
+        let voices = tempfile::tempdir().unwrap();
+        let tts = TtsService::new(
+            voices.path().to_path_buf(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+        let items = [
+            BatchItem {
+                voice: Some("nobody".to_string()),
+                ..item("unknown")
+            },
+            BatchItem {
+                text: "  ".to_string(),
+                ..item("blank")
+            },
+        ];
+
+        let data = run(&tts, &items, AudioFormat::Wav, 2, 100).unwrap();
+
+        // Nothing rendered, so the manifest is the only entry
+        let size = u32::from_le_bytes(data[22..26].try_into().unwrap()) as usize;
+        let name_len = u16::from_le_bytes(data[26..28].try_into().unwrap()) as usize;
+        assert_eq!(&data[30..30 + name_len], MANIFEST_FILE.as_bytes());
+        let start = 30 + name_len;
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&data[start..start + size]).unwrap();
+
+        assert_eq!(manifest["succeeded"], 0);
+        assert_eq!(manifest["failed"], 2);
+        let items = manifest["items"].as_array().unwrap();
+        assert_eq!(items[0]["id"], "unknown");
+        assert!(items[0]["error"].as_str().unwrap().contains("nobody"));
+        assert!(items[0].get("file").is_none());
+        assert_eq!(items[1]["id"], "blank");
+        assert_eq!(items[1]["error"], "Text cannot be empty");
+    }
+}
@@ -0,0 +1,453 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::error::AppError;
+
+/// Limits applied to the set of resident voice engines
+#[derive(Debug, Clone, Default)]
+pub struct EngineCacheConfig {
+    /// Maximum number of engines kept loaded (None = unlimited)
+    pub max_engines: Option<usize>,
+    /// Maximum estimated memory used by loaded engines (None = unlimited)
+    pub max_bytes: Option<u64>,
+    /// Voices that are never evicted once loaded
+    pub pinned: HashSet<String>,
+}
+
+struct CacheEntry<T> {
+    value: Arc<T>,
+    bytes: u64,
+    last_used: u64,
+}
+
+struct CacheState<T> {
+    entries: HashMap<String, CacheEntry<T>>,
+    clock: u64,
+}
+
+impl<T> CacheState<T> {
+    fn total_bytes(&self) -> u64 {
+        self.entries.values().map(|e| e.bytes).sum()
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    loads: AtomicU64,
+    load_failures: AtomicU64,
+    evictions: AtomicU64,
+    load_millis: AtomicU64,
+}
+
+/// LRU cache of loaded engines with a count/memory budget.
+///
+/// Loads are serialised per key so concurrent first requests for the same
+/// voice share a single load instead of each building their own session.
+pub struct EngineCache<T> {
+    config: EngineCacheConfig,
+    state: Mutex<CacheState<T>>,
+    load_locks: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    counters: Counters,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadedEngine {
+    pub id: String,
+    pub estimated_bytes: u64,
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EngineCacheStats {
+    pub loaded: Vec<LoadedEngine>,
+    pub total_bytes: u64,
+    pub max_engines: Option<usize>,
+    pub max_bytes: Option<u64>,
+    pub hits: u64,
+    pub misses: u64,
+    pub loads: u64,
+    pub load_failures: u64,
+    pub evictions: u64,
+    pub load_millis: u64,
+}
+
+impl<T> EngineCache<T> {
+    pub fn new(config: EngineCacheConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(CacheState {
+                entries: HashMap::new(),
+                clock: 0,
+            }),
+            load_locks: Mutex::new(HashMap::new()),
+            counters: Counters::default(),
+        }
+    }
+
+    /// Return the cached value for `key`, or run `load` to create it.
+    ///
+    /// `load` returns the value together with its estimated size in bytes.
+    pub fn get_or_load<F>(&self, key: &str, load: F) -> Result<Arc<T>, AppError>
+    where
+        F: FnOnce() -> Result<(T, u64), AppError>,
+    {
+        if let Some(value) = self.lookup(key) {
+            self.counters.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(value);
+        }
+
+        // Serialise loads of the same key; `_guard` drops before `key_lock`
+        let key_lock = LoadLock {
+            locks: &self.load_locks,
+            key,
+            lock: Arc::clone(
+                self.load_locks
+                    .lock()
+                    .unwrap()
+                    .entry(key.to_string())
+                    .or_default(),
+            ),
+        };
+        // A loader that panicked only poisons the guard; the lock holds no data
+        let _guard = key_lock.lock.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // Another caller may have finished loading while we waited
+        if let Some(value) = self.lookup(key) {
+            self.counters.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(value);
+        }
+
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
+
+        let started = Instant::now();
+        let (value, bytes) = match load() {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                self.counters.load_failures.fetch_add(1, Ordering::Relaxed);
+                return Err(e);
+            }
+        };
+        let elapsed = started.elapsed();
+        self.counters.loads.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .load_millis
+            .fetch_add(elapsed.as_millis() as u64, Ordering::Relaxed);
+
+        tracing::info!(
+            "Loaded engine '{}' (~{} MB) in {} ms",
+            key,
+            bytes / (1024 * 1024),
+            elapsed.as_millis()
+        );
+
+        let value = Arc::new(value);
+        {
+            let mut state = self.state.lock().unwrap();
+            self.make_room(&mut state, key, bytes);
+            state.clock += 1;
+            let last_used = state.clock;
+            state.entries.insert(
+                key.to_string(),
+                CacheEntry {
+                    value: Arc::clone(&value),
+                    bytes,
+                    last_used,
+                },
+            );
+        }
+
+        Ok(value)
+    }
+
+    /// Drop `key` from the cache. In-flight users keep their `Arc` alive.
+    pub fn remove(&self, key: &str) -> bool {
+        let removed = self.state.lock().unwrap().entries.remove(key).is_some();
+        if removed {
+            tracing::info!("Unloaded engine '{}'", key);
+        }
+        removed
+    }
+
+    pub fn is_loaded(&self, key: &str) -> bool {
+        self.state.lock().unwrap().entries.contains_key(key)
+    }
+
+    /// A loaded value, without loading it or counting as a use
+    pub fn peek(&self, key: &str) -> Option<Arc<T>> {
+        self.state
+            .lock()
+            .unwrap()
+            .entries
+            .get(key)
+            .map(|entry| Arc::clone(&entry.value))
+    }
+
+    pub fn stats(&self) -> EngineCacheStats {
+        let state = self.state.lock().unwrap();
+        let mut loaded: Vec<LoadedEngine> = state
+            .entries
+            .iter()
+            .map(|(id, entry)| LoadedEngine {
+                id: id.clone(),
+                estimated_bytes: entry.bytes,
+                pinned: self.config.pinned.contains(id),
+            })
+            .collect();
+        loaded.sort_by(|a, b| a.id.cmp(&b.id));
+
+        EngineCacheStats {
+            loaded,
+            total_bytes: state.total_bytes(),
+            max_engines: self.config.max_engines,
+            max_bytes: self.config.max_bytes,
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            loads: self.counters.loads.load(Ordering::Relaxed),
+            load_failures: self.counters.load_failures.load(Ordering::Relaxed),
+            evictions: self.counters.evictions.load(Ordering::Relaxed),
+            load_millis: self.counters.load_millis.load(Ordering::Relaxed),
+        }
+    }
+
+    fn lookup(&self, key: &str) -> Option<Arc<T>> {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let now = state.clock;
+        state.entries.get_mut(key).map(|entry| {
+            entry.last_used = now;
+            Arc::clone(&entry.value)
+        })
+    }
+
+    /// Evict least recently used, unpinned entries until `incoming_bytes`
+    /// fits within the configured limits.
+    fn make_room(&self, state: &mut CacheState<T>, incoming: &str, incoming_bytes: u64) {
+        loop {
+            let over_count = self
+                .config
+                .max_engines
+                .map(|max| state.entries.len() + 1 > max)
+                .unwrap_or(false);
+            let over_bytes = self
+                .config
+                .max_bytes
+                .map(|max| state.total_bytes() + incoming_bytes > max)
+                .unwrap_or(false);
+
+            if !over_count && !over_bytes {
+                return;
+            }
+
+            let victim = state
+                .entries
+                .iter()
+                .filter(|(id, _)| !self.config.pinned.contains(*id) && id.as_str() != incoming)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(id, _)| id.clone());
+
+            match victim {
+                Some(id) => {
+                    let entry = state.entries.remove(&id).unwrap();
+                    self.counters.evictions.fetch_add(1, Ordering::Relaxed);
+                    tracing::info!(
+                        "Evicted engine '{}' (~{} MB) to make room for '{}'",
+                        id,
+                        entry.bytes / (1024 * 1024),
+                        incoming
+                    );
+                }
+                None => {
+                    // Only pinned engines remain; allow going over budget
+                    tracing::warn!(
+                        "Engine cache over budget loading '{}': nothing left to evict",
+                        incoming
+                    );
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// A key's load lock, forgotten on drop once nobody else is waiting on it
+struct LoadLock<'a> {
+    locks: &'a Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    key: &'a str,
+    lock: Arc<Mutex<()>>,
+}
+
+impl Drop for LoadLock<'_> {
+    fn drop(&mut self) {
+        let mut locks = self.locks.lock().unwrap();
+        // The map holds one reference and we hold the other
+        if Arc::strong_count(&self.lock) <= 2 {
+            locks.remove(self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::thread;
+    use std::time::Duration;
+
+    fn load(value: u32, bytes: u64) -> impl FnOnce() -> Result<(u32, u64), AppError> {
+        move || Ok((value, bytes))
+    }
+
+    #[test]
+    fn caches_loaded_values() {
+        let cache = EngineCache::new(EngineCacheConfig::default());
+        assert_eq!(*cache.get_or_load("a", load(1, 10)).unwrap(), 1);
+        assert_eq!(*cache.get_or_load("a", load(2, 10)).unwrap(), 1);
+
+        let stats = cache.stats();
+        assert_eq!(stats.loads, 1);
+        assert_eq!(stats.hits, 1);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_over_count() {
+        let cache = EngineCache::new(EngineCacheConfig {
+            max_engines: Some(2),
+            ..Default::default()
+        });
+        cache.get_or_load("a", load(1, 10)).unwrap();
+        cache.get_or_load("b", load(2, 10)).unwrap();
+        cache.get_or_load("a", load(1, 10)).unwrap();
+        cache.get_or_load("c", load(3, 10)).unwrap();
+
+        assert!(cache.is_loaded("a"));
+        assert!(!cache.is_loaded("b"));
+        assert!(cache.is_loaded("c"));
+        assert_eq!(cache.stats().evictions, 1);
+    }
+
+    #[test]
+    fn evicts_to_fit_byte_budget() {
+        let cache = EngineCache::new(EngineCacheConfig {
+            max_bytes: Some(100),
+            ..Default::default()
+        });
+        cache.get_or_load("a", load(1, 40)).unwrap();
+        cache.get_or_load("b", load(2, 40)).unwrap();
+        cache.get_or_load("c", load(3, 80)).unwrap();
+
+        let stats = cache.stats();
+        assert_eq!(stats.loaded.len(), 1);
+        assert_eq!(stats.total_bytes, 80);
+    }
+
+    #[test]
+    fn never_evicts_pinned() {
+        let cache = EngineCache::new(EngineCacheConfig {
+            max_engines: Some(1),
+            pinned: ["a".to_string()].into_iter().collect(),
+            ..Default::default()
+        });
+        cache.get_or_load("a", load(1, 10)).unwrap();
+        cache.get_or_load("b", load(2, 10)).unwrap();
+        cache.get_or_load("c", load(3, 10)).unwrap();
+
+        assert!(cache.is_loaded("a"));
+        assert!(!cache.is_loaded("b"));
+        assert!(cache.is_loaded("c"));
+    }
+
+    #[test]
+    fn remove_forces_reload() {
+        let cache = EngineCache::new(EngineCacheConfig::default());
+        cache.get_or_load("a", load(1, 10)).unwrap();
+        assert!(cache.remove("a"));
+        assert!(!cache.remove("a"));
+        assert_eq!(*cache.get_or_load("a", load(2, 10)).unwrap(), 2);
+        assert_eq!(cache.stats().loads, 2);
+    }
+
+    #[test]
+    fn peek_neither_loads_nor_touches() {
+        let cache = EngineCache::new(EngineCacheConfig {
+            max_engines: Some(2),
+            ..Default::default()
+        });
+        assert!(cache.peek("a").is_none());
+        cache.get_or_load("a", load(1, 10)).unwrap();
+        cache.get_or_load("b", load(2, 10)).unwrap();
+        assert_eq!(*cache.peek("a").unwrap(), 1);
+
+        // "a" is still the least recently used entry
+        cache.get_or_load("c", load(3, 10)).unwrap();
+        assert!(!cache.is_loaded("a"));
+        assert_eq!(cache.stats().loads, 3);
+    }
+
+    #[test]
+    fn failed_load_is_not_cached() {
+        let cache: EngineCache<u32> = EngineCache::new(EngineCacheConfig::default());
+        let result = cache.get_or_load("a", || Err(AppError::VoiceNotFound("a".into())));
+        assert!(result.is_err());
+        assert!(!cache.is_loaded("a"));
+        assert_eq!(cache.stats().load_failures, 1);
+    }
+
+    #[test]
+    fn panicked_load_does_not_block_waiting_callers() {
+        let cache = Arc::new(EngineCache::<u32>::new(EngineCacheConfig::default()));
+
+        let panicking = Arc::clone(&cache);
+        let first = thread::spawn(move || {
+            let waiters = Arc::clone(&panicking);
+            panicking.get_or_load("a", move || {
+                // Panic, poisoning the key's lock, once the second caller waits on it
+                while Arc::strong_count(&waiters.load_locks.lock().unwrap()["a"]) < 3 {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                panic!("session creation failed")
+            })
+        });
+
+        let waiting = Arc::clone(&cache);
+        let second = thread::spawn(move || *waiting.get_or_load("a", load(1, 10)).unwrap());
+
+        assert!(first.join().is_err());
+        assert_eq!(second.join().unwrap(), 1);
+        assert!(cache.load_locks.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn concurrent_first_requests_load_once() {
+        let cache = Arc::new(EngineCache::new(EngineCacheConfig::default()));
+        let load_count = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let cache = Arc::clone(&cache);
+                let load_count = Arc::clone(&load_count);
+                thread::spawn(move || {
+                    cache
+                        .get_or_load("a", || {
+                            load_count.fetch_add(1, Ordering::SeqCst);
+                            thread::sleep(Duration::from_millis(50));
+                            Ok((1u32, 10))
+                        })
+                        .unwrap();
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(load_count.load(Ordering::SeqCst), 1);
+        assert!(cache.load_locks.lock().unwrap().is_empty());
+    }
+}
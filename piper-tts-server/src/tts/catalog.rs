@@ -0,0 +1,496 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::error::AppError;
+use crate::tts::temp_path;
+use crate::tts::voice::LanguageConfig;
+
+/// A voice catalog in Piper's `voices.json` format.
+///
+/// File paths are relative to the directory holding the catalog, so a
+/// mirrored copy of the Piper voices repository works as-is offline.
+#[derive(Debug)]
+pub struct Catalog {
+    base_dir: PathBuf,
+    voices: BTreeMap<String, CatalogVoice>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CatalogVoice {
+    pub key: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub language: Option<LanguageConfig>,
+    #[serde(default)]
+    pub quality: Option<String>,
+    #[serde(default)]
+    pub num_speakers: Option<u32>,
+    pub files: BTreeMap<String, CatalogFile>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CatalogFile {
+    #[serde(default)]
+    pub size_bytes: Option<u64>,
+    #[serde(default)]
+    pub md5_digest: Option<String>,
+    #[serde(default)]
+    pub sha256_digest: Option<String>,
+}
+
+/// Catalog entry as listed by the admin API
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogEntry {
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<String>,
+    pub size_bytes: u64,
+    pub installed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallReport {
+    pub voice: String,
+    pub model_path: PathBuf,
+    pub config_path: PathBuf,
+    pub bytes: u64,
+}
+
+/// The files making up a voice, either loose or packed in an archive
+enum VoiceSource<'a> {
+    Files {
+        model: (&'a str, &'a CatalogFile),
+        config: (&'a str, &'a CatalogFile),
+    },
+    Archive((&'a str, &'a CatalogFile)),
+}
+
+impl Catalog {
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let voices: BTreeMap<String, CatalogVoice> =
+            serde_json::from_reader(File::open(path).map_err(|e| {
+                AppError::BadRequest(format!(
+                    "Cannot open voice catalog {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?)?;
+
+        let base_dir = path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+
+        Ok(Self { base_dir, voices })
+    }
+
+    pub fn entries(&self, voices_dir: &Path) -> Vec<CatalogEntry> {
+        self.voices
+            .values()
+            .map(|voice| CatalogEntry {
+                key: voice.key.clone(),
+                language: voice.language.as_ref().map(|l| l.code.clone()),
+                quality: voice.quality.clone(),
+                size_bytes: voice.files.values().filter_map(|f| f.size_bytes).sum(),
+                installed: voices_dir.join(format!("{}.onnx", voice.key)).exists()
+                    && voices_dir.join(format!("{}.onnx.json", voice.key)).exists(),
+            })
+            .collect()
+    }
+
+    /// Install a voice into `voices_dir`, verifying sizes and checksums.
+    ///
+    /// Files are written to temporary names first and renamed into place
+    /// only once every check has passed, so a failed install never leaves
+    /// a half-written voice behind.
+    pub fn install(&self, voice_key: &str, voices_dir: &Path) -> Result<InstallReport, AppError> {
+        let voice = self
+            .voices
+            .get(voice_key)
+            .ok_or_else(|| AppError::VoiceNotFound(format!("{} (not in catalog)", voice_key)))?;
+
+        // The key becomes a file name in the voices directory
+        if voice.key.is_empty() || voice.key.starts_with('.') || voice.key.contains(['/', '\\']) {
+            return Err(AppError::BadRequest(format!(
+                "Invalid voice key in catalog: '{}'",
+                voice.key
+            )));
+        }
+
+        fs::create_dir_all(voices_dir)?;
+
+        let model_path = voices_dir.join(format!("{}.onnx", voice.key));
+        let config_path = voices_dir.join(format!("{}.onnx.json", voice.key));
+        // Unique per install, so concurrent installs never mix their files
+        let model_tmp = temp_path(&model_path);
+        let config_tmp = temp_path(&config_path);
+
+        let result = match voice_source(voice)? {
+            VoiceSource::Files { model, config } => self
+                .copy_verified(model, &model_tmp)
+                .and_then(|model_bytes| Ok(model_bytes + self.copy_verified(config, &config_tmp)?)),
+            VoiceSource::Archive(archive) => self.extract_archive(archive, &model_tmp, &config_tmp),
+        };
+
+        let bytes = match result {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                let _ = fs::remove_file(&model_tmp);
+                let _ = fs::remove_file(&config_tmp);
+                return Err(e);
+            }
+        };
+
+        // Voices are discovered by their model, so the config goes in first
+        // and the voice only appears once both files are in place
+        if let Err(e) = fs::rename(&config_tmp, &config_path) {
+            let _ = fs::remove_file(&model_tmp);
+            let _ = fs::remove_file(&config_tmp);
+            return Err(e.into());
+        }
+        if let Err(e) = fs::rename(&model_tmp, &model_path) {
+            let _ = fs::remove_file(&model_tmp);
+            let _ = fs::remove_file(&config_path);
+            return Err(e.into());
+        }
+
+        tracing::info!("Installed voice '{}' ({} bytes)", voice.key, bytes);
+
+        Ok(InstallReport {
+            voice: voice.key.clone(),
+            model_path,
+            config_path,
+            bytes,
+        })
+    }
+
+    fn source_path(&self, relative: &str) -> PathBuf {
+        self.base_dir.join(relative)
+    }
+
+    fn copy_verified(
+        &self,
+        (name, file): (&str, &CatalogFile),
+        dest: &Path,
+    ) -> Result<u64, AppError> {
+        let source = File::open(self.source_path(name)).map_err(|e| {
+            AppError::BadRequest(format!("Cannot open catalog file {}: {}", name, e))
+        })?;
+        let mut out = File::create(dest)?;
+        let checked = copy_hashed(source, &mut out)?;
+        out.sync_all()?;
+        checked.verify(name, file)?;
+        Ok(checked.size)
+    }
+
+    fn extract_archive(
+        &self,
+        (name, file): (&str, &CatalogFile),
+        model_tmp: &Path,
+        config_tmp: &Path,
+    ) -> Result<u64, AppError> {
+        let path = self.source_path(name);
+
+        // Verify the archive itself before trusting anything inside it
+        let archive = File::open(&path).map_err(|e| {
+            AppError::BadRequest(format!("Cannot open catalog file {}: {}", name, e))
+        })?;
+        copy_hashed(archive, &mut io::sink())?.verify(name, file)?;
+
+        let archive = File::open(&path)?;
+        let reader: Box<dyn Read> = if name.ends_with(".tar") {
+            Box::new(archive)
+        } else {
+            Box::new(GzDecoder::new(archive))
+        };
+
+        let mut bytes = 0;
+        let (mut have_model, mut have_config) = (false, false);
+        for entry in tar::Archive::new(reader).entries()? {
+            let mut entry = entry?;
+            let entry_name = entry
+                .path()?
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            let (dest, seen) = if entry_name.ends_with(".onnx.json") {
+                (config_tmp, &mut have_config)
+            } else if entry_name.ends_with(".onnx") {
+                (model_tmp, &mut have_model)
+            } else {
+                continue;
+            };
+            // A second model or config would silently replace the first
+            if *seen {
+                return Err(AppError::BadRequest(format!(
+                    "Archive {} contains more than one {} file",
+                    name,
+                    if dest == config_tmp {
+                        ".onnx.json"
+                    } else {
+                        ".onnx"
+                    }
+                )));
+            }
+            *seen = true;
+
+            let mut out = File::create(dest)?;
+            bytes += io::copy(&mut entry, &mut out)?;
+            out.sync_all()?;
+        }
+
+        if !have_model || !have_config {
+            return Err(AppError::BadRequest(format!(
+                "Archive {} does not contain both a .onnx model and its .onnx.json config",
+                name
+            )));
+        }
+
+        Ok(bytes)
+    }
+}
+
+fn voice_source(voice: &CatalogVoice) -> Result<VoiceSource<'_>, AppError> {
+    let find = |suffix: &str| {
+        voice
+            .files
+            .iter()
+            .find(|(name, _)| name.ends_with(suffix))
+            .map(|(name, file)| (name.as_str(), file))
+    };
+
+    match (find(".onnx"), find(".onnx.json")) {
+        (Some(model), Some(config)) => Ok(VoiceSource::Files { model, config }),
+        _ => [".tar.gz", ".tgz", ".tar"]
+            .iter()
+            .find_map(|suffix| find(suffix))
+            .map(VoiceSource::Archive)
+            .ok_or_else(|| {
+                AppError::BadRequest(format!(
+                    "Catalog entry '{}' lists neither .onnx/.onnx.json files nor an archive",
+                    voice.key
+                ))
+            }),
+    }
+}
+
+struct HashedCopy {
+    size: u64,
+    md5: String,
+    sha256: String,
+}
+
+impl HashedCopy {
+    fn verify(&self, name: &str, expected: &CatalogFile) -> Result<(), AppError> {
+        if let Some(size) = expected.size_bytes {
+            if size != self.size {
+                return Err(AppError::BadRequest(format!(
+                    "Size mismatch for {}: expected {} bytes, got {}",
+                    name, size, self.size
+                )));
+            }
+        }
+        if let Some(md5) = &expected.md5_digest {
+            if !md5.eq_ignore_ascii_case(&self.md5) {
+                return Err(AppError::BadRequest(format!("MD5 mismatch for {}", name)));
+            }
+        }
+        if let Some(sha256) = &expected.sha256_digest {
+            if !sha256.eq_ignore_ascii_case(&self.sha256) {
+                return Err(AppError::BadRequest(format!(
+                    "SHA256 mismatch for {}",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn copy_hashed(mut reader: impl Read, writer: &mut impl Write) -> Result<HashedCopy, AppError> {
+    let mut md5 = Md5::new();
+    let mut sha256 = Sha256::new();
+    let mut size = 0u64;
+    let mut buf = vec![0u8; 64 * 1024];
+
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        md5.update(&buf[..n]);
+        sha256.update(&buf[..n]);
+        writer.write_all(&buf[..n])?;
+        size += n as u64;
+    }
+
+    Ok(HashedCopy {
+        size,
+        md5: format!("{:x}", md5.finalize()),
+        sha256: format!("{:x}", sha256.finalize()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: &[u8] = b"not really an onnx model";
+    const CONFIG: &[u8] = br#"{"audio":{"sample_rate":22050}}"#;
+
+    fn md5_hex(data: &[u8]) -> String {
+        format!("{:x}", Md5::digest(data))
+    }
+
+    fn write_catalog(dir: &Path, model_md5: &str) -> PathBuf {
+        let voice_dir = dir.join("en/en_GB/test/medium");
+        fs::create_dir_all(&voice_dir).unwrap();
+        fs::write(voice_dir.join("en_GB-test-medium.onnx"), MODEL).unwrap();
+        fs::write(voice_dir.join("en_GB-test-medium.onnx.json"), CONFIG).unwrap();
+
+        let catalog = serde_json::json!({
+            "en_GB-test-medium": {
+                "key": "en_GB-test-medium",
+                "name": "test",
+                "language": { "code": "en_GB", "family": "en", "region": "GB" },
+                "quality": "medium",
+                "num_speakers": 1,
+                "files": {
+                    "en/en_GB/test/medium/en_GB-test-medium.onnx": {
+                        "size_bytes": MODEL.len(),
+                        "md5_digest": model_md5
+                    },
+                    "en/en_GB/test/medium/en_GB-test-medium.onnx.json": {
+                        "size_bytes": CONFIG.len(),
+                        "md5_digest": md5_hex(CONFIG)
+                    },
+                    "en/en_GB/test/medium/MODEL_CARD": {}
+                }
+            }
+        });
+        let path = dir.join("voices.json");
+        fs::write(&path, catalog.to_string()).unwrap();
+        path
+    }
+
+    #[test]
+    fn installs_voice_from_mirror() {
+        let mirror = tempfile::tempdir().unwrap();
+        let voices = tempfile::tempdir().unwrap();
+        let catalog = Catalog::load(&write_catalog(mirror.path(), &md5_hex(MODEL))).unwrap();
+
+        assert!(!catalog.entries(voices.path())[0].installed);
+
+        let report = catalog.install("en_GB-test-medium", voices.path()).unwrap();
+        assert_eq!(report.bytes, (MODEL.len() + CONFIG.len()) as u64);
+        assert_eq!(fs::read(&report.model_path).unwrap(), MODEL);
+        assert_eq!(fs::read(&report.config_path).unwrap(), CONFIG);
+        assert!(catalog.entries(voices.path())[0].installed);
+    }
+
+    #[test]
+    fn rejects_checksum_mismatch_without_leaving_files() {
+        let mirror = tempfile::tempdir().unwrap();
+        let voices = tempfile::tempdir().unwrap();
+        let catalog = Catalog::load(&write_catalog(mirror.path(), &md5_hex(b"other"))).unwrap();
+
+        assert!(catalog.install("en_GB-test-medium", voices.path()).is_err());
+        assert_eq!(fs::read_dir(voices.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn installs_voice_from_archive() {
+        let mirror = tempfile::tempdir().unwrap();
+        let voices = tempfile::tempdir().unwrap();
+
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, data) in [
+            ("voice/model.onnx", MODEL),
+            ("voice/model.onnx.json", CONFIG),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, data).unwrap();
+        }
+        let archive = builder.into_inner().unwrap();
+        fs::write(mirror.path().join("voice.tar"), &archive).unwrap();
+
+        let digest = format!("{:x}", Sha256::digest(&archive));
+        let catalog = serde_json::json!({
+            "en_GB-test-medium": {
+                "key": "en_GB-test-medium",
+                "files": { "voice.tar": { "sha256_digest": digest } }
+            }
+        });
+        let path = mirror.path().join("voices.json");
+        fs::write(&path, catalog.to_string()).unwrap();
+
+        let report = Catalog::load(&path)
+            .unwrap()
+            .install("en_GB-test-medium", voices.path())
+            .unwrap();
+        assert_eq!(fs::read(report.model_path).unwrap(), MODEL);
+        assert_eq!(fs::read(report.config_path).unwrap(), CONFIG);
+    }
+
+    #[test]
+    fn rejects_archive_with_several_models() {
+        let mirror = tempfile::tempdir().unwrap();
+        let voices = tempfile::tempdir().unwrap();
+
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, data) in [
+            ("voice/model.onnx", MODEL),
+            ("voice/model.onnx.json", CONFIG),
+            ("voice/other.onnx", MODEL),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, data).unwrap();
+        }
+        fs::write(
+            mirror.path().join("voice.tar"),
+            builder.into_inner().unwrap(),
+        )
+        .unwrap();
+        let catalog = serde_json::json!({
+            "en_GB-test-medium": {
+                "key": "en_GB-test-medium",
+                "files": { "voice.tar": {} }
+            }
+        });
+        let path = mirror.path().join("voices.json");
+        fs::write(&path, catalog.to_string()).unwrap();
+
+        let result = Catalog::load(&path)
+            .unwrap()
+            .install("en_GB-test-medium", voices.path());
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        assert_eq!(fs::read_dir(voices.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn unknown_voice_is_not_found() {
+        let mirror = tempfile::tempdir().unwrap();
+        let catalog = Catalog::load(&write_catalog(mirror.path(), "")).unwrap();
+        assert!(matches!(
+            catalog.install("en_US-missing-low", mirror.path()),
+            Err(AppError::VoiceNotFound(_))
+        ));
+    }
+}
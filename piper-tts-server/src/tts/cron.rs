@@ -0,0 +1,255 @@
+//! Five-field cron expressions: `minute hour day-of-month month day-of-week`.
+//!
+//! Fields take `*`, numbers, names (`jan`, `mon`), ranges (`1-5`), lists
+//! (`0,30`) and steps (`*/15`, `9-17/2`); `@hourly`, `@daily`, `@weekly`,
+//! `@monthly` and `@yearly` are shorthands. As in Vixie cron, when both
+//! day fields are restricted a time matches if either does.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration, NaiveDateTime, Timelike};
+
+use crate::error::AppError;
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// How far ahead `next_after` looks; covers leap days
+const MAX_LOOKAHEAD_DAYS: i64 = 366 * 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    /// Sunday is 0
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl FromStr for Cron {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expression = s.trim();
+        let expanded = match expression {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(invalid(
+                expression,
+                "expected five fields: minute hour day-of-month month day-of-week",
+            ));
+        };
+
+        let field = |text: &str, name: &str, min: u32, max: u32, names: &[&str]| {
+            parse_field(text, min, max, names)
+                .map_err(|reason| invalid(expression, &format!("{} field: {}", name, reason)))
+        };
+        let weekdays = field(weekday, "day-of-week", 0, 7, &WEEKDAYS)?;
+        Ok(Cron {
+            expression: expression.to_string(),
+            minutes: field(minute, "minute", 0, 59, &[])?,
+            hours: field(hour, "hour", 0, 23, &[])?,
+            days: field(day, "day-of-month", 1, 31, &[])?,
+            months: field(month, "month", 1, 12, &MONTHS)?,
+            // 7 is Sunday too
+            weekdays: (weekdays | (weekdays >> 7)) & 0x7f,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        })
+    }
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+fn invalid(expression: &str, reason: &str) -> AppError {
+    AppError::BadRequest(format!(
+        "Invalid cron expression '{}': {}",
+        expression, reason
+    ))
+}
+
+/// Bit `n` set for every value `n` the field allows
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |text: &str| -> Result<u32, String> {
+        if let Some(index) = names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(text))
+        {
+            // Names count from the field's minimum: jan is 1, sun is 0
+            return Ok(index as u32 + min);
+        }
+        let n: u32 = text
+            .parse()
+            .map_err(|_| format!("'{}' is not a number", text))?;
+        if !(min..=max).contains(&n) {
+            return Err(format!("{} is outside {}-{}", n, min, max));
+        }
+        Ok(n)
+    };
+
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .ok()
+                    .filter(|&s| s > 0)
+                    .ok_or_else(|| format!("invalid step '{}'", step))?;
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+        let (low, high) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((low, high)) => (value(low)?, value(high)?),
+            // `5/15` means from 5 to the end, every 15
+            None if step.is_some() => (value(range)?, max),
+            None => {
+                let n = value(range)?;
+                (n, n)
+            }
+        };
+        if low > high {
+            return Err(format!("range {}-{} is backwards", low, high));
+        }
+        for n in (low..=high).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << n;
+        }
+    }
+    Ok(bits)
+}
+
+impl Cron {
+    pub fn matches(&self, time: &NaiveDateTime) -> bool {
+        has(self.minutes, time.minute())
+            && has(self.hours, time.hour())
+            && has(self.months, time.month())
+            && self.matches_day(time)
+    }
+
+    fn matches_day(&self, time: &NaiveDateTime) -> bool {
+        let day = has(self.days, time.day());
+        let weekday = has(self.weekdays, time.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// The first matching minute after `time`
+    pub fn next_after(&self, time: &NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = time.date().and_hms_opt(time.hour(), time.minute(), 0)? + Duration::minutes(1);
+        let end = start + Duration::days(MAX_LOOKAHEAD_DAYS);
+        let mut candidate = start;
+        while candidate < end {
+            if !has(self.months, candidate.month()) || !self.matches_day(&candidate) {
+                candidate = candidate.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !has(self.hours, candidate.hour()) {
+                candidate =
+                    candidate.date().and_hms_opt(candidate.hour(), 0, 0)? + Duration::hours(1);
+            } else if !has(self.minutes, candidate.minute()) {
+                candidate += Duration::minutes(1);
+            } else {
+                return Some(candidate);
+            }
+        }
+        None
+    }
+}
+
+fn has(bits: u64, n: u32) -> bool {
+    bits & (1 << n) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, mo, d)
+            .unwrap()
+            .and_hms_opt(h, mi, 0)
+            .unwrap()
+    }
+
+    fn cron(expression: &str) -> Cron {
+        expression.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_fields() {
+        let every_quarter = cron("*/15 9-17 * * mon-fri");
+        // 2026-10-19 is a Monday
+        assert!(every_quarter.matches(&at(2026, 10, 19, 9, 45)));
+        assert!(!every_quarter.matches(&at(2026, 10, 19, 9, 50)));
+        assert!(!every_quarter.matches(&at(2026, 10, 18, 9, 45)));
+        assert!(!every_quarter.matches(&at(2026, 10, 19, 18, 0)));
+
+        assert!(cron("0 12 * * 7").matches(&at(2026, 10, 18, 12, 0)));
+        assert!(cron("30 8 1,15 jan,jul *").matches(&at(2026, 7, 15, 8, 30)));
+        assert!(cron("@hourly").matches(&at(2026, 7, 15, 8, 0)));
+        assert_eq!(cron(" @daily ").to_string(), "@daily");
+
+        for bad in [
+            "",
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "x * * * *",
+        ] {
+            assert!(bad.parse::<Cron>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn matches_either_restricted_day() {
+        // The 1st of the month, or any Friday
+        let cron = cron("0 9 1 * fri");
+        assert!(cron.matches(&at(2026, 10, 1, 9, 0)));
+        assert!(cron.matches(&at(2026, 10, 23, 9, 0)));
+        assert!(!cron.matches(&at(2026, 10, 22, 9, 0)));
+    }
+
+    #[test]
+    fn finds_the_next_run() {
+        let now = at(2026, 10, 18, 14, 35);
+        assert_eq!(
+            cron("*/15 * * * *").next_after(&now),
+            Some(at(2026, 10, 18, 14, 45))
+        );
+        assert_eq!(
+            cron("0 9 * * mon").next_after(&now),
+            Some(at(2026, 10, 19, 9, 0))
+        );
+        assert_eq!(
+            cron("0 0 29 2 *").next_after(&now),
+            Some(at(2028, 2, 29, 0, 0))
+        );
+        assert_eq!(cron("0 0 31 2 *").next_after(&now), None);
+    }
+}
@@ -0,0 +1,890 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::dsl::{chapters, sentences};
+use crate::error::AppError;
+use crate::tts::voice::InferenceParams;
+use crate::tts::{write_atomic, Audio, AudioFormat, Cue, TtsService};
+
+const MANIFEST_FILE: &str = "job.json";
+const INPUT_FILE: &str = "input.txt";
+
+/// Silence inserted between sentences, matching Piper's default
+const SENTENCE_SILENCE: Duration = Duration::from_millis(200);
+
+const MAX_PAUSE_MS: u64 = 60_000;
+
+#[derive(Debug, Clone)]
+pub struct JobConfig {
+    pub dir: PathBuf,
+    /// Jobs waiting to start before new submissions are refused
+    pub max_queued: usize,
+    /// How long finished jobs and their results are kept
+    pub retention: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobState::Queued => "queued",
+            JobState::Running => "running",
+            JobState::Completed => "completed",
+            JobState::Failed => "failed",
+            JobState::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobState::Completed | JobState::Failed | JobState::Cancelled
+        )
+    }
+}
+
+/// Narrate a Markdown or plain-text document chapter by chapter
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudiobookOptions {
+    /// One file per chapter instead of a single file with chapter markers
+    pub split: bool,
+    pub paragraph_pause_ms: u64,
+    pub chapter_pause_ms: u64,
+    /// Voice id or alias for chapter headings; the job's voice if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading_voice: Option<String>,
+    /// Length scale for chapter headings; above 1.0 reads them more slowly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading_length_scale: Option<f32>,
+}
+
+impl Default for AudiobookOptions {
+    fn default() -> Self {
+        Self {
+            split: false,
+            paragraph_pause_ms: 750,
+            chapter_pause_ms: 2000,
+            heading_voice: None,
+            heading_length_scale: None,
+        }
+    }
+}
+
+impl AudiobookOptions {
+    fn validate(&self) -> Result<(), AppError> {
+        for (name, ms) in [
+            ("paragraph_pause_ms", self.paragraph_pause_ms),
+            ("chapter_pause_ms", self.chapter_pause_ms),
+        ] {
+            if ms > MAX_PAUSE_MS {
+                return Err(AppError::BadRequest(format!(
+                    "{} must be at most {}",
+                    name, MAX_PAUSE_MS
+                )));
+            }
+        }
+        if let Some(scale) = self.heading_length_scale {
+            if !(scale.is_finite() && scale > 0.0) {
+                return Err(AppError::BadRequest(
+                    "heading_length_scale must be greater than 0".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where a chapter sits in an audiobook job's output. Times are relative
+/// to the single result file, or to the chapter's own file when split.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ChapterMark {
+    /// 1-based
+    pub index: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+/// A job's manifest, persisted as `{dir}/{id}/job.json`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Job {
+    pub id: String,
+    pub state: JobState,
+    /// Name of the API key that submitted the job; only it (and the admin
+    /// token) may see the job
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    pub format: AudioFormat,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audiobook: Option<AudiobookOptions>,
+    pub sentences_total: usize,
+    pub sentences_done: usize,
+    /// Unix seconds
+    pub created_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Finished chapters of an audiobook job
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chapters: Vec<ChapterMark>,
+}
+
+#[derive(Default)]
+struct JobsState {
+    jobs: HashMap<String, Job>,
+    queue: VecDeque<String>,
+    cancel_requested: HashSet<String>,
+}
+
+/// Long-running synthesis jobs, run sentence by sentence by a pool of
+/// worker threads. Each job is a directory holding its manifest, input
+/// text and result, so queued and finished jobs survive restarts.
+pub struct JobQueue {
+    config: JobConfig,
+    state: Mutex<JobsState>,
+    wakeup: Condvar,
+    next_id: AtomicU64,
+}
+
+impl JobQueue {
+    pub fn open(config: JobConfig) -> Result<Self, AppError> {
+        std::fs::create_dir_all(&config.dir)?;
+
+        let mut state = JobsState::default();
+        for entry in std::fs::read_dir(&config.dir)? {
+            let path = entry?.path().join(MANIFEST_FILE);
+            let job: Job = match std::fs::File::open(&path)
+                .map_err(AppError::from)
+                .and_then(|file| Ok(serde_json::from_reader(file)?))
+            {
+                Ok(job) => job,
+                Err(e) => {
+                    if path.parent().is_some_and(Path::is_dir) {
+                        tracing::warn!("Skipping unreadable job {}: {}", path.display(), e);
+                    }
+                    continue;
+                }
+            };
+            state.jobs.insert(job.id.clone(), job);
+        }
+
+        // Jobs interrupted by a restart start over
+        let mut pending: Vec<&mut Job> = state
+            .jobs
+            .values_mut()
+            .filter(|job| !job.state.is_finished())
+            .collect();
+        pending.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        let mut queue = VecDeque::new();
+        for job in pending {
+            if job.state == JobState::Running {
+                job.state = JobState::Queued;
+                job.sentences_done = 0;
+                job.started_at = None;
+                job.chapters.clear();
+                save_manifest(&config.dir, job)?;
+            }
+            queue.push_back(job.id.clone());
+        }
+        state.queue = queue;
+
+        if !state.jobs.is_empty() {
+            tracing::info!(
+                "Loaded {} jobs ({} queued) from {}",
+                state.jobs.len(),
+                state.queue.len(),
+                config.dir.display()
+            );
+        }
+
+        let jobs = Self {
+            config,
+            state: Mutex::new(state),
+            wakeup: Condvar::new(),
+            next_id: AtomicU64::new(0),
+        };
+        jobs.remove_expired();
+        Ok(jobs)
+    }
+
+    pub fn submit(
+        &self,
+        text: &str,
+        voice: Option<String>,
+        format: AudioFormat,
+        audiobook: Option<AudiobookOptions>,
+        owner: Option<String>,
+    ) -> Result<Job, AppError> {
+        if let Some(options) = &audiobook {
+            options.validate()?;
+        }
+        let sentences_total = plan(text, audiobook.as_ref()).segments.len();
+        if sentences_total == 0 {
+            return Err(AppError::BadRequest("Text cannot be empty".into()));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.queue.len() >= self.config.max_queued {
+            return Err(AppError::Busy(format!(
+                "{} jobs already queued, try again later",
+                state.queue.len()
+            )));
+        }
+
+        let job = Job {
+            id: self.new_id(),
+            state: JobState::Queued,
+            owner,
+            voice,
+            format,
+            audiobook,
+            sentences_total,
+            sentences_done: 0,
+            created_at: unix_now(),
+            started_at: None,
+            finished_at: None,
+            duration_ms: None,
+            error: None,
+            chapters: Vec::new(),
+        };
+
+        let job_dir = self.config.dir.join(&job.id);
+        std::fs::create_dir_all(&job_dir)?;
+        // A job only exists once both files are complete
+        let written = write_atomic(&job_dir.join(INPUT_FILE), text.as_bytes())
+            .map_err(AppError::from)
+            .and_then(|_| save_manifest(&self.config.dir, &job));
+        if let Err(e) = written {
+            let _ = std::fs::remove_dir_all(&job_dir);
+            return Err(e);
+        }
+
+        state.jobs.insert(job.id.clone(), job.clone());
+        state.queue.push_back(job.id.clone());
+        self.wakeup.notify_one();
+
+        tracing::info!("Queued job {} ({} sentences)", job.id, sentences_total);
+        Ok(job)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.state.lock().unwrap().jobs.get(id).cloned()
+    }
+
+    /// All jobs, newest first
+    pub fn list(&self) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self.state.lock().unwrap().jobs.values().cloned().collect();
+        jobs.sort_by(|a, b| (b.created_at, &b.id).cmp(&(a.created_at, &a.id)));
+        jobs
+    }
+
+    /// Cancel a queued job now, or ask a running one to stop after its
+    /// current sentence
+    pub fn cancel(&self, id: &str) -> Result<Job, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let job = state
+            .jobs
+            .get(id)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound(format!("Job '{}' not found", id)))?;
+
+        match job.state {
+            JobState::Queued => {
+                state.queue.retain(|queued| queued != id);
+                let job = state.jobs.get_mut(id).unwrap();
+                job.state = JobState::Cancelled;
+                job.finished_at = Some(unix_now());
+                save_manifest(&self.config.dir, job)?;
+                Ok(job.clone())
+            }
+            JobState::Running => {
+                state.cancel_requested.insert(id.to_string());
+                Ok(job)
+            }
+            _ => Err(AppError::Conflict(format!(
+                "Job '{}' has already finished",
+                id
+            ))),
+        }
+    }
+
+    /// The encoded result of a completed job
+    pub fn result(&self, id: &str) -> Result<(Vec<u8>, AudioFormat), AppError> {
+        let job = self.completed(id)?;
+        if job.audiobook.as_ref().is_some_and(|options| options.split) {
+            return Err(AppError::Conflict(format!(
+                "Job '{}' was split into chapters; fetch /api/jobs/{}/chapters/{{n}}",
+                id, id
+            )));
+        }
+        let audio = std::fs::read(result_path(&self.config.dir, &job))?;
+        Ok((audio, job.format))
+    }
+
+    /// One chapter's file from a completed, split audiobook job
+    pub fn chapter(&self, id: &str, index: usize) -> Result<(Vec<u8>, AudioFormat), AppError> {
+        let job = self.completed(id)?;
+        let file = job
+            .chapters
+            .iter()
+            .find(|chapter| chapter.index == index)
+            .and_then(|chapter| chapter.file.as_ref())
+            .ok_or_else(|| {
+                AppError::NotFound(format!("Job '{}' has no chapter file {}", id, index))
+            })?;
+        let audio = std::fs::read(self.config.dir.join(&job.id).join(file))?;
+        Ok((audio, job.format))
+    }
+
+    fn completed(&self, id: &str) -> Result<Job, AppError> {
+        let job = self
+            .get(id)
+            .ok_or_else(|| AppError::NotFound(format!("Job '{}' not found", id)))?;
+        if job.state != JobState::Completed {
+            return Err(AppError::Conflict(format!(
+                "Job '{}' is {}, not completed",
+                id,
+                job.state.as_str()
+            )));
+        }
+        Ok(job)
+    }
+
+    /// Take queued jobs and run them, forever; one call per worker thread
+    pub fn run_worker(&self, tts: &TtsService) {
+        loop {
+            let mut job = {
+                let mut state = self.state.lock().unwrap();
+                loop {
+                    if let Some(id) = state.queue.pop_front() {
+                        if let Some(job) = state.jobs.get_mut(&id) {
+                            job.state = JobState::Running;
+                            job.started_at = Some(unix_now());
+                            break job.clone();
+                        }
+                    } else {
+                        state = self.wakeup.wait(state).unwrap();
+                    }
+                }
+            };
+            if let Err(e) = save_manifest(&self.config.dir, &job) {
+                tracing::warn!("Failed to save job {}: {}", job.id, e);
+            }
+
+            match self.execute(&mut job, tts) {
+                Ok(true) => job.state = JobState::Completed,
+                Ok(false) => job.state = JobState::Cancelled,
+                Err(e) => {
+                    tracing::warn!("Job {} failed: {}", job.id, e);
+                    job.state = JobState::Failed;
+                    job.error = Some(e.to_string());
+                }
+            }
+            job.finished_at = Some(unix_now());
+            tracing::info!("Job {} {}", job.id, job.state.as_str());
+            self.update(&job);
+            self.state.lock().unwrap().cancel_requested.remove(&job.id);
+
+            self.remove_expired();
+        }
+    }
+
+    /// Synthesize a job's sentences; Ok(false) if it was cancelled
+    fn execute(&self, job: &mut Job, tts: &TtsService) -> Result<bool, AppError> {
+        let job_dir = self.config.dir.join(&job.id);
+        let text = std::fs::read_to_string(job_dir.join(INPUT_FILE))?;
+        let plan = plan(&text, job.audiobook.as_ref());
+        job.sentences_total = plan.segments.len();
+        job.chapters.clear();
+
+        let options = job.audiobook.clone();
+        let split = options.as_ref().is_some_and(|options| options.split);
+        let heading_voice = options
+            .as_ref()
+            .and_then(|options| options.heading_voice.clone())
+            .or_else(|| job.voice.clone());
+        let heading_params = InferenceParams {
+            length_scale: options
+                .as_ref()
+                .and_then(|options| options.heading_length_scale),
+            ..Default::default()
+        };
+
+        // Headings in another voice are brought to the narrator's rate
+        let sample_rate = tts.sample_rate(job.voice.as_deref())?;
+
+        let mut combined: Option<Audio> = None;
+        // The chapter being narrated, and the sample it starts at
+        let mut chapter: Option<(usize, usize)> = None;
+        for segment in &plan.segments {
+            if self
+                .state
+                .lock()
+                .unwrap()
+                .cancel_requested
+                .contains(&job.id)
+            {
+                return Ok(false);
+            }
+
+            let audio = if segment.heading {
+                tts.synthesize_with(&segment.text, heading_voice.as_deref(), &heading_params)?
+                    .resample(sample_rate)
+            } else {
+                tts.synthesize(&segment.text, job.voice.as_deref())?
+            };
+
+            let starts_chapter =
+                options.is_some() && chapter.map(|(index, _)| index) != Some(segment.chapter);
+            if starts_chapter {
+                if let Some((index, start)) = chapter {
+                    self.close_chapter(job, &plan, index, start, &mut combined, split)?;
+                }
+            }
+            let start = append(&mut combined, audio, segment.pause)?;
+            if starts_chapter {
+                chapter = Some((segment.chapter, start));
+            }
+
+            job.sentences_done += 1;
+            self.update(job);
+        }
+        if let Some((index, start)) = chapter {
+            self.close_chapter(job, &plan, index, start, &mut combined, split)?;
+        }
+
+        if split {
+            job.duration_ms = Some(job.chapters.iter().map(|c| c.end_ms - c.start_ms).sum());
+            return Ok(true);
+        }
+
+        let audio = combined.ok_or_else(|| AppError::BadRequest("Job has no text".into()))?;
+        let encoded = if job.format == AudioFormat::Wav && !job.chapters.is_empty() {
+            let cues: Vec<Cue> = job
+                .chapters
+                .iter()
+                .map(|chapter| Cue {
+                    sample: (chapter.start_ms * audio.sample_rate as u64 / 1000) as u32,
+                    label: chapter_label(chapter),
+                })
+                .collect();
+            audio.encode_wav_with_cues(&cues)?
+        } else {
+            audio.encode(job.format)?
+        };
+        write_atomic(&result_path(&self.config.dir, job), &encoded)?;
+        job.duration_ms = Some(audio.duration().as_millis() as u64);
+        Ok(true)
+    }
+
+    /// Record a finished chapter, writing it to its own file when splitting
+    fn close_chapter(
+        &self,
+        job: &mut Job,
+        plan: &Plan,
+        index: usize,
+        start: usize,
+        combined: &mut Option<Audio>,
+        split: bool,
+    ) -> Result<(), AppError> {
+        let Some(audio) = combined.as_ref() else {
+            return Ok(());
+        };
+        let millis = |samples: usize| samples as u64 * 1000 / audio.sample_rate.max(1) as u64;
+        let mut mark = ChapterMark {
+            index: index + 1,
+            title: plan.titles.get(index).cloned().flatten(),
+            start_ms: millis(start),
+            end_ms: millis(audio.samples.len()),
+            file: None,
+        };
+
+        if split {
+            let audio = combined.take().unwrap();
+            let file = format!("chapter-{:03}.{}", mark.index, job.format.extension());
+            write_atomic(
+                &self.config.dir.join(&job.id).join(&file),
+                &audio.encode(job.format)?,
+            )?;
+            mark.file = Some(file);
+        }
+        job.chapters.push(mark);
+        Ok(())
+    }
+
+    /// Store a worker's copy of a job in memory and on disk
+    fn update(&self, job: &Job) {
+        self.state
+            .lock()
+            .unwrap()
+            .jobs
+            .insert(job.id.clone(), job.clone());
+        if let Err(e) = save_manifest(&self.config.dir, job) {
+            tracing::warn!("Failed to save job {}: {}", job.id, e);
+        }
+    }
+
+    /// Delete finished jobs older than the retention period
+    fn remove_expired(&self) {
+        let cutoff = unix_now().saturating_sub(self.config.retention.as_secs());
+        let mut state = self.state.lock().unwrap();
+        let expired: Vec<String> = state
+            .jobs
+            .values()
+            .filter(|job| job.state.is_finished() && job.finished_at.unwrap_or(0) < cutoff)
+            .map(|job| job.id.clone())
+            .collect();
+
+        for id in expired {
+            state.jobs.remove(&id);
+            if let Err(e) = std::fs::remove_dir_all(self.config.dir.join(&id)) {
+                tracing::warn!("Failed to remove expired job {}: {}", id, e);
+            }
+        }
+    }
+
+    /// Time-ordered ids that stay unique across restarts
+    fn new_id(&self) -> String {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let seq = self.next_id.fetch_add(1, Ordering::Relaxed);
+        format!("{:x}-{:x}", nanos, seq)
+    }
+}
+
+/// One synthesis step of a job
+struct Segment {
+    text: String,
+    heading: bool,
+    chapter: usize,
+    /// Silence before this segment, unless it starts the output
+    pause: Duration,
+}
+
+struct Plan {
+    segments: Vec<Segment>,
+    /// Audiobook chapter titles, by chapter index
+    titles: Vec<Option<String>>,
+}
+
+/// Break a job's text into sentences, or into chapter headings and
+/// paragraphs for an audiobook
+fn plan(text: &str, audiobook: Option<&AudiobookOptions>) -> Plan {
+    let Some(options) = audiobook else {
+        let segments = sentences::split(text)
+            .into_iter()
+            .map(|text| Segment {
+                text,
+                heading: false,
+                chapter: 0,
+                pause: SENTENCE_SILENCE,
+            })
+            .collect();
+        return Plan {
+            segments,
+            titles: Vec::new(),
+        };
+    };
+
+    let paragraph_pause = Duration::from_millis(options.paragraph_pause_ms);
+    let mut segments = Vec::new();
+    let mut titles = Vec::new();
+    let mut pause = Duration::ZERO;
+    for (index, chapter) in chapters::parse(text).into_iter().enumerate() {
+        if index > 0 {
+            pause = Duration::from_millis(options.chapter_pause_ms);
+        }
+        if let Some(title) = &chapter.title {
+            segments.push(Segment {
+                text: title.clone(),
+                heading: true,
+                chapter: index,
+                pause,
+            });
+            pause = paragraph_pause;
+        }
+        for paragraph in &chapter.paragraphs {
+            for text in sentences::split(paragraph) {
+                segments.push(Segment {
+                    text,
+                    heading: false,
+                    chapter: index,
+                    pause,
+                });
+                pause = SENTENCE_SILENCE;
+            }
+            pause = paragraph_pause;
+        }
+        titles.push(chapter.title);
+    }
+    Plan { segments, titles }
+}
+
+/// Append `audio` after `pause`, returning the sample it starts at
+fn append(combined: &mut Option<Audio>, audio: Audio, pause: Duration) -> Result<usize, AppError> {
+    let Some(combined) = combined.as_mut() else {
+        *combined = Some(audio);
+        return Ok(0);
+    };
+    if combined.sample_rate != audio.sample_rate {
+        return Err(AppError::BadRequest(format!(
+            "Can't join audio at {} Hz and {} Hz; use voices with the same sample rate",
+            combined.sample_rate, audio.sample_rate
+        )));
+    }
+    let gap = (combined.sample_rate as f64 * pause.as_secs_f64()) as usize;
+    combined.samples.extend(std::iter::repeat_n(0.0, gap));
+    let start = combined.samples.len();
+    combined.samples.extend(audio.samples);
+    Ok(start)
+}
+
+fn chapter_label(chapter: &ChapterMark) -> String {
+    chapter
+        .title
+        .clone()
+        .unwrap_or_else(|| format!("Chapter {}", chapter.index))
+}
+
+fn save_manifest(dir: &Path, job: &Job) -> Result<(), AppError> {
+    let json = serde_json::to_vec_pretty(job)?;
+    write_atomic(&dir.join(&job.id).join(MANIFEST_FILE), &json)?;
+    Ok(())
+}
+
+fn result_path(dir: &Path, job: &Job) -> PathBuf {
+    dir.join(&job.id)
+        .join(format!("result.{}", job.format.extension()))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(dir: &Path, max_queued: usize) -> JobQueue {
+        JobQueue::open(JobConfig {
+            dir: dir.to_path_buf(),
+            max_queued,
+            retention: Duration::from_secs(3600),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn submitted_jobs_are_queued_and_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let jobs = open(dir.path(), 10);
+        let job = jobs
+            .submit(
+                "First sentence. Second one!",
+                Some("narrator".into()),
+                AudioFormat::Wav,
+                None,
+                Some("kiosk".into()),
+            )
+            .unwrap();
+        assert_eq!(job.state, JobState::Queued);
+        assert_eq!(job.sentences_total, 2);
+
+        let reopened = open(dir.path(), 10);
+        let reloaded = reopened.get(&job.id).unwrap();
+        assert_eq!(reloaded.voice.as_deref(), Some("narrator"));
+        assert_eq!(reloaded.owner.as_deref(), Some("kiosk"));
+        assert_eq!(reopened.state.lock().unwrap().queue, vec![job.id.clone()]);
+    }
+
+    #[test]
+    fn interrupted_jobs_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let jobs = open(dir.path(), 10);
+        let mut job = jobs
+            .submit("One. Two. Three.", None, AudioFormat::Pcm, None, None)
+            .unwrap();
+        job.state = JobState::Running;
+        job.sentences_done = 2;
+        save_manifest(dir.path(), &job).unwrap();
+
+        let reopened = open(dir.path(), 10);
+        let job = reopened.get(&job.id).unwrap();
+        assert_eq!(job.state, JobState::Queued);
+        assert_eq!(job.sentences_done, 0);
+        assert_eq!(reopened.state.lock().unwrap().queue.len(), 1);
+    }
+
+    #[test]
+    fn queue_is_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let jobs = open(dir.path(), 1);
+        jobs.submit("One.", None, AudioFormat::Wav, None, None)
+            .unwrap();
+        assert!(matches!(
+            jobs.submit("Two.", None, AudioFormat::Wav, None, None),
+            Err(AppError::Busy(_))
+        ));
+    }
+
+    #[test]
+    fn cancels_queued_jobs_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let jobs = open(dir.path(), 10);
+        let job = jobs
+            .submit("One.", None, AudioFormat::Wav, None, None)
+            .unwrap();
+
+        assert_eq!(jobs.cancel(&job.id).unwrap().state, JobState::Cancelled);
+        assert!(jobs.state.lock().unwrap().queue.is_empty());
+        assert!(matches!(jobs.cancel(&job.id), Err(AppError::Conflict(_))));
+        assert!(matches!(jobs.result(&job.id), Err(AppError::Conflict(_))));
+        assert!(matches!(jobs.cancel("nope"), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn removes_expired_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let jobs = JobQueue::open(JobConfig {
+            dir: dir.path().to_path_buf(),
+            max_queued: 10,
+            retention: Duration::ZERO,
+        })
+        .unwrap();
+        let mut job = jobs
+            .submit("One.", None, AudioFormat::Wav, None, None)
+            .unwrap();
+        job.state = JobState::Completed;
+        job.finished_at = Some(1);
+        jobs.update(&job);
+
+        jobs.remove_expired();
+        assert!(jobs.get(&job.id).is_none());
+        assert!(!dir.path().join(&job.id).exists());
+    }
+
+    #[test]
+    fn rejects_empty_text() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(open(dir.path(), 10)
+            .submit(" \n ", None, AudioFormat::Wav, None, None)
+            .is_err());
+    }
+
+    #[test]
+    fn plans_audiobook_chapters() {
+        let options = AudiobookOptions {
+            paragraph_pause_ms: 500,
+            chapter_pause_ms: 1500,
+            ..Default::default()
+        };
+        let plan = plan(
+            "Intro.\n\n# One\n\nFirst. Second.\n\nThird.\n\n# Two\n\nFourth.",
+            Some(&options),
+        );
+        let steps: Vec<_> = plan
+            .segments
+            .iter()
+            .map(|s| (s.text.as_str(), s.heading, s.chapter, s.pause.as_millis()))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("Intro.", false, 0, 0),
+                ("One", true, 1, 1500),
+                ("First.", false, 1, 500),
+                ("Second.", false, 1, 200),
+                ("Third.", false, 1, 500),
+                ("Two", true, 2, 1500),
+                ("Fourth.", false, 2, 500),
+            ]
+        );
+        assert_eq!(
+            plan.titles,
+            vec![None, Some("One".into()), Some("Two".into())]
+        );
+    }
+
+    #[test]
+    fn validates_audiobook_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let jobs = open(dir.path(), 10);
+        let options = AudiobookOptions {
+            chapter_pause_ms: MAX_PAUSE_MS + 1,
+            ..Default::default()
+        };
+        assert!(jobs
+            .submit(
+                "# One\n\nText.",
+                None,
+                AudioFormat::Wav,
+                Some(options),
+                None
+            )
+            .is_err());
+
+        let job = jobs
+            .submit(
+                "# One\n\nText.",
+                None,
+                AudioFormat::Wav,
+                Some(Default::default()),
+                None,
+            )
+            .unwrap();
+        assert_eq!(job.sentences_total, 2);
+        assert!(matches!(
+            jobs.chapter(&job.id, 1),
+            Err(AppError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn joins_audio_with_pauses() {
+        let mut combined = None;
+        let clip = |rate| Audio {
+            samples: vec![1.0; 10],
+            sample_rate: rate,
+        };
+        assert_eq!(
+            append(&mut combined, clip(1000), Duration::from_secs(1)).unwrap(),
+            0
+        );
+        assert_eq!(
+            append(&mut combined, clip(1000), Duration::from_millis(5)).unwrap(),
+            15
+        );
+        assert_eq!(combined.as_ref().unwrap().samples.len(), 25);
+        assert!(append(&mut combined, clip(2000), Duration::ZERO).is_err());
+    }
+}
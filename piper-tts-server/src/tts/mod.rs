@@ -1,35 +1,274 @@
+pub mod alias;
+pub mod audio;
+pub mod audio_cache;
+pub mod batch;
+pub mod cache;
+pub mod catalog;
+pub mod cron;
+pub mod jobs;
 pub mod piper;
+pub mod playback;
+pub mod prompts;
+pub mod schedules;
+pub mod templates;
+pub mod validate;
 pub mod voice;
+pub mod watcher;
+pub mod zip;
 
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use axum::body::Bytes;
+use sha2::{Digest, Sha256};
+use tokio::sync::broadcast;
 
 use crate::dsl;
 use crate::error::AppError;
+use crate::tts::voice::{InferenceParams, VoiceConfig};
 
+pub use alias::{ResolvedVoice, VoiceAliases};
+pub use audio::{Audio, AudioFormat, Cue};
+pub use audio_cache::{AudioCache, AudioCacheConfig, AudioCacheStats, AudioKey, PurgeReport};
+pub use batch::BatchItem;
+pub use cache::{EngineCache, EngineCacheConfig, EngineCacheStats};
+pub use catalog::{Catalog, CatalogEntry, InstallReport};
+pub use jobs::{AudiobookOptions, Job, JobConfig, JobQueue};
 pub use piper::PiperEngine;
-pub use voice::{Voice, VoiceInfo};
+pub use playback::PlaybackQueue;
+pub use prompts::{Prompt, PromptLibrary, PromptStatus};
+pub use schedules::{Schedule, ScheduleStatus, Scheduler};
+pub use templates::{Template, TemplateInfo, TemplateLibrary};
+pub use validate::{Check, VoiceStatus};
+pub use voice::{Voice, VoiceDetail, VoiceInfo};
+pub use watcher::{VoiceEvent, VoiceEventKind, VoiceWatcher};
+
+/// How voices are run and how input text is interpreted
+#[derive(Debug, Clone)]
+pub struct EngineOptions {
+    /// ONNX Runtime intra-op threads per voice session
+    pub intra_threads: usize,
+    /// Interpret DSL tags; when false text is passed to espeak as written
+    pub dsl: bool,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        Self {
+            intra_threads: 4,
+            dsl: true,
+        }
+    }
+}
+
+/// Encoded audio from [`TtsService::speak_cached`]
+pub struct CachedAudio {
+    pub audio: Bytes,
+    /// Cache key, usable as an HTTP entity tag
+    pub etag: String,
+    pub hit: bool,
+}
 
 pub struct TtsService {
     voices_dir: PathBuf,
-    engines: RwLock<HashMap<String, Arc<PiperEngine>>>,
+    engines: EngineCache<PiperEngine>,
+    voice_events: broadcast::Sender<VoiceEvent>,
+    aliases: VoiceAliases,
+    options: EngineOptions,
+    audio_cache: AudioCache,
+    /// Model checksums by voice id, with the (mtime, size) they were taken at
+    model_checksums: Mutex<HashMap<String, (SystemTime, u64, String)>>,
 }
 
 impl TtsService {
-    pub fn new(voices_dir: PathBuf) -> Self {
+    pub fn new(
+        voices_dir: PathBuf,
+        cache_config: EngineCacheConfig,
+        aliases: VoiceAliases,
+        options: EngineOptions,
+    ) -> Self {
         Self {
             voices_dir,
-            engines: RwLock::new(HashMap::new()),
+            engines: EngineCache::new(cache_config),
+            voice_events: broadcast::channel(64).0,
+            aliases,
+            options,
+            audio_cache: AudioCache::disabled(),
+            model_checksums: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Serve repeated requests from `cache` instead of re-synthesizing
+    pub fn with_audio_cache(mut self, cache: AudioCache) -> Self {
+        self.audio_cache = cache;
+        self
+    }
+
+    pub fn voices_dir(&self) -> &Path {
+        &self.voices_dir
+    }
+
+    /// Synthesize `text` to WAV with a voice id or alias (the default alias if `None`)
+    pub fn speak(&self, text: &str, voice: Option<&str>) -> Result<Vec<u8>, AppError> {
+        self.synthesize(text, voice)?.encode(AudioFormat::Wav)
+    }
+
+    /// Synthesize `text` to unencoded samples
+    pub fn synthesize(&self, text: &str, voice: Option<&str>) -> Result<Audio, AppError> {
+        self.synthesize_with(text, voice, &InferenceParams::default())
+    }
+
+    /// Synthesize `text` with `overrides` taking precedence over the voice's
+    /// alias and model parameters
+    pub fn synthesize_with(
+        &self,
+        text: &str,
+        voice: Option<&str>,
+        overrides: &InferenceParams,
+    ) -> Result<Audio, AppError> {
+        let mut resolved = self.resolve_voice(voice)?;
+        resolved.params = overrides.or(&resolved.params);
+        self.render(text, &resolved)
+    }
+
+    /// Synthesize `text` at a multiple of the voice's normal speaking rate
+    pub fn synthesize_at_speed(
+        &self,
+        text: &str,
+        voice: Option<&str>,
+        speed: f32,
+    ) -> Result<Audio, AppError> {
+        let mut resolved = self.resolve_voice(voice)?;
+        let config = Voice::load(&self.voices_dir, &resolved.voice_id)?.config;
+        let base = resolved
+            .params
+            .apply(&config.inference.unwrap_or_default())
+            .length_scale;
+        resolved.params.length_scale = Some(base / speed);
+        self.render(text, &resolved)
+    }
+
+    /// Synthesize and encode `text`, reusing earlier results for the same
+    /// text, voice, parameters, format and model.
+    pub fn speak_cached(
+        &self,
+        text: &str,
+        voice: Option<&str>,
+        format: AudioFormat,
+    ) -> Result<CachedAudio, AppError> {
+        let resolved = self.resolve_voice(voice)?;
+        let text = self.expand_clock(text, &resolved.voice_id)?;
+        let etag = self.audio_key(&text, &resolved, format)?;
+
+        if let Some(audio) = self.audio_cache.get(&etag, format) {
+            return Ok(CachedAudio {
+                audio,
+                etag,
+                hit: true,
+            });
+        }
+
+        let audio = Bytes::from(self.render(&text, &resolved)?.encode(format)?);
+        self.audio_cache.insert(&etag, format, audio.clone());
+        Ok(CachedAudio {
+            audio,
+            etag,
+            hit: false,
+        })
+    }
+
+    /// The entity tag `speak_cached` would return, without synthesizing
+    pub fn audio_etag(
+        &self,
+        text: &str,
+        voice: Option<&str>,
+        format: AudioFormat,
+    ) -> Result<String, AppError> {
+        let resolved = self.resolve_voice(voice)?;
+        let text = self.expand_clock(text, &resolved.voice_id)?;
+        self.audio_key(&text, &resolved, format)
+    }
+
+    pub fn audio_cache_stats(&self) -> AudioCacheStats {
+        self.audio_cache.stats()
+    }
+
+    pub fn purge_audio_cache(&self) -> PurgeReport {
+        self.audio_cache.purge()
+    }
+
+    fn audio_key(
+        &self,
+        text: &str,
+        resolved: &ResolvedVoice,
+        format: AudioFormat,
+    ) -> Result<String, AppError> {
+        let voice = Voice::load(&self.voices_dir, &resolved.voice_id)?;
+        let inference = resolved
+            .params
+            .apply(&voice.config.inference.clone().unwrap_or_default());
+        let model_checksum = self.model_checksum(&voice)?;
+
+        Ok(AudioKey {
+            text,
+            voice_id: &voice.id,
+            speaker_id: piper::DEFAULT_SPEAKER_ID,
+            inference: &inference,
+            format,
+            model_checksum: &model_checksum,
+            dsl: self.options.dsl,
         }
+        .digest())
+    }
+
+    /// SHA-256 of the model file, recomputed only when the file changes
+    fn model_checksum(&self, voice: &Voice) -> Result<String, AppError> {
+        let meta = std::fs::metadata(&voice.model_path)?;
+        let modified = meta.modified()?;
+
+        if let Some((at, size, checksum)) = self.model_checksums.lock().unwrap().get(&voice.id) {
+            if *at == modified && *size == meta.len() {
+                return Ok(checksum.clone());
+            }
+        }
+
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut File::open(&voice.model_path)?, &mut hasher)?;
+        let checksum = format!("{:x}", hasher.finalize());
+
+        self.model_checksums
+            .lock()
+            .unwrap()
+            .insert(voice.id.clone(), (modified, meta.len(), checksum.clone()));
+        Ok(checksum)
     }
 
-    pub fn speak(&self, text: &str, voice_id: &str) -> Result<Vec<u8>, AppError> {
+    /// Run the DSL → phonemize → ONNX pipeline for a resolved voice
+    fn render(&self, text: &str, resolved: &ResolvedVoice) -> Result<Audio, AppError> {
         // 1. Get or load engine
-        let engine = self.get_engine(voice_id)?;
+        let engine = self.get_engine(&resolved.voice_id)?;
+        self.render_with(&engine, text, resolved)
+    }
+
+    fn render_with(
+        &self,
+        engine: &PiperEngine,
+        text: &str,
+        resolved: &ResolvedVoice,
+    ) -> Result<Audio, AppError> {
+        let voice_id = resolved.voice_id.as_str();
 
         // 2. Process DSL
-        let processed = dsl::process(text);
+        let processed = if self.options.dsl {
+            dsl::process(&self.expand_clock(text, voice_id)?)
+        } else {
+            text.to_string()
+        };
 
         // 3. Phonemize
         let voice = Voice::load(&self.voices_dir, voice_id)?;
@@ -45,41 +284,200 @@ impl TtsService {
         let ids = piper::phonemes_to_ids(&phonemes, &voice.config.phoneme_id_map);
 
         // 5. Synthesize
-        let samples = engine.synthesize(&ids)?;
+        let samples = engine.synthesize(&ids, &resolved.params)?;
+
+        Ok(Audio {
+            samples,
+            sample_rate: voice.config.audio.sample_rate,
+        })
+    }
+
+    /// Language code of a voice (id or alias), e.g. `en_GB`
+    pub fn voice_language(&self, voice: Option<&str>) -> Result<String, AppError> {
+        let resolved = self.resolve_voice(voice)?;
+        let voice = Voice::load(&self.voices_dir, &resolved.voice_id)?;
+        Ok(voice_language(&voice.config))
+    }
+
+    /// Output sample rate of a voice (id or alias)
+    pub fn sample_rate(&self, voice: Option<&str>) -> Result<u32, AppError> {
+        let resolved = self.resolve_voice(voice)?;
+        let voice = Voice::load(&self.voices_dir, &resolved.voice_id)?;
+        Ok(voice.config.audio.sample_rate)
+    }
 
-        // 6. Encode WAV
-        let wav = piper::samples_to_wav(&samples, voice.config.audio.sample_rate)?;
+    /// Fill in `[time]` and `[date]` for the voice's language, before
+    /// anything is cached under the text
+    fn expand_clock<'a>(&self, text: &'a str, voice_id: &str) -> Result<Cow<'a, str>, AppError> {
+        if !self.options.dsl || !dsl::clock::has_tags(text) {
+            return Ok(Cow::Borrowed(text));
+        }
+        let voice = Voice::load(&self.voices_dir, voice_id)?;
+        let now = chrono::Local::now().naive_local();
+        Ok(Cow::Owned(
+            dsl::clock::expand(text, &voice_language(&voice.config), now).into_owned(),
+        ))
+    }
 
-        Ok(wav)
+    pub fn resolve_voice(&self, voice: Option<&str>) -> Result<ResolvedVoice, AppError> {
+        self.aliases.resolve(voice)
+    }
+
+    pub fn aliases(&self) -> &VoiceAliases {
+        &self.aliases
     }
 
     fn get_engine(&self, voice_id: &str) -> Result<Arc<PiperEngine>, AppError> {
-        // Check cache
-        {
-            let engines = self.engines.read().unwrap();
-            if let Some(engine) = engines.get(voice_id) {
-                return Ok(Arc::clone(engine));
+        self.engines.get_or_load(voice_id, || {
+            let voice = Voice::load(&self.voices_dir, voice_id)?;
+            // The model file size is a reasonable proxy for resident memory
+            let bytes = std::fs::metadata(&voice.model_path)?.len();
+            let engine = PiperEngine::new(&voice, self.options.intra_threads)?;
+            Ok((engine, bytes))
+        })
+    }
+
+    /// Load a voice and run a short synthesis so the first real request
+    /// doesn't pay for session creation and graph optimisation.
+    pub fn warm_up(&self, voice_id: &str) -> Result<Duration, AppError> {
+        let started = Instant::now();
+        self.speak("Ready.", Some(voice_id))?;
+        Ok(started.elapsed())
+    }
+
+    /// Bring the engine cache in line with a voice's files on disk.
+    ///
+    /// A loaded engine is dropped and, unless the voice was removed, loaded
+    /// again from the new files so it stays warm.
+    pub fn apply_voice_change(&self, voice_id: &str, kind: VoiceEventKind) -> VoiceEvent {
+        let was_loaded = self.engines.remove(voice_id);
+
+        let mut event = VoiceEvent {
+            voice: voice_id.to_string(),
+            kind,
+            reloaded: false,
+            error: None,
+        };
+
+        if was_loaded && kind != VoiceEventKind::Removed {
+            match self.get_engine(voice_id) {
+                Ok(_) => event.reloaded = true,
+                Err(e) => event.error = Some(e.to_string()),
             }
         }
 
-        // Load new engine
-        let voice = Voice::load(&self.voices_dir, voice_id)?;
-        let engine = Arc::new(PiperEngine::new(&voice)?);
+        match &event.error {
+            Some(e) => tracing::error!("Voice '{}' {:?} but reload failed: {}", voice_id, kind, e),
+            None => tracing::info!(
+                "Voice '{}' {:?}{}",
+                voice_id,
+                kind,
+                if event.reloaded {
+                    " (engine reloaded)"
+                } else {
+                    ""
+                }
+            ),
+        }
 
-        // Cache it
-        {
-            let mut engines = self.engines.write().unwrap();
-            engines.insert(voice_id.to_string(), Arc::clone(&engine));
+        // No subscribers is fine
+        let _ = self.voice_events.send(event.clone());
+        event
+    }
+
+    pub fn subscribe_voice_events(&self) -> broadcast::Receiver<VoiceEvent> {
+        self.voice_events.subscribe()
+    }
+
+    /// Check a voice end to end: config, phoneme map, ONNX session, model
+    /// inputs/outputs and a tiny synthesis.
+    pub fn validate_voice(&self, voice_id: &str) -> VoiceStatus {
+        let mut status = VoiceStatus::new(voice_id);
+
+        let voice = match Voice::load(&self.voices_dir, voice_id) {
+            Ok(voice) => {
+                status.pass(Check::Config);
+                voice
+            }
+            Err(e) => {
+                status.fail(Check::Config, e.to_string());
+                return status;
+            }
+        };
+
+        let missing = validate::missing_phoneme_symbols(&voice.config.phoneme_id_map);
+        if missing.is_empty() {
+            status.pass(Check::PhonemeMap);
+        } else {
+            status.fail(
+                Check::PhonemeMap,
+                format!("phoneme_id_map is missing {}", missing.join(", ")),
+            );
+        }
+
+        // Reuse a loaded engine, but never load one into the shared cache:
+        // validating a voice must not evict engines serving real traffic
+        let engine =
+            match self.engines.peek(voice_id).map(Ok).unwrap_or_else(|| {
+                PiperEngine::new(&voice, self.options.intra_threads).map(Arc::new)
+            }) {
+                Ok(engine) => {
+                    status.pass(Check::Session);
+                    engine
+                }
+                Err(e) => {
+                    status.fail(Check::Session, e.to_string());
+                    return status;
+                }
+            };
+
+        let problems = engine.signature().problems();
+        if problems.is_empty() {
+            status.pass(Check::Signature);
+        } else {
+            status.fail(Check::Signature, problems.join("; "));
+            return status;
+        }
+
+        let started = Instant::now();
+        let resolved = ResolvedVoice {
+            voice_id: voice_id.to_string(),
+            params: Default::default(),
+        };
+        match self.render_with(&engine, "Test.", &resolved) {
+            Ok(_) => {
+                status.synthesis_ms = Some(started.elapsed().as_millis() as u64);
+                status.pass(Check::Synthesis);
+            }
+            Err(e) => status.fail(Check::Synthesis, e.to_string()),
         }
 
-        Ok(engine)
+        status
+    }
+
+    pub fn engine_stats(&self) -> EngineCacheStats {
+        self.engines.stats()
     }
 
     pub fn list_voices(&self) -> Result<Vec<VoiceInfo>, AppError> {
         let mut voices = Vec::new();
 
+        for id in self.voice_ids()? {
+            if let Ok(voice) = Voice::load(&self.voices_dir, &id) {
+                voices.push(voice_info(&voice));
+            }
+        }
+
+        Ok(voices)
+    }
+
+    /// Ids of every `.onnx` model in the voices directory, sorted, whether
+    /// or not its config loads
+    pub fn voice_ids(&self) -> Result<Vec<String>, AppError> {
+        let mut ids = Vec::new();
+
         if !self.voices_dir.exists() {
-            return Ok(voices);
+            return Ok(ids);
         }
 
         for entry in std::fs::read_dir(&self.voices_dir)? {
@@ -87,33 +485,110 @@ impl TtsService {
             let path = entry.path();
 
             if path.extension().map(|e| e == "onnx").unwrap_or(false) {
-                let id = path
-                    .file_stem()
-                    .unwrap()
-                    .to_string_lossy()
-                    .to_string();
-
-                if let Ok(voice) = Voice::load(&self.voices_dir, &id) {
-                    let language = voice
-                        .config
-                        .espeak
-                        .as_ref()
-                        .map(|e| e.voice.clone())
-                        .unwrap_or_else(|| "en".to_string());
-
-                    // Parse voice name from ID (e.g., en_GB-alba-medium -> Alba)
-                    let name = parse_voice_name(&id);
-
-                    voices.push(VoiceInfo {
-                        id,
-                        name,
-                        language,
-                    });
-                }
+                let id = path.file_stem().unwrap().to_string_lossy().to_string();
+                ids.push(id);
             }
         }
 
-        Ok(voices)
+        ids.sort();
+
+        Ok(ids)
+    }
+
+    pub fn voice_detail(&self, voice_id: &str) -> Result<VoiceDetail, AppError> {
+        let voice = Voice::load(&self.voices_dir, voice_id)?;
+        let model_size_bytes = std::fs::metadata(&voice.model_path)?.len();
+
+        Ok(VoiceDetail {
+            info: voice_info(&voice),
+            model_size_bytes,
+            loaded: self.engines.is_loaded(voice_id),
+            inference: voice.config.inference.clone().unwrap_or_default(),
+            piper_version: voice.config.piper_version.clone(),
+        })
+    }
+}
+
+/// Ids that become file names: letters, digits, `-`, `_` and `.`, not
+/// starting with a dot
+pub(crate) fn validate_file_id(kind: &str, id: &str) -> Result<(), AppError> {
+    let valid = !id.is_empty()
+        && id.len() <= 128
+        && !id.starts_with('.')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(format!(
+            "Invalid {} id '{}' (use letters, digits, '-', '_' and '.')",
+            kind, id
+        )))
+    }
+}
+
+/// Write via a temporary file and rename, so readers never see partial data
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let tmp = temp_path(path);
+    if let Err(e) = std::fs::write(&tmp, data).and_then(|_| std::fs::rename(&tmp, path)) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// A hidden temporary name next to `path` to write before renaming into
+/// place. It is unique to this process and call, so concurrent writers and
+/// files that differ only in extension never share one.
+pub(crate) fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        file_name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Language code (e.g. `en_GB`), or the espeak voice for older configs
+fn voice_language(config: &VoiceConfig) -> String {
+    config
+        .language
+        .as_ref()
+        .map(|l| l.code.clone())
+        .or_else(|| config.espeak.as_ref().map(|e| e.voice.clone()))
+        .unwrap_or_else(|| "en".to_string())
+}
+
+fn voice_info(voice: &Voice) -> VoiceInfo {
+    let config = &voice.config;
+    let espeak_voice = config.espeak.as_ref().map(|e| e.voice.clone());
+
+    VoiceInfo {
+        id: voice.id.clone(),
+        // Parse voice name from ID (e.g., en_GB-alba-medium -> Alba)
+        name: parse_voice_name(&voice.id),
+        language: espeak_voice.clone().unwrap_or_else(|| "en".to_string()),
+        language_code: voice_language(config),
+        language_family: config.language.as_ref().and_then(|l| l.family.clone()),
+        language_region: config.language.as_ref().and_then(|l| l.region.clone()),
+        language_name: config
+            .language
+            .as_ref()
+            .and_then(|l| l.name_english.clone()),
+        language_name_native: config.language.as_ref().and_then(|l| l.name_native.clone()),
+        country: config
+            .language
+            .as_ref()
+            .and_then(|l| l.country_english.clone()),
+        espeak_voice,
+        dataset: config.dataset.clone(),
+        quality: config.audio.quality.clone(),
+        sample_rate: config.audio.sample_rate,
+        num_speakers: config.num_speakers,
+        speakers: config.speakers(),
     }
 }
 
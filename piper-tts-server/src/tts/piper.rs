@@ -6,41 +6,153 @@ use std::sync::Mutex;
 use hound::{SampleFormat, WavSpec, WavWriter};
 use ort::session::builder::GraphOptimizationLevel;
 use ort::session::Session;
-use ort::value::Value;
+use ort::value::{Outlet, TensorElementType, Value};
 
 use crate::error::AppError;
-use crate::tts::voice::Voice;
+use crate::tts::voice::{InferenceConfig, InferenceParams, Voice};
 
 pub struct PiperEngine {
     session: Mutex<Session>,
-    noise_scale: f32,
-    length_scale: f32,
-    noise_w: f32,
+    inference: InferenceConfig,
+    signature: ModelSignature,
 }
 
+/// Name, element type and rank of a model input or output
+#[derive(Debug, Clone, PartialEq)]
+pub struct TensorSpec {
+    pub name: String,
+    pub ty: Option<TensorElementType>,
+    pub rank: Option<usize>,
+}
+
+impl TensorSpec {
+    fn from_outlet(outlet: &Outlet) -> Self {
+        let dtype = outlet.dtype();
+        Self {
+            name: outlet.name().to_string(),
+            ty: dtype.tensor_type(),
+            rank: dtype.tensor_shape().map(|shape| shape.len()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelSignature {
+    pub inputs: Vec<TensorSpec>,
+    pub outputs: Vec<TensorSpec>,
+}
+
+impl ModelSignature {
+    /// Multi-speaker models take a speaker id (`sid`) input
+    pub fn has_speaker_input(&self) -> bool {
+        self.inputs.iter().any(|i| i.name == "sid")
+    }
+
+    /// Describe every way this model differs from what the engine feeds it
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        let expected_inputs = [
+            ("input", TensorElementType::Int64, 2),
+            ("input_lengths", TensorElementType::Int64, 1),
+            ("scales", TensorElementType::Float32, 1),
+            ("sid", TensorElementType::Int64, 1),
+        ];
+
+        for (name, ty, rank) in expected_inputs {
+            match self.inputs.iter().find(|i| i.name == name) {
+                Some(spec) => check_spec(spec, ty, rank, &mut problems),
+                None if name == "sid" => {}
+                None => problems.push(format!("missing input '{}'", name)),
+            }
+        }
+
+        for input in &self.inputs {
+            if !expected_inputs
+                .iter()
+                .any(|(name, _, _)| *name == input.name)
+            {
+                problems.push(format!("unexpected input '{}'", input.name));
+            }
+        }
+
+        match self
+            .outputs
+            .iter()
+            .find(|o| o.name == "output" || o.name == "audio")
+        {
+            Some(spec) => {
+                if spec.ty != Some(TensorElementType::Float32) {
+                    problems.push(format!("output '{}' is not float32", spec.name));
+                }
+            }
+            None => problems.push("missing output 'output' (or 'audio')".to_string()),
+        }
+
+        problems
+    }
+}
+
+fn check_spec(spec: &TensorSpec, ty: TensorElementType, rank: usize, problems: &mut Vec<String>) {
+    if spec.ty != Some(ty) {
+        problems.push(format!(
+            "input '{}' has type {:?}, expected {:?}",
+            spec.name, spec.ty, ty
+        ));
+    }
+    if spec.rank != Some(rank) {
+        problems.push(format!(
+            "input '{}' has rank {:?}, expected {}",
+            spec.name, spec.rank, rank
+        ));
+    }
+}
+
+/// Speaker used for multi-speaker models
+pub const DEFAULT_SPEAKER_ID: i64 = 0;
+
 impl PiperEngine {
-    pub fn new(voice: &Voice) -> Result<Self, AppError> {
+    pub fn new(voice: &Voice, intra_threads: usize) -> Result<Self, AppError> {
         // Load the ONNX model using ort (official ONNX Runtime)
         let session = Session::builder()
             .map_err(|e| AppError::TtsError(format!("Failed to create session builder: {}", e)))?
             .with_optimization_level(GraphOptimizationLevel::Level3)
             .map_err(|e| AppError::TtsError(format!("Failed to set optimization level: {}", e)))?
-            .with_intra_threads(4)
+            .with_intra_threads(intra_threads)
             .map_err(|e| AppError::TtsError(format!("Failed to set threads: {}", e)))?
             .commit_from_file(&voice.model_path)
             .map_err(|e| AppError::TtsError(format!("Failed to load model: {}", e)))?;
 
         let inference = voice.config.inference.clone().unwrap_or_default();
+        let signature = ModelSignature {
+            inputs: session
+                .inputs()
+                .iter()
+                .map(TensorSpec::from_outlet)
+                .collect(),
+            outputs: session
+                .outputs()
+                .iter()
+                .map(TensorSpec::from_outlet)
+                .collect(),
+        };
 
         Ok(Self {
             session: Mutex::new(session),
-            noise_scale: inference.noise_scale,
-            length_scale: inference.length_scale,
-            noise_w: inference.noise_w,
+            inference,
+            signature,
         })
     }
 
-    pub fn synthesize(&self, phoneme_ids: &[i64]) -> Result<Vec<f32>, AppError> {
+    pub fn signature(&self) -> &ModelSignature {
+        &self.signature
+    }
+
+    pub fn synthesize(
+        &self,
+        phoneme_ids: &[i64],
+        params: &InferenceParams,
+    ) -> Result<Vec<f32>, AppError> {
         if phoneme_ids.is_empty() {
             return Ok(Vec::new());
         }
@@ -57,17 +169,30 @@ impl PiperEngine {
             .map_err(|e| AppError::TtsError(format!("Failed to create lengths tensor: {}", e)))?;
 
         // scales: [3] = [noise_scale, length_scale, noise_w]
-        let scales_value = Value::from_array((vec![3], vec![
-            self.noise_scale,
-            self.length_scale,
-            self.noise_w,
-        ]))
-            .map_err(|e| AppError::TtsError(format!("Failed to create scales tensor: {}", e)))?;
+        let scales = params.apply(&self.inference);
+        let scales_value = Value::from_array((
+            vec![3],
+            vec![scales.noise_scale, scales.length_scale, scales.noise_w],
+        ))
+        .map_err(|e| AppError::TtsError(format!("Failed to create scales tensor: {}", e)))?;
+
+        let mut inputs = ort::inputs![
+            "input" => input_value,
+            "input_lengths" => lengths_value,
+            "scales" => scales_value,
+        ];
+
+        // sid: [batch] = [1]; multi-speaker models default to the first speaker
+        if self.signature.has_speaker_input() {
+            let sid_value = Value::from_array((vec![1], vec![DEFAULT_SPEAKER_ID]))
+                .map_err(|e| AppError::TtsError(format!("Failed to create sid tensor: {}", e)))?;
+            inputs.push(("sid".into(), sid_value.into()));
+        }
 
         // Run inference
         let mut session = self.session.lock().unwrap();
         let outputs = session
-            .run(ort::inputs![input_value, lengths_value, scales_value])
+            .run(inputs)
             .map_err(|e| AppError::TtsError(format!("Inference failed: {}", e)))?;
 
         // Extract audio samples from output
@@ -80,7 +205,7 @@ impl PiperEngine {
             .try_extract_tensor::<f32>()
             .map_err(|e| AppError::TtsError(format!("Failed to extract output tensor: {}", e)))?;
 
-        let audio: Vec<f32> = output_view.1.iter().copied().collect();
+        let audio: Vec<f32> = output_view.1.to_vec();
 
         Ok(audio)
     }
@@ -96,10 +221,7 @@ pub fn phonemize(text: &str, voice: &str) -> Result<String, AppError> {
         .args(["--ipa", "-q", "-v", voice, text])
         .output()
         .map_err(|e| {
-            AppError::TtsError(format!(
-                "Failed to run espeak-ng (is it installed?): {}",
-                e
-            ))
+            AppError::TtsError(format!("Failed to run espeak-ng (is it installed?): {}", e))
         })?;
 
     if !output.status.success() {
@@ -107,9 +229,7 @@ pub fn phonemize(text: &str, voice: &str) -> Result<String, AppError> {
         return Err(AppError::TtsError(format!("espeak-ng failed: {}", stderr)));
     }
 
-    let phonemes = String::from_utf8_lossy(&output.stdout)
-        .trim()
-        .to_string();
+    let phonemes = String::from_utf8_lossy(&output.stdout).trim().to_string();
 
     Ok(phonemes)
 }
@@ -163,10 +283,8 @@ pub fn samples_to_wav(samples: &[f32], sample_rate: u32) -> Result<Vec<u8>, AppE
             .map_err(|e| AppError::TtsError(format!("Failed to create WAV writer: {}", e)))?;
 
         for sample in samples {
-            // Convert f32 [-1.0, 1.0] to i16 with 2x gain boost
-            let scaled = (sample * 2.0 * 32767.0).clamp(-32768.0, 32767.0) as i16;
             writer
-                .write_sample(scaled)
+                .write_sample(sample_to_i16(*sample))
                 .map_err(|e| AppError::TtsError(format!("Failed to write sample: {}", e)))?;
         }
 
@@ -178,6 +296,19 @@ pub fn samples_to_wav(samples: &[f32], sample_rate: u32) -> Result<Vec<u8>, AppE
     Ok(buffer)
 }
 
+/// Convert audio samples to headerless 16-bit little-endian mono PCM
+pub fn samples_to_pcm(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|sample| sample_to_i16(*sample).to_le_bytes())
+        .collect()
+}
+
+/// Convert f32 [-1.0, 1.0] to i16 with 2x gain boost
+fn sample_to_i16(sample: f32) -> i16 {
+    (sample * 2.0 * 32767.0).clamp(-32768.0, 32767.0) as i16
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -190,6 +321,52 @@ mod tests {
         assert!(!ids.is_empty());
     }
 
+    fn spec(name: &str, ty: TensorElementType, rank: usize) -> TensorSpec {
+        TensorSpec {
+            name: name.to_string(),
+            ty: Some(ty),
+            rank: Some(rank),
+        }
+    }
+
+    fn piper_signature() -> ModelSignature {
+        ModelSignature {
+            inputs: vec![
+                spec("input", TensorElementType::Int64, 2),
+                spec("input_lengths", TensorElementType::Int64, 1),
+                spec("scales", TensorElementType::Float32, 1),
+            ],
+            outputs: vec![spec("output", TensorElementType::Float32, 4)],
+        }
+    }
+
+    #[test]
+    fn test_signature_accepts_piper_model() {
+        let mut signature = piper_signature();
+        assert!(signature.problems().is_empty());
+        assert!(!signature.has_speaker_input());
+
+        signature
+            .inputs
+            .push(spec("sid", TensorElementType::Int64, 1));
+        assert!(signature.problems().is_empty());
+        assert!(signature.has_speaker_input());
+    }
+
+    #[test]
+    fn test_signature_reports_mismatches() {
+        let mut signature = piper_signature();
+        signature.inputs.remove(1);
+        signature.inputs[1] = spec("scales", TensorElementType::Float64, 1);
+        signature.outputs.clear();
+
+        let problems = signature.problems();
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].contains("input_lengths"));
+        assert!(problems[1].contains("scales"));
+        assert!(problems[2].contains("output"));
+    }
+
     #[test]
     fn test_samples_to_wav_empty() {
         let wav = samples_to_wav(&[], 22050).unwrap();
@@ -197,6 +374,15 @@ mod tests {
         assert!(wav.starts_with(b"RIFF"));
     }
 
+    #[test]
+    fn test_samples_to_pcm() {
+        let pcm = samples_to_pcm(&[0.0, 0.25, -1.0]);
+        assert_eq!(pcm.len(), 6);
+        assert_eq!(&pcm[0..2], &[0, 0]);
+        assert_eq!(i16::from_le_bytes([pcm[2], pcm[3]]), 16383);
+        assert_eq!(i16::from_le_bytes([pcm[4], pcm[5]]), -32768);
+    }
+
     #[test]
     fn test_samples_to_wav_valid() {
         let samples: Vec<f32> = vec![0.0, 0.5, -0.5, 1.0, -1.0];
@@ -0,0 +1,850 @@
+//! Server-side playback: one worker owns the output device and plays queued
+//! announcements one at a time, highest priority first.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::tts::Audio;
+
+/// How often the worker checks whether the current item has finished
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Name of the only device the null backend has
+const NULL_DEVICE: &str = "null";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// Where announcements are played
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// The host's sound cards (needs the `audio-playback` feature)
+    #[default]
+    Device,
+    /// Nowhere; items take as long as they would to play. For hosts
+    /// without sound hardware, and CI
+    Null,
+}
+
+impl FromStr for Backend {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "device" => Ok(Backend::Device),
+            "null" => Ok(Backend::Null),
+            other => Err(AppError::BadRequest(format!(
+                "Unknown playback backend '{}' (expected device or null)",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceInfo {
+    pub name: String,
+    /// The system's default output
+    pub default: bool,
+}
+
+impl Backend {
+    /// Output devices this backend can play on
+    pub fn devices(&self) -> Result<Vec<DeviceInfo>, AppError> {
+        match self {
+            Backend::Null => Ok(vec![DeviceInfo {
+                name: NULL_DEVICE.to_string(),
+                default: true,
+            }]),
+            #[cfg(feature = "audio-playback")]
+            Backend::Device => output_devices(),
+            #[cfg(not(feature = "audio-playback"))]
+            Backend::Device => Err(AppError::BadRequest(
+                "Built without the audio-playback feature".into(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaybackSettings {
+    pub backend: Backend,
+    /// Items waiting to play before new ones are refused
+    pub max_queued: usize,
+    /// Global volume, 0.0 to 1.0
+    pub volume: f32,
+    /// WAV played before items that ask for it
+    pub chime: Option<PathBuf>,
+}
+
+/// Where the worker sends audio
+pub trait Output {
+    /// Start playing `audio` on `device` (the configured one if `None`),
+    /// replacing anything still playing
+    fn play(&mut self, audio: &Audio, device: Option<&str>, volume: f32) -> Result<(), AppError>;
+
+    fn set_volume(&mut self, volume: f32);
+
+    fn pause(&mut self);
+
+    fn resume(&mut self);
+
+    fn stop(&mut self);
+
+    /// Whether the audio passed to the last `play` has finished
+    fn is_finished(&self) -> bool;
+}
+
+/// How to play one item
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PlayOptions {
+    /// Higher priorities jump ahead of lower ones in the queue
+    #[serde(default)]
+    pub priority: Priority,
+    /// Cut off whatever is playing and play this next
+    #[serde(default)]
+    pub interrupt: bool,
+    /// Output device name; the configured device when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// 0.0 to 1.0, scaled by the global volume
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f32>,
+    /// Play the configured chime first
+    #[serde(default)]
+    pub chime: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedItem {
+    pub id: u64,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    pub priority: Priority,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f32>,
+    pub chime: bool,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NowPlaying {
+    #[serde(flatten)]
+    pub item: QueuedItem,
+    pub position_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackStatus {
+    pub paused: bool,
+    pub volume: f32,
+    pub current: Option<NowPlaying>,
+    pub queue: Vec<QueuedItem>,
+}
+
+struct Entry {
+    item: QueuedItem,
+    audio: Audio,
+}
+
+struct Current {
+    item: QueuedItem,
+    /// Time played before the last pause
+    played: Duration,
+    /// When playback last (re)started; `None` while paused
+    resumed_at: Option<Instant>,
+}
+
+impl Current {
+    fn position(&self) -> Duration {
+        self.played + self.resumed_at.map_or(Duration::ZERO, |at| at.elapsed())
+    }
+
+    fn volume(&self, global: f32) -> f32 {
+        global * self.item.volume.unwrap_or(1.0)
+    }
+}
+
+struct QueueState {
+    next_id: u64,
+    queue: VecDeque<Entry>,
+    current: Option<Current>,
+    paused: bool,
+    volume: f32,
+    /// Stop the current item at the next poll
+    skip: bool,
+}
+
+pub struct PlaybackQueue {
+    backend: Backend,
+    max_queued: usize,
+    chime: Option<Audio>,
+    state: Mutex<QueueState>,
+    changed: Condvar,
+}
+
+impl PlaybackQueue {
+    pub fn new(settings: &PlaybackSettings) -> Result<Self, AppError> {
+        validate_volume(settings.volume)?;
+        let chime = settings.chime.as_deref().map(Audio::read_wav).transpose()?;
+        Ok(Self {
+            backend: settings.backend,
+            max_queued: settings.max_queued,
+            chime,
+            state: Mutex::new(QueueState {
+                next_id: 0,
+                queue: VecDeque::new(),
+                current: None,
+                paused: false,
+                volume: settings.volume,
+                skip: false,
+            }),
+            changed: Condvar::new(),
+        })
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Queue `audio` behind everything of the same or higher priority; with
+    /// `interrupt`, play it right away, cutting off the current item
+    pub fn enqueue(
+        &self,
+        text: &str,
+        voice: Option<&str>,
+        audio: Audio,
+        options: &PlayOptions,
+    ) -> Result<QueuedItem, AppError> {
+        if let Some(volume) = options.volume {
+            validate_volume(volume)?;
+        }
+        if let Some(device) = &options.device {
+            if !self.backend.devices()?.iter().any(|d| &d.name == device) {
+                return Err(AppError::NotFound(format!(
+                    "Output device '{}' not found",
+                    device
+                )));
+            }
+        }
+        let audio = if options.chime {
+            let chime = self.chime.as_ref().ok_or_else(|| {
+                AppError::BadRequest("No chime configured (set playback.chime)".into())
+            })?;
+            let mut samples = chime.resample(audio.sample_rate).samples;
+            samples.extend_from_slice(&audio.samples);
+            Audio {
+                samples,
+                sample_rate: audio.sample_rate,
+            }
+        } else {
+            audio
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if state.queue.len() >= self.max_queued {
+            return Err(AppError::Busy(format!(
+                "Playback queue is full ({} items)",
+                self.max_queued
+            )));
+        }
+
+        state.next_id += 1;
+        let item = QueuedItem {
+            id: state.next_id,
+            text: text.to_string(),
+            voice: voice.map(str::to_string),
+            priority: options.priority,
+            device: options.device.clone(),
+            volume: options.volume,
+            chime: options.chime,
+            duration_ms: audio.duration().as_millis() as u64,
+        };
+        let position = if options.interrupt {
+            state.skip = state.current.is_some();
+            0
+        } else {
+            state
+                .queue
+                .iter()
+                .position(|entry| entry.item.priority < options.priority)
+                .unwrap_or(state.queue.len())
+        };
+        state.queue.insert(
+            position,
+            Entry {
+                item: item.clone(),
+                audio,
+            },
+        );
+        self.changed.notify_all();
+        Ok(item)
+    }
+
+    /// Stop the current item; the next one starts unless paused
+    pub fn skip(&self) -> Option<QueuedItem> {
+        let mut state = self.state.lock().unwrap();
+        let current = state.current.as_ref().map(|current| current.item.clone());
+        state.skip = current.is_some();
+        self.changed.notify_all();
+        current
+    }
+
+    /// Drop everything waiting; the current item keeps playing
+    pub fn clear(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let removed = state.queue.len();
+        state.queue.clear();
+        removed
+    }
+
+    pub fn pause(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.paused = true;
+        if let Some(current) = &mut state.current {
+            current.played = current.position();
+            current.resumed_at = None;
+        }
+        self.changed.notify_all();
+    }
+
+    pub fn resume(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.paused = false;
+        if let Some(current) = &mut state.current {
+            current.resumed_at.get_or_insert_with(Instant::now);
+        }
+        self.changed.notify_all();
+    }
+
+    /// Set the global volume, including for the current item
+    pub fn set_volume(&self, volume: f32) -> Result<(), AppError> {
+        validate_volume(volume)?;
+        self.state.lock().unwrap().volume = volume;
+        self.changed.notify_all();
+        Ok(())
+    }
+
+    pub fn status(&self) -> PlaybackStatus {
+        let state = self.state.lock().unwrap();
+        PlaybackStatus {
+            paused: state.paused,
+            volume: state.volume,
+            current: state.current.as_ref().map(|current| NowPlaying {
+                item: current.item.clone(),
+                position_ms: current.position().as_millis() as u64,
+            }),
+            queue: state.queue.iter().map(|entry| entry.item.clone()).collect(),
+        }
+    }
+
+    /// Play queued items on `output` forever
+    pub fn run_worker<O: Output>(&self, mut output: O) {
+        let mut output_paused = false;
+        let mut output_volume = 1.0;
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(current) = &state.current {
+                let volume = current.volume(state.volume);
+                if state.skip {
+                    output.stop();
+                    state.current = None;
+                    state.skip = false;
+                    continue;
+                }
+                if state.paused != output_paused {
+                    if state.paused {
+                        output.pause();
+                    } else {
+                        output.resume();
+                    }
+                    output_paused = state.paused;
+                }
+                if volume != output_volume {
+                    output.set_volume(volume);
+                    output_volume = volume;
+                }
+                if !state.paused && output.is_finished() {
+                    state.current = None;
+                    continue;
+                }
+                state = self.changed.wait_timeout(state, POLL_INTERVAL).unwrap().0;
+            } else if state.paused {
+                state = self.changed.wait(state).unwrap();
+            } else if let Some(entry) = state.queue.pop_front() {
+                state.skip = false;
+                let current = Current {
+                    item: entry.item,
+                    played: Duration::ZERO,
+                    resumed_at: Some(Instant::now()),
+                };
+                output_paused = false;
+                output_volume = current.volume(state.volume);
+                match output.play(&entry.audio, current.item.device.as_deref(), output_volume) {
+                    Ok(()) => {
+                        tracing::debug!("Playing item {}", current.item.id);
+                        state.current = Some(current);
+                    }
+                    Err(e) => tracing::error!("Failed to play item {}: {}", current.item.id, e),
+                }
+            } else {
+                state = self.changed.wait(state).unwrap();
+            }
+        }
+    }
+}
+
+fn validate_volume(volume: f32) -> Result<(), AppError> {
+    if !(0.0..=1.0).contains(&volume) {
+        return Err(AppError::BadRequest(format!(
+            "Volume must be between 0.0 and 1.0 (got {})",
+            volume
+        )));
+    }
+    Ok(())
+}
+
+/// Plays nothing, but takes as long as the audio would
+#[derive(Debug, Default)]
+pub struct NullOutput {
+    /// Left to play as of `resumed_at`
+    remaining: Duration,
+    resumed_at: Option<Instant>,
+}
+
+impl NullOutput {
+    fn remaining(&self) -> Duration {
+        self.remaining
+            .saturating_sub(self.resumed_at.map_or(Duration::ZERO, |at| at.elapsed()))
+    }
+}
+
+impl Output for NullOutput {
+    fn play(&mut self, audio: &Audio, _device: Option<&str>, _volume: f32) -> Result<(), AppError> {
+        self.remaining = audio.duration();
+        self.resumed_at = Some(Instant::now());
+        Ok(())
+    }
+
+    fn set_volume(&mut self, _volume: f32) {}
+
+    fn pause(&mut self) {
+        self.remaining = self.remaining();
+        self.resumed_at = None;
+    }
+
+    fn resume(&mut self) {
+        self.resumed_at.get_or_insert_with(Instant::now);
+    }
+
+    fn stop(&mut self) {
+        self.remaining = Duration::ZERO;
+        self.resumed_at = None;
+    }
+
+    fn is_finished(&self) -> bool {
+        self.remaining().is_zero()
+    }
+}
+
+#[cfg(feature = "audio-playback")]
+fn output_devices() -> Result<Vec<DeviceInfo>, AppError> {
+    use rodio::cpal::traits::HostTrait;
+    use rodio::DeviceTrait;
+
+    let host = rodio::cpal::default_host();
+    let default = host.default_output_device().and_then(|d| d.name().ok());
+    let devices = host
+        .output_devices()
+        .map_err(|e| AppError::TtsError(format!("Failed to list output devices: {}", e)))?;
+    Ok(devices
+        .filter_map(|device| device.name().ok())
+        .map(|name| DeviceInfo {
+            default: default.as_ref() == Some(&name),
+            name,
+        })
+        .collect())
+}
+
+/// Sound cards, via rodio. Streams are opened on first use and kept open.
+#[cfg(feature = "audio-playback")]
+pub struct DeviceOutput {
+    /// Used when an item names no device; the system default if `None`
+    device: Option<String>,
+    // Playback stops when a stream is dropped
+    streams:
+        std::collections::HashMap<Option<String>, (rodio::OutputStream, rodio::OutputStreamHandle)>,
+    sink: Option<rodio::Sink>,
+}
+
+#[cfg(feature = "audio-playback")]
+impl DeviceOutput {
+    /// Open `device` (the system default if `None`) so a missing device
+    /// shows up at startup rather than on the first announcement
+    pub fn open(device: Option<String>) -> Result<Self, AppError> {
+        let mut output = Self {
+            device,
+            streams: std::collections::HashMap::new(),
+            sink: None,
+        };
+        output.stream(None)?;
+        Ok(output)
+    }
+
+    fn stream(&mut self, device: Option<&str>) -> Result<&rodio::OutputStreamHandle, AppError> {
+        use rodio::cpal::traits::HostTrait;
+        use rodio::DeviceTrait;
+
+        let name = device.map(str::to_string).or_else(|| self.device.clone());
+        if !self.streams.contains_key(&name) {
+            let opened = match &name {
+                None => rodio::OutputStream::try_default(),
+                Some(name) => {
+                    let device = rodio::cpal::default_host()
+                        .output_devices()
+                        .map_err(|e| {
+                            AppError::TtsError(format!("Failed to list output devices: {}", e))
+                        })?
+                        .find(|device| device.name().is_ok_and(|n| &n == name))
+                        .ok_or_else(|| {
+                            AppError::NotFound(format!("Output device '{}' not found", name))
+                        })?;
+                    rodio::OutputStream::try_from_device(&device)
+                }
+            }
+            .map_err(|e| AppError::TtsError(format!("Failed to open audio output: {}", e)))?;
+            self.streams.insert(name.clone(), opened);
+        }
+        Ok(&self.streams[&name].1)
+    }
+}
+
+#[cfg(feature = "audio-playback")]
+impl Output for DeviceOutput {
+    fn play(&mut self, audio: &Audio, device: Option<&str>, volume: f32) -> Result<(), AppError> {
+        // Dropping the previous sink stops it
+        self.sink = None;
+        let sink = rodio::Sink::try_new(self.stream(device)?)
+            .map_err(|e| AppError::TtsError(format!("Failed to open audio output: {}", e)))?;
+        sink.set_volume(volume);
+        sink.append(rodio::buffer::SamplesBuffer::new(
+            1,
+            audio.sample_rate,
+            audio.samples.clone(),
+        ));
+        self.sink = Some(sink);
+        Ok(())
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        if let Some(sink) = &self.sink {
+            sink.set_volume(volume);
+        }
+    }
+
+    fn pause(&mut self) {
+        if let Some(sink) = &self.sink {
+            sink.pause();
+        }
+    }
+
+    fn resume(&mut self) {
+        if let Some(sink) = &self.sink {
+            sink.play();
+        }
+    }
+
+    fn stop(&mut self) {
+        self.sink = None;
+    }
+
+    fn is_finished(&self) -> bool {
+        self.sink.as_ref().is_none_or(rodio::Sink::empty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// What the worker asked the output to do
+    #[derive(Debug, Clone, PartialEq)]
+    enum Call {
+        Play(usize, Option<String>, f32),
+        Volume(f32),
+    }
+
+    /// A null output that records its calls
+    #[derive(Default)]
+    struct Recorder {
+        output: NullOutput,
+        calls: Arc<Mutex<Vec<Call>>>,
+    }
+
+    impl Output for Recorder {
+        fn play(
+            &mut self,
+            audio: &Audio,
+            device: Option<&str>,
+            volume: f32,
+        ) -> Result<(), AppError> {
+            self.calls.lock().unwrap().push(Call::Play(
+                audio.samples.len(),
+                device.map(str::to_string),
+                volume,
+            ));
+            self.output.play(audio, device, volume)
+        }
+
+        fn set_volume(&mut self, volume: f32) {
+            self.calls.lock().unwrap().push(Call::Volume(volume));
+        }
+
+        fn pause(&mut self) {
+            self.output.pause();
+        }
+
+        fn resume(&mut self) {
+            self.output.resume();
+        }
+
+        fn stop(&mut self) {
+            self.output.stop();
+        }
+
+        fn is_finished(&self) -> bool {
+            self.output.is_finished()
+        }
+    }
+
+    fn settings() -> PlaybackSettings {
+        PlaybackSettings {
+            backend: Backend::Null,
+            max_queued: 10,
+            volume: 1.0,
+            chime: None,
+        }
+    }
+
+    /// `millis` of silence at 1 kHz, so the sample count identifies it
+    fn audio(millis: usize) -> Audio {
+        Audio {
+            samples: vec![0.0; millis],
+            sample_rate: 1000,
+        }
+    }
+
+    fn options(priority: Priority) -> PlayOptions {
+        PlayOptions {
+            priority,
+            ..PlayOptions::default()
+        }
+    }
+
+    fn wait_for(queue: &PlaybackQueue, done: impl Fn(&PlaybackStatus) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done(&queue.status()) {
+            assert!(Instant::now() < deadline, "timed out");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn start(queue: &Arc<PlaybackQueue>) -> Arc<Mutex<Vec<Call>>> {
+        let output = Recorder::default();
+        let calls = Arc::clone(&output.calls);
+        let worker = Arc::clone(queue);
+        std::thread::spawn(move || worker.run_worker(output));
+        calls
+    }
+
+    fn played(calls: &Mutex<Vec<Call>>) -> Vec<usize> {
+        calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|call| match call {
+                Call::Play(len, ..) => Some(*len),
+                Call::Volume(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn orders_by_priority_then_arrival() {
+        let queue = PlaybackQueue::new(&settings()).unwrap();
+        for (text, priority) in [
+            ("low", Priority::Low),
+            ("first", Priority::Normal),
+            ("urgent", Priority::High),
+            ("second", Priority::Normal),
+        ] {
+            queue
+                .enqueue(text, None, audio(10), &options(priority))
+                .unwrap();
+        }
+        let order: Vec<String> = queue.status().queue.into_iter().map(|i| i.text).collect();
+        assert_eq!(order, ["urgent", "first", "second", "low"]);
+
+        let full = PlaybackQueue::new(&PlaybackSettings {
+            max_queued: 1,
+            ..settings()
+        })
+        .unwrap();
+        full.enqueue("a", None, audio(10), &PlayOptions::default())
+            .unwrap();
+        assert!(matches!(
+            full.enqueue("b", None, audio(10), &PlayOptions::default()),
+            Err(AppError::Busy(_))
+        ));
+    }
+
+    #[test]
+    fn plays_in_order_and_interrupts() {
+        let queue = Arc::new(PlaybackQueue::new(&settings()).unwrap());
+        let calls = start(&queue);
+
+        queue
+            .enqueue("long", None, audio(5000), &PlayOptions::default())
+            .unwrap();
+        queue
+            .enqueue("next", None, audio(20), &PlayOptions::default())
+            .unwrap();
+        wait_for(&queue, |status| status.current.is_some());
+
+        let alert = PlayOptions {
+            interrupt: true,
+            ..options(Priority::High)
+        };
+        queue.enqueue("alert", None, audio(30), &alert).unwrap();
+        wait_for(&queue, |status| {
+            status.current.is_none() && status.queue.is_empty()
+        });
+        assert_eq!(played(&calls), [5000, 30, 20]);
+    }
+
+    #[test]
+    fn pauses_skips_and_clears() {
+        let queue = Arc::new(PlaybackQueue::new(&settings()).unwrap());
+        let calls = start(&queue);
+
+        queue
+            .enqueue("one", None, audio(5000), &PlayOptions::default())
+            .unwrap();
+        wait_for(&queue, |status| status.current.is_some());
+        queue.pause();
+        queue
+            .enqueue("two", None, audio(300), &PlayOptions::default())
+            .unwrap();
+        queue
+            .enqueue("three", None, audio(40), &PlayOptions::default())
+            .unwrap();
+
+        // Paused: skipping stops the current item but nothing new starts
+        assert_eq!(queue.skip().unwrap().text, "one");
+        wait_for(&queue, |status| status.current.is_none());
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(queue.status().queue.len(), 2);
+
+        queue.resume();
+        wait_for(&queue, |status| status.queue.len() == 1);
+        assert_eq!(queue.clear(), 1);
+        wait_for(&queue, |status| status.current.is_none());
+        assert_eq!(played(&calls), [5000, 300]);
+    }
+
+    #[test]
+    fn applies_devices_and_volume() {
+        let queue = Arc::new(
+            PlaybackQueue::new(&PlaybackSettings {
+                volume: 0.5,
+                ..settings()
+            })
+            .unwrap(),
+        );
+        let calls = start(&queue);
+
+        let quiet = PlayOptions {
+            device: Some("null".into()),
+            volume: Some(0.5),
+            ..PlayOptions::default()
+        };
+        queue.enqueue("quiet", None, audio(5000), &quiet).unwrap();
+        wait_for(&queue, |status| status.current.is_some());
+        queue.set_volume(1.0).unwrap();
+        wait_for(&queue, |_| calls.lock().unwrap().len() == 2);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                Call::Play(5000, Some("null".into()), 0.25),
+                Call::Volume(0.5)
+            ]
+        );
+
+        assert!(queue.set_volume(1.5).is_err());
+        let unknown = PlayOptions {
+            device: Some("PA system".into()),
+            ..PlayOptions::default()
+        };
+        assert!(matches!(
+            queue.enqueue("x", None, audio(10), &unknown),
+            Err(AppError::NotFound(_))
+        ));
+        let chime = PlayOptions {
+            chime: true,
+            ..PlayOptions::default()
+        };
+        assert!(queue.enqueue("x", None, audio(10), &chime).is_err());
+    }
+
+    #[test]
+    fn prefixes_the_chime() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chime.wav");
+        std::fs::write(
+            &path,
+            audio(50).encode(crate::tts::AudioFormat::Wav).unwrap(),
+        )
+        .unwrap();
+        let queue = PlaybackQueue::new(&PlaybackSettings {
+            chime: Some(path),
+            ..settings()
+        })
+        .unwrap();
+
+        let chime = PlayOptions {
+            chime: true,
+            ..PlayOptions::default()
+        };
+        let item = queue.enqueue("ding", None, audio(100), &chime).unwrap();
+        assert_eq!(item.duration_ms, 150);
+    }
+
+    #[test]
+    fn null_output_pauses() {
+        let mut output = NullOutput::default();
+        output.play(&audio(40), None, 1.0).unwrap();
+        output.pause();
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(!output.is_finished());
+        output.resume();
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(output.is_finished());
+    }
+}
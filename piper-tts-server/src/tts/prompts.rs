@@ -0,0 +1,468 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::tts::{validate_file_id, write_atomic, AudioFormat, TtsService};
+
+const REGISTRY_FILE: &str = "prompts.json";
+
+/// A named phrase: DSL text and the voice (id or alias) to speak it with
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Prompt {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+}
+
+/// Registry entry as stored in `prompts.json`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct PromptRecord {
+    #[serde(flatten)]
+    prompt: Prompt,
+    /// Fingerprint of the rendered audio; None until rendered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(skip)]
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptState {
+    /// Waiting for (re-)rendering
+    Pending,
+    Rendered,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptStatus {
+    pub id: String,
+    #[serde(flatten)]
+    pub prompt: Prompt,
+    pub state: PromptState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+struct LibraryState {
+    prompts: HashMap<String, PromptRecord>,
+    queue: VecDeque<String>,
+    queued: HashSet<String>,
+}
+
+impl LibraryState {
+    fn enqueue(&mut self, id: &str) {
+        if self.queued.insert(id.to_string()) {
+            self.queue.push_back(id.to_string());
+        }
+    }
+}
+
+/// Named prompts pre-rendered to `{dir}/{id}.wav`.
+///
+/// Each prompt remembers the synthesis fingerprint (see
+/// [`TtsService::audio_etag`]) it was rendered with; when the current
+/// fingerprint differs — new model, changed alias or voice config — the
+/// prompt is queued for re-rendering.
+pub struct PromptLibrary {
+    dir: PathBuf,
+    state: Mutex<LibraryState>,
+    wakeup: Condvar,
+}
+
+impl PromptLibrary {
+    pub fn open(dir: &Path) -> Result<Self, AppError> {
+        std::fs::create_dir_all(dir)?;
+
+        let registry = dir.join(REGISTRY_FILE);
+        let prompts: HashMap<String, PromptRecord> = if registry.exists() {
+            serde_json::from_reader(std::fs::File::open(&registry)?)?
+        } else {
+            HashMap::new()
+        };
+
+        let mut state = LibraryState {
+            prompts,
+            ..Default::default()
+        };
+        let mut unrendered: Vec<String> = state
+            .prompts
+            .iter()
+            .filter(|(id, record)| record.etag.is_none() || !audio_path(dir, id).exists())
+            .map(|(id, _)| id.clone())
+            .collect();
+        unrendered.sort();
+        for id in unrendered {
+            state.enqueue(&id);
+        }
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            state: Mutex::new(state),
+            wakeup: Condvar::new(),
+        })
+    }
+
+    /// Add or replace prompts; new and changed ones are queued for rendering
+    pub fn register(
+        &self,
+        prompts: HashMap<String, Prompt>,
+    ) -> Result<Vec<PromptStatus>, AppError> {
+        for (id, prompt) in &prompts {
+            validate_prompt_id(id)?;
+            if prompt.text.trim().is_empty() {
+                return Err(AppError::BadRequest(format!("Prompt '{}' has no text", id)));
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let mut ids: Vec<&String> = prompts.keys().collect();
+        ids.sort();
+        for id in ids {
+            let prompt = prompts[id].clone();
+            let unchanged = state
+                .prompts
+                .get(id)
+                .is_some_and(|record| record.prompt == prompt && record.etag.is_some());
+            if unchanged {
+                continue;
+            }
+            state.prompts.insert(
+                id.clone(),
+                PromptRecord {
+                    prompt,
+                    etag: None,
+                    error: None,
+                },
+            );
+            state.enqueue(id);
+        }
+        self.save(&state)?;
+        self.wakeup.notify_all();
+
+        let mut statuses: Vec<PromptStatus> = prompts
+            .keys()
+            .filter_map(|id| status_of(&state, &self.dir, id))
+            .collect();
+        statuses.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(statuses)
+    }
+
+    pub fn remove(&self, id: &str) -> Result<bool, AppError> {
+        let mut state = self.state.lock().unwrap();
+        if state.prompts.remove(id).is_none() {
+            return Ok(false);
+        }
+        state.queued.remove(id);
+        state.queue.retain(|queued| queued != id);
+        self.save(&state)?;
+
+        match std::fs::remove_file(audio_path(&self.dir, id)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        Ok(true)
+    }
+
+    pub fn list(&self) -> Vec<PromptStatus> {
+        let state = self.state.lock().unwrap();
+        let mut statuses: Vec<PromptStatus> = state
+            .prompts
+            .keys()
+            .filter_map(|id| status_of(&state, &self.dir, id))
+            .collect();
+        statuses.sort_by(|a, b| a.id.cmp(&b.id));
+        statuses
+    }
+
+    pub fn status(&self, id: &str) -> Option<PromptStatus> {
+        status_of(&self.state.lock().unwrap(), &self.dir, id)
+    }
+
+    /// Rendered WAV and its ETag, rendering now if the prompt isn't ready
+    pub fn audio(&self, id: &str, tts: &TtsService) -> Result<(Vec<u8>, String), AppError> {
+        let record = self
+            .state
+            .lock()
+            .unwrap()
+            .prompts
+            .get(id)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound(format!("Prompt '{}' not found", id)))?;
+
+        if let Some(etag) = &record.etag {
+            if !self.state.lock().unwrap().queued.contains(id) {
+                match std::fs::read(audio_path(&self.dir, id)) {
+                    Ok(wav) => return Ok((wav, etag.clone())),
+                    Err(e) => {
+                        tracing::warn!("Prompt '{}' audio unreadable, re-rendering: {}", id, e)
+                    }
+                }
+            }
+        }
+
+        self.render(id, tts)?;
+        let etag = self
+            .state
+            .lock()
+            .unwrap()
+            .prompts
+            .get(id)
+            .and_then(|record| record.etag.clone())
+            .ok_or_else(|| AppError::NotFound(format!("Prompt '{}' not found", id)))?;
+        Ok((std::fs::read(audio_path(&self.dir, id))?, etag))
+    }
+
+    /// Queue prompts whose audio no longer matches what would be rendered now
+    pub fn refresh(&self, tts: &TtsService) {
+        let records: Vec<(String, PromptRecord)> = {
+            let state = self.state.lock().unwrap();
+            state
+                .prompts
+                .iter()
+                .map(|(id, record)| (id.clone(), record.clone()))
+                .collect()
+        };
+
+        let mut stale = Vec::new();
+        for (id, record) in records {
+            let current = tts.audio_etag(
+                &record.prompt.text,
+                record.prompt.voice.as_deref(),
+                AudioFormat::Wav,
+            );
+            let up_to_date =
+                matches!((&current, &record.etag), (Ok(now), Some(then)) if now == then);
+            if !up_to_date {
+                stale.push(id);
+            }
+        }
+
+        if stale.is_empty() {
+            return;
+        }
+        stale.sort();
+        tracing::info!("Queued {} prompts for re-rendering", stale.len());
+
+        let mut state = self.state.lock().unwrap();
+        for id in &stale {
+            if state.prompts.contains_key(id) {
+                state.enqueue(id);
+            }
+        }
+        self.wakeup.notify_all();
+    }
+
+    /// Render queued prompts forever; run on a dedicated thread
+    pub fn run(&self, tts: &TtsService) {
+        loop {
+            let id = {
+                let mut state = self.state.lock().unwrap();
+                loop {
+                    if let Some(id) = state.queue.pop_front() {
+                        // Edits made while rendering queue the prompt again
+                        state.queued.remove(&id);
+                        break id;
+                    }
+                    state = self.wakeup.wait(state).unwrap();
+                }
+            };
+            if let Err(e) = self.render(&id, tts) {
+                tracing::warn!("Failed to render prompt '{}': {}", id, e);
+            }
+        }
+    }
+
+    fn render(&self, id: &str, tts: &TtsService) -> Result<(), AppError> {
+        let Some(prompt) = self
+            .state
+            .lock()
+            .unwrap()
+            .prompts
+            .get(id)
+            .map(|record| record.prompt.clone())
+        else {
+            return Ok(());
+        };
+
+        let result = tts
+            .speak_cached(&prompt.text, prompt.voice.as_deref(), AudioFormat::Wav)
+            .and_then(|cached| {
+                write_atomic(&audio_path(&self.dir, id), &cached.audio)?;
+                Ok(cached.etag)
+            });
+
+        let mut state = self.state.lock().unwrap();
+        // The prompt may have been edited or removed while rendering
+        let Some(record) = state.prompts.get_mut(id).filter(|r| r.prompt == prompt) else {
+            return result.map(|_| ());
+        };
+
+        match result {
+            Ok(etag) => {
+                record.etag = Some(etag);
+                record.error = None;
+                tracing::info!("Rendered prompt '{}'", id);
+                self.save(&state)
+            }
+            Err(e) => {
+                record.error = Some(e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    fn save(&self, state: &LibraryState) -> Result<(), AppError> {
+        let json = serde_json::to_vec_pretty(&state.prompts)?;
+        write_atomic(&self.dir.join(REGISTRY_FILE), &json)?;
+        Ok(())
+    }
+}
+
+fn status_of(state: &LibraryState, dir: &Path, id: &str) -> Option<PromptStatus> {
+    let record = state.prompts.get(id)?;
+    let prompt_state = if state.queued.contains(id) {
+        PromptState::Pending
+    } else if record.error.is_some() {
+        PromptState::Failed
+    } else if record.etag.is_none() || !audio_path(dir, id).exists() {
+        PromptState::Pending
+    } else {
+        PromptState::Rendered
+    };
+
+    Some(PromptStatus {
+        id: id.to_string(),
+        prompt: record.prompt.clone(),
+        state: prompt_state,
+        error: record.error.clone(),
+    })
+}
+
+fn audio_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}.wav", id))
+}
+
+/// Prompt ids become file names, so keep them to a safe character set.
+/// Dots are refused too: `{id}.wav` must map back to exactly one id and
+/// never collide with the registry.
+pub fn validate_prompt_id(id: &str) -> Result<(), AppError> {
+    validate_file_id("prompt", id)?;
+    if id.contains('.') {
+        return Err(AppError::BadRequest(format!(
+            "Invalid prompt id '{}' (use letters, digits, '-' and '_')",
+            id
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt(text: &str) -> Prompt {
+        Prompt {
+            text: text.to_string(),
+            voice: Some("announcer".to_string()),
+        }
+    }
+
+    fn register(library: &PromptLibrary, id: &str, text: &str) -> Vec<PromptStatus> {
+        library
+            .register([(id.to_string(), prompt(text))].into_iter().collect())
+            .unwrap()
+    }
+
+    #[test]
+    fn registered_prompts_are_pending_and_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = PromptLibrary::open(dir.path()).unwrap();
+        let statuses = register(&library, "welcome", "Welcome to [emphasis]Acme[/emphasis]");
+        assert_eq!(statuses[0].state, PromptState::Pending);
+
+        let reopened = PromptLibrary::open(dir.path()).unwrap();
+        let status = reopened.status("welcome").unwrap();
+        assert_eq!(
+            status.prompt,
+            prompt("Welcome to [emphasis]Acme[/emphasis]")
+        );
+        assert_eq!(status.state, PromptState::Pending);
+        assert_eq!(reopened.state.lock().unwrap().queue.len(), 1);
+    }
+
+    #[test]
+    fn rendered_prompts_reload_as_rendered() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = PromptLibrary::open(dir.path()).unwrap();
+        register(&library, "goodbye", "Goodbye.");
+
+        // Simulate a finished render
+        {
+            let mut state = library.state.lock().unwrap();
+            state.prompts.get_mut("goodbye").unwrap().etag = Some("abc".into());
+            state.queue.clear();
+            state.queued.clear();
+            library.save(&state).unwrap();
+        }
+        std::fs::write(audio_path(dir.path(), "goodbye"), b"RIFF").unwrap();
+
+        let reopened = PromptLibrary::open(dir.path()).unwrap();
+        assert_eq!(
+            reopened.status("goodbye").unwrap().state,
+            PromptState::Rendered
+        );
+
+        // Re-registering identical text doesn't re-render; changed text does
+        register(&reopened, "goodbye", "Goodbye.");
+        assert_eq!(
+            reopened.status("goodbye").unwrap().state,
+            PromptState::Rendered
+        );
+        register(&reopened, "goodbye", "Goodbye, and thanks.");
+        assert_eq!(
+            reopened.status("goodbye").unwrap().state,
+            PromptState::Pending
+        );
+    }
+
+    #[test]
+    fn remove_deletes_audio() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = PromptLibrary::open(dir.path()).unwrap();
+        register(&library, "hold", "Please hold.");
+        std::fs::write(audio_path(dir.path(), "hold"), b"RIFF").unwrap();
+
+        assert!(library.remove("hold").unwrap());
+        assert!(!library.remove("hold").unwrap());
+        assert!(!audio_path(dir.path(), "hold").exists());
+        assert!(library.state.lock().unwrap().queue.is_empty());
+    }
+
+    #[test]
+    fn rejects_unsafe_ids_and_empty_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = PromptLibrary::open(dir.path()).unwrap();
+        for id in [
+            "",
+            "../etc",
+            ".hidden",
+            "a/b",
+            "a b",
+            "menu.main",
+            "prompts.json",
+        ] {
+            assert!(validate_prompt_id(id).is_err(), "{}", id);
+        }
+        assert!(validate_prompt_id("menu-main_1a").is_ok());
+        assert!(library
+            .register([("blank".to_string(), prompt("  "))].into_iter().collect())
+            .is_err());
+    }
+}
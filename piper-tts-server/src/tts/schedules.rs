@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{Local, NaiveDateTime, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::tts::cron::Cron;
+use crate::tts::playback::{PlayOptions, PlaybackQueue, QueuedItem};
+use crate::tts::{validate_file_id, write_atomic, TtsService};
+
+const REGISTRY_FILE: &str = "schedules.json";
+
+/// An announcement spoken through the playback queue whenever `cron`
+/// matches the local time. `text` is DSL, so `[time]` and `[date]` are
+/// filled in when it fires.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Schedule {
+    pub cron: String,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub play: PlayOptions,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Registry entry as stored in `schedules.json`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct ScheduleRecord {
+    #[serde(flatten)]
+    schedule: Schedule,
+    /// Unix seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_run: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleStatus {
+    pub id: String,
+    #[serde(flatten)]
+    pub schedule: Schedule,
+    /// Unix seconds; `None` when disabled or the rule never matches
+    pub next_run: Option<u64>,
+    pub last_run: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Named schedules persisted to `{dir}/schedules.json`
+pub struct Scheduler {
+    dir: PathBuf,
+    schedules: Mutex<HashMap<String, ScheduleRecord>>,
+}
+
+impl Scheduler {
+    pub fn open(dir: &Path) -> Result<Self, AppError> {
+        std::fs::create_dir_all(dir)?;
+
+        let registry = dir.join(REGISTRY_FILE);
+        let schedules: HashMap<String, ScheduleRecord> = if registry.exists() {
+            serde_json::from_reader(std::fs::File::open(&registry)?)?
+        } else {
+            HashMap::new()
+        };
+        for (id, record) in &schedules {
+            if let Err(e) = record.schedule.cron.parse::<Cron>() {
+                tracing::warn!("Schedule '{}' will never run: {}", id, e);
+            }
+        }
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            schedules: Mutex::new(schedules),
+        })
+    }
+
+    /// Add or replace schedules
+    pub fn put(
+        &self,
+        schedules: HashMap<String, Schedule>,
+    ) -> Result<Vec<ScheduleStatus>, AppError> {
+        for (id, schedule) in &schedules {
+            validate_file_id("schedule", id)?;
+            schedule.cron.parse::<Cron>()?;
+            if schedule.text.trim().is_empty() {
+                return Err(AppError::BadRequest(format!(
+                    "Schedule '{}' has no text",
+                    id
+                )));
+            }
+        }
+
+        let mut state = self.schedules.lock().unwrap();
+        let mut ids: Vec<String> = schedules.keys().cloned().collect();
+        ids.sort();
+        for (id, schedule) in schedules {
+            state.insert(
+                id,
+                ScheduleRecord {
+                    schedule,
+                    last_run: None,
+                    last_error: None,
+                },
+            );
+        }
+        self.save(&state)?;
+
+        let now = now();
+        Ok(ids.iter().map(|id| status(id, &state[id], &now)).collect())
+    }
+
+    pub fn list(&self) -> Vec<ScheduleStatus> {
+        let state = self.schedules.lock().unwrap();
+        let now = now();
+        let mut statuses: Vec<ScheduleStatus> = state
+            .iter()
+            .map(|(id, record)| status(id, record, &now))
+            .collect();
+        statuses.sort_by(|a, b| a.id.cmp(&b.id));
+        statuses
+    }
+
+    pub fn status(&self, id: &str) -> Option<ScheduleStatus> {
+        let state = self.schedules.lock().unwrap();
+        state.get(id).map(|record| status(id, record, &now()))
+    }
+
+    pub fn remove(&self, id: &str) -> Result<bool, AppError> {
+        let mut state = self.schedules.lock().unwrap();
+        if state.remove(id).is_none() {
+            return Ok(false);
+        }
+        self.save(&state)?;
+        Ok(true)
+    }
+
+    /// Speak a schedule's announcement now
+    pub fn fire(
+        &self,
+        id: &str,
+        tts: &TtsService,
+        playback: &PlaybackQueue,
+    ) -> Result<QueuedItem, AppError> {
+        let schedule = self
+            .schedules
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|record| record.schedule.clone())
+            .ok_or_else(|| AppError::NotFound(format!("Schedule '{}' not found", id)))?;
+
+        let result = tts
+            .synthesize(&schedule.text, schedule.voice.as_deref())
+            .and_then(|audio| {
+                playback.enqueue(
+                    &schedule.text,
+                    schedule.voice.as_deref(),
+                    audio,
+                    &schedule.play,
+                )
+            });
+
+        let mut state = self.schedules.lock().unwrap();
+        if let Some(record) = state.get_mut(id) {
+            record.last_run = Some(unix_now());
+            record.last_error = result.as_ref().err().map(|e| e.to_string());
+            if let Err(e) = self.save(&state) {
+                tracing::warn!("Failed to save schedules: {}", e);
+            }
+        }
+        result
+    }
+
+    /// Fire due schedules at the start of every minute, forever
+    pub fn run(&self, tts: &TtsService, playback: &PlaybackQueue) {
+        let mut last_checked = None;
+        loop {
+            let now = Local::now().naive_local();
+            let minute = truncate_to_minute(&now);
+            if last_checked != Some(minute) {
+                last_checked = Some(minute);
+                for id in self.due(&minute) {
+                    match self.fire(&id, tts, playback) {
+                        Ok(item) => tracing::info!("Schedule '{}' queued as item {}", id, item.id),
+                        Err(e) => tracing::warn!("Schedule '{}' failed: {}", id, e),
+                    }
+                }
+            }
+
+            let into_minute = Duration::new(now.second() as u64, now.nanosecond());
+            std::thread::sleep(
+                Duration::from_secs(60).saturating_sub(into_minute) + Duration::from_millis(50),
+            );
+        }
+    }
+
+    fn due(&self, minute: &NaiveDateTime) -> Vec<String> {
+        let state = self.schedules.lock().unwrap();
+        let mut due: Vec<String> = state
+            .iter()
+            .filter(|(_, record)| {
+                record.schedule.enabled
+                    && record
+                        .schedule
+                        .cron
+                        .parse::<Cron>()
+                        .is_ok_and(|cron| cron.matches(minute))
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        due.sort();
+        due
+    }
+
+    fn save(&self, schedules: &HashMap<String, ScheduleRecord>) -> Result<(), AppError> {
+        write_atomic(
+            &self.dir.join(REGISTRY_FILE),
+            &serde_json::to_vec_pretty(schedules)?,
+        )?;
+        Ok(())
+    }
+}
+
+fn status(id: &str, record: &ScheduleRecord, now: &NaiveDateTime) -> ScheduleStatus {
+    let next_run = record
+        .schedule
+        .enabled
+        .then(|| record.schedule.cron.parse::<Cron>().ok())
+        .flatten()
+        .and_then(|cron| cron.next_after(now))
+        .and_then(|next| Local.from_local_datetime(&next).earliest())
+        .map(|next| next.timestamp() as u64);
+    ScheduleStatus {
+        id: id.to_string(),
+        schedule: record.schedule.clone(),
+        next_run,
+        last_run: record.last_run,
+        last_error: record.last_error.clone(),
+    }
+}
+
+fn now() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
+fn truncate_to_minute(time: &NaiveDateTime) -> NaiveDateTime {
+    time.with_second(0)
+        .and_then(|time| time.with_nanosecond(0))
+        .unwrap_or(*time)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn schedule(cron: &str, text: &str) -> Schedule {
+        Schedule {
+            cron: cron.to_string(),
+            text: text.to_string(),
+            voice: None,
+            enabled: true,
+            play: PlayOptions::default(),
+        }
+    }
+
+    #[test]
+    fn persists_schedules() {
+        let dir = tempfile::tempdir().unwrap();
+        let scheduler = Scheduler::open(dir.path()).unwrap();
+        let statuses = scheduler
+            .put(HashMap::from([
+                (
+                    "hourly".to_string(),
+                    schedule("@hourly", "The time is [time]"),
+                ),
+                ("lunch".to_string(), schedule("0 12 * * mon-fri", "Lunch!")),
+            ]))
+            .unwrap();
+        assert_eq!(statuses.len(), 2);
+        assert!(statuses.iter().all(|status| status.next_run.is_some()));
+
+        let reopened = Scheduler::open(dir.path()).unwrap();
+        assert_eq!(reopened.list().len(), 2);
+        assert_eq!(
+            reopened.status("hourly").unwrap().schedule.text,
+            "The time is [time]"
+        );
+        assert!(reopened.remove("lunch").unwrap());
+        assert!(!reopened.remove("lunch").unwrap());
+        assert_eq!(Scheduler::open(dir.path()).unwrap().list().len(), 1);
+    }
+
+    #[test]
+    fn rejects_invalid_schedules() {
+        let dir = tempfile::tempdir().unwrap();
+        let scheduler = Scheduler::open(dir.path()).unwrap();
+        for (id, schedule) in [
+            ("bad-cron", schedule("every hour", "Hi")),
+            ("no-text", schedule("@hourly", " ")),
+            ("../escape", schedule("@hourly", "Hi")),
+        ] {
+            assert!(scheduler
+                .put(HashMap::from([(id.to_string(), schedule)]))
+                .is_err());
+        }
+        assert!(scheduler.list().is_empty());
+    }
+
+    #[test]
+    fn finds_due_schedules() {
+        let dir = tempfile::tempdir().unwrap();
+        let scheduler = Scheduler::open(dir.path()).unwrap();
+        let mut disabled = schedule("*/15 * * * *", "Off");
+        disabled.enabled = false;
+        scheduler
+            .put(HashMap::from([
+                ("quarterly".to_string(), schedule("*/15 * * * *", "Quarter")),
+                ("hourly".to_string(), schedule("0 * * * *", "Hour")),
+                ("disabled".to_string(), disabled),
+            ]))
+            .unwrap();
+
+        let at = |minute| {
+            NaiveDate::from_ymd_opt(2026, 10, 18)
+                .unwrap()
+                .and_hms_opt(15, minute, 0)
+                .unwrap()
+        };
+        assert_eq!(scheduler.due(&at(0)), ["hourly", "quarterly"]);
+        assert_eq!(scheduler.due(&at(45)), ["quarterly"]);
+        assert!(scheduler.due(&at(7)).is_empty());
+        assert!(scheduler.status("disabled").unwrap().next_run.is_none());
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{NaiveDate, NaiveTime, Timelike};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::dsl;
+use crate::error::AppError;
+use crate::tts::{validate_file_id, write_atomic};
+
+const REGISTRY_FILE: &str = "templates.json";
+
+lazy_static! {
+    static ref PLACEHOLDER: Regex = Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").unwrap();
+}
+
+/// How a variable's value is read out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SayAs {
+    /// As written
+    #[default]
+    Text,
+    /// Letter by letter: "B. B. C."
+    Characters,
+    /// Digit by digit: "4 2"
+    Digits,
+    /// Digit by digit, pausing between groups
+    Telephone,
+    /// `HH:MM`, phrased for the voice's language
+    Time,
+    /// `YYYY-MM-DD`, phrased for the voice's language
+    Date,
+}
+
+/// DSL text with `{{name}}` placeholders, filled in per request
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Template {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub say_as: BTreeMap<String, SayAs>,
+}
+
+impl Template {
+    /// Placeholder names, in order of first use
+    pub fn variables(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for caps in PLACEHOLDER.captures_iter(&self.text) {
+            if !names.iter().any(|name| name == &caps[1]) {
+                names.push(caps[1].to_string());
+            }
+        }
+        names
+    }
+
+    fn validate(&self, name: &str) -> Result<(), AppError> {
+        if self.text.trim().is_empty() {
+            return Err(AppError::BadRequest(format!(
+                "Template '{}' has no text",
+                name
+            )));
+        }
+        let variables = self.variables();
+        if let Some(unused) = self.say_as.keys().find(|key| !variables.contains(key)) {
+            return Err(AppError::BadRequest(format!(
+                "Template '{}' has a say_as hint for '{}', which it doesn't use",
+                name, unused
+            )));
+        }
+        Ok(())
+    }
+
+    /// Fill in the placeholders. Values are escaped, so they can't add DSL
+    /// tags; `language` phrases `time` and `date` values.
+    pub fn render(
+        &self,
+        variables: &HashMap<String, serde_json::Value>,
+        language: &str,
+    ) -> Result<String, AppError> {
+        let expected = self.variables();
+        if let Some(unknown) = variables.keys().find(|key| !expected.contains(key)) {
+            return Err(AppError::BadRequest(format!(
+                "Unknown variable '{}' (expected: {})",
+                unknown,
+                expected.join(", ")
+            )));
+        }
+
+        let mut values = HashMap::new();
+        for name in &expected {
+            let value = match variables.get(name) {
+                Some(serde_json::Value::String(s)) => s.clone(),
+                Some(value @ (serde_json::Value::Number(_) | serde_json::Value::Bool(_))) => {
+                    value.to_string()
+                }
+                Some(_) => {
+                    return Err(AppError::BadRequest(format!(
+                        "Variable '{}' must be a string or number",
+                        name
+                    )))
+                }
+                None => return Err(AppError::BadRequest(format!("Missing variable '{}'", name))),
+            };
+            let say_as = self.say_as.get(name).copied().unwrap_or_default();
+            let spoken = say(name, &value, say_as, language)?;
+            values.insert(name.as_str(), dsl::escape(&spoken).into_owned());
+        }
+
+        Ok(PLACEHOLDER
+            .replace_all(&self.text, |caps: &regex::Captures| {
+                values[&caps[1]].clone()
+            })
+            .into_owned())
+    }
+}
+
+fn say(name: &str, value: &str, say_as: SayAs, language: &str) -> Result<String, AppError> {
+    let spaced = |text: &str| {
+        text.chars()
+            .filter(|c| c.is_alphanumeric())
+            .map(String::from)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    Ok(match say_as {
+        SayAs::Text => value.to_string(),
+        SayAs::Characters => value
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .map(|c| format!("{}.", c.to_uppercase()))
+            .collect::<Vec<_>>()
+            .join(" "),
+        SayAs::Digits => spaced(value),
+        SayAs::Telephone => value
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|group| !group.is_empty())
+            .map(spaced)
+            .collect::<Vec<_>>()
+            .join(", "),
+        SayAs::Time => {
+            let time = NaiveTime::parse_from_str(value.trim(), "%H:%M")
+                .or_else(|_| NaiveTime::parse_from_str(value.trim(), "%H:%M:%S"))
+                .map_err(|_| {
+                    AppError::BadRequest(format!(
+                        "Variable '{}' must be a time (HH:MM), got '{}'",
+                        name, value
+                    ))
+                })?;
+            dsl::clock::time_phrase(language, time.hour(), time.minute())
+        }
+        SayAs::Date => {
+            let date = NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").map_err(|_| {
+                AppError::BadRequest(format!(
+                    "Variable '{}' must be a date (YYYY-MM-DD), got '{}'",
+                    name, value
+                ))
+            })?;
+            dsl::clock::date_phrase(language, date)
+        }
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateInfo {
+    pub name: String,
+    #[serde(flatten)]
+    pub template: Template,
+    pub variables: Vec<String>,
+}
+
+impl TemplateInfo {
+    fn new(name: &str, template: &Template) -> Self {
+        Self {
+            name: name.to_string(),
+            template: template.clone(),
+            variables: template.variables(),
+        }
+    }
+}
+
+/// Named templates persisted to `{dir}/templates.json`
+pub struct TemplateLibrary {
+    dir: PathBuf,
+    templates: Mutex<HashMap<String, Template>>,
+}
+
+impl TemplateLibrary {
+    pub fn open(dir: &Path) -> Result<Self, AppError> {
+        std::fs::create_dir_all(dir)?;
+
+        let registry = dir.join(REGISTRY_FILE);
+        let templates = if registry.exists() {
+            serde_json::from_reader(std::fs::File::open(&registry)?)?
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            templates: Mutex::new(templates),
+        })
+    }
+
+    /// Add or replace templates
+    pub fn put(&self, templates: HashMap<String, Template>) -> Result<Vec<TemplateInfo>, AppError> {
+        for (name, template) in &templates {
+            validate_file_id("template", name)?;
+            template.validate(name)?;
+        }
+
+        let mut state = self.templates.lock().unwrap();
+        let mut names: Vec<String> = templates.keys().cloned().collect();
+        names.sort();
+        state.extend(templates);
+        self.save(&state)?;
+
+        Ok(names
+            .iter()
+            .map(|name| TemplateInfo::new(name, &state[name]))
+            .collect())
+    }
+
+    pub fn list(&self) -> Vec<TemplateInfo> {
+        let state = self.templates.lock().unwrap();
+        let mut templates: Vec<TemplateInfo> = state
+            .iter()
+            .map(|(name, template)| TemplateInfo::new(name, template))
+            .collect();
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        templates
+    }
+
+    pub fn get(&self, name: &str) -> Option<Template> {
+        self.templates.lock().unwrap().get(name).cloned()
+    }
+
+    pub fn info(&self, name: &str) -> Option<TemplateInfo> {
+        let state = self.templates.lock().unwrap();
+        state
+            .get(name)
+            .map(|template| TemplateInfo::new(name, template))
+    }
+
+    pub fn remove(&self, name: &str) -> Result<bool, AppError> {
+        let mut state = self.templates.lock().unwrap();
+        if state.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.save(&state)?;
+        Ok(true)
+    }
+
+    fn save(&self, templates: &HashMap<String, Template>) -> Result<(), AppError> {
+        write_atomic(
+            &self.dir.join(REGISTRY_FILE),
+            &serde_json::to_vec_pretty(templates)?,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn template(text: &str, say_as: &[(&str, SayAs)]) -> Template {
+        Template {
+            text: text.to_string(),
+            voice: None,
+            say_as: say_as
+                .iter()
+                .map(|(name, say_as)| (name.to_string(), *say_as))
+                .collect(),
+        }
+    }
+
+    fn vars(value: serde_json::Value) -> HashMap<String, serde_json::Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn fills_in_variables() {
+        let departure = template(
+            "The {{ service }} to {{destination}} departs from platform {{platform}} at {{time}}.",
+            &[("service", SayAs::Characters), ("time", SayAs::Time)],
+        );
+        assert_eq!(
+            departure.variables(),
+            ["service", "destination", "platform", "time"]
+        );
+        let text = departure
+            .render(
+                &vars(json!({
+                    "service": "ic",
+                    "destination": "Leeds",
+                    "platform": 4,
+                    "time": "14:35",
+                })),
+                "en_GB",
+            )
+            .unwrap();
+        assert_eq!(
+            text,
+            "The I. C. to Leeds departs from platform 4 at twenty-five to three."
+        );
+    }
+
+    #[test]
+    fn escapes_values() {
+        let greeting = template("[emphasis]Hello[/emphasis] {{name}}", &[]);
+        let text = greeting
+            .render(&vars(json!({"name": "[pause:9000] {{name}}"})), "en_GB")
+            .unwrap();
+        assert_eq!(text, "[emphasis]Hello[/emphasis] (pause:9000) {{name}}");
+        assert_eq!(dsl::process(&text), "HELLO (pause:9000) {{name}}");
+    }
+
+    #[test]
+    fn reads_hinted_values() {
+        let read = |value, say_as| say("x", value, say_as, "en_US").unwrap();
+        assert_eq!(read("A12", SayAs::Digits), "A 1 2");
+        assert_eq!(
+            read("0161 496-0000", SayAs::Telephone),
+            "0 1 6 1, 4 9 6, 0 0 0 0"
+        );
+        assert_eq!(
+            read("2026-10-18", SayAs::Date),
+            "Sunday, October eighteenth"
+        );
+        assert!(say("x", "3pm", SayAs::Time, "en_GB").is_err());
+    }
+
+    #[test]
+    fn rejects_bad_variables() {
+        let t = template("Platform {{platform}}", &[]);
+        assert!(t.render(&vars(json!({})), "en").is_err());
+        assert!(t
+            .render(&vars(json!({"platform": 4, "extra": 1})), "en")
+            .is_err());
+        assert!(t.render(&vars(json!({"platform": [4]})), "en").is_err());
+    }
+
+    #[test]
+    fn persists_templates() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = TemplateLibrary::open(dir.path()).unwrap();
+        assert!(library
+            .put(HashMap::from([(
+                "bad".to_string(),
+                template("Hi {{name}}", &[("nmae", SayAs::Characters)]),
+            )]))
+            .is_err());
+        library
+            .put(HashMap::from([(
+                "welcome".to_string(),
+                template("Welcome, {{name}}", &[]),
+            )]))
+            .unwrap();
+
+        let reopened = TemplateLibrary::open(dir.path()).unwrap();
+        assert_eq!(reopened.info("welcome").unwrap().variables, ["name"]);
+        assert!(reopened.remove("welcome").unwrap());
+        assert!(TemplateLibrary::open(dir.path()).unwrap().list().is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+/// Symbols every Piper phoneme map needs: BOS, EOS and padding
+const REQUIRED_SYMBOLS: [&str; 3] = ["^", "$", "_"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    Config,
+    PhonemeMap,
+    Session,
+    Signature,
+    Synthesis,
+}
+
+impl Check {
+    pub const ALL: [Check; 5] = [
+        Check::Config,
+        Check::PhonemeMap,
+        Check::Session,
+        Check::Signature,
+        Check::Synthesis,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Check::Config => "config",
+            Check::PhonemeMap => "phonemes",
+            Check::Session => "session",
+            Check::Signature => "signature",
+            Check::Synthesis => "synthesis",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub check: Check,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Outcome of validating one voice; checks after a fatal failure are absent
+#[derive(Debug, Clone, Serialize)]
+pub struct VoiceStatus {
+    pub voice: String,
+    pub ok: bool,
+    pub checks: Vec<CheckResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub synthesis_ms: Option<u64>,
+}
+
+impl VoiceStatus {
+    pub fn new(voice: &str) -> Self {
+        Self {
+            voice: voice.to_string(),
+            ok: true,
+            checks: Vec::new(),
+            synthesis_ms: None,
+        }
+    }
+
+    pub fn pass(&mut self, check: Check) {
+        self.checks.push(CheckResult {
+            check,
+            ok: true,
+            detail: None,
+        });
+    }
+
+    pub fn fail(&mut self, check: Check, detail: String) {
+        self.ok = false;
+        self.checks.push(CheckResult {
+            check,
+            ok: false,
+            detail: Some(detail),
+        });
+    }
+
+    pub fn result(&self, check: Check) -> Option<&CheckResult> {
+        self.checks.iter().find(|c| c.check == check)
+    }
+}
+
+pub fn missing_phoneme_symbols(id_map: &HashMap<String, Vec<i64>>) -> Vec<&'static str> {
+    REQUIRED_SYMBOLS
+        .iter()
+        .copied()
+        .filter(|symbol| !id_map.contains_key(*symbol))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_missing_symbols() {
+        let mut map = HashMap::new();
+        map.insert("^".to_string(), vec![1]);
+        assert_eq!(missing_phoneme_symbols(&map), vec!["$", "_"]);
+
+        map.insert("$".to_string(), vec![2]);
+        map.insert("_".to_string(), vec![0]);
+        assert!(missing_phoneme_symbols(&map).is_empty());
+    }
+
+    #[test]
+    fn any_failure_fails_the_voice() {
+        let mut status = VoiceStatus::new("v");
+        status.pass(Check::Config);
+        assert!(status.ok);
+        status.fail(Check::PhonemeMap, "missing $".into());
+        assert!(!status.ok);
+        assert!(status.result(Check::Config).unwrap().ok);
+        assert!(!status.result(Check::PhonemeMap).unwrap().ok);
+        assert!(status.result(Check::Session).is_none());
+    }
+}
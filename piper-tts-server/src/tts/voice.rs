@@ -10,14 +10,42 @@ pub struct VoiceConfig {
     pub audio: AudioConfig,
     pub espeak: Option<EspeakConfig>,
     #[serde(default)]
+    pub language: Option<LanguageConfig>,
+    #[serde(default)]
+    pub dataset: Option<String>,
+    #[serde(default = "default_num_speakers")]
+    pub num_speakers: u32,
+    #[serde(default)]
+    pub speaker_id_map: HashMap<String, i64>,
+    #[serde(default)]
     pub phoneme_id_map: HashMap<String, Vec<i64>>,
     #[serde(default)]
     pub inference: Option<InferenceConfig>,
+    #[serde(default)]
+    pub piper_version: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct AudioConfig {
     pub sample_rate: u32,
+    #[serde(default)]
+    pub quality: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LanguageConfig {
+    /// e.g. `en_GB`
+    pub code: String,
+    #[serde(default)]
+    pub family: Option<String>,
+    #[serde(default)]
+    pub region: Option<String>,
+    #[serde(default)]
+    pub name_native: Option<String>,
+    #[serde(default)]
+    pub name_english: Option<String>,
+    #[serde(default)]
+    pub country_english: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -25,7 +53,7 @@ pub struct EspeakConfig {
     pub voice: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct InferenceConfig {
     #[serde(default = "default_noise_scale")]
     pub noise_scale: f32,
@@ -35,6 +63,10 @@ pub struct InferenceConfig {
     pub noise_w: f32,
 }
 
+fn default_num_speakers() -> u32 {
+    1
+}
+
 fn default_noise_scale() -> f32 {
     0.667
 }
@@ -47,6 +79,37 @@ fn default_noise_w() -> f32 {
     0.8
 }
 
+/// Per-request overrides for a voice's inference parameters
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct InferenceParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noise_scale: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length_scale: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noise_w: Option<f32>,
+}
+
+impl InferenceParams {
+    /// Fill any unset values from `fallback`
+    pub fn or(&self, fallback: &InferenceParams) -> InferenceParams {
+        InferenceParams {
+            noise_scale: self.noise_scale.or(fallback.noise_scale),
+            length_scale: self.length_scale.or(fallback.length_scale),
+            noise_w: self.noise_w.or(fallback.noise_w),
+        }
+    }
+
+    /// Apply these overrides on top of a voice's configured defaults
+    pub fn apply(&self, base: &InferenceConfig) -> InferenceConfig {
+        InferenceConfig {
+            noise_scale: self.noise_scale.unwrap_or(base.noise_scale),
+            length_scale: self.length_scale.unwrap_or(base.length_scale),
+            noise_w: self.noise_w.unwrap_or(base.noise_w),
+        }
+    }
+}
+
 impl Default for InferenceConfig {
     fn default() -> Self {
         Self {
@@ -59,7 +122,6 @@ impl Default for InferenceConfig {
 
 #[derive(Debug)]
 pub struct Voice {
-    #[allow(dead_code)]
     pub id: String,
     pub config: VoiceConfig,
     pub model_path: PathBuf,
@@ -67,6 +129,11 @@ pub struct Voice {
 
 impl Voice {
     pub fn load(voices_dir: &Path, voice_id: &str) -> Result<Self, AppError> {
+        // Voice ids come from clients; keep them inside the voices directory
+        if voice_id.is_empty() || voice_id.starts_with('.') || voice_id.contains(['/', '\\']) {
+            return Err(AppError::VoiceNotFound(voice_id.to_string()));
+        }
+
         let model_path = voices_dir.join(format!("{}.onnx", voice_id));
         let config_path = voices_dir.join(format!("{}.onnx.json", voice_id));
 
@@ -91,9 +158,117 @@ impl Voice {
     }
 }
 
+impl VoiceConfig {
+    /// Speaker names ordered by speaker id
+    pub fn speakers(&self) -> Vec<String> {
+        let mut speakers: Vec<(&String, &i64)> = self.speaker_id_map.iter().collect();
+        speakers.sort_by_key(|(_, id)| **id);
+        speakers.into_iter().map(|(name, _)| name.clone()).collect()
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct VoiceInfo {
     pub id: String,
     pub name: String,
+    /// The espeak voice (e.g. `en-gb-x-rp`); see `language_code` for the
+    /// language itself
     pub language: String,
+    /// Language code (e.g. `en_GB`), or the espeak voice for older configs
+    pub language_code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language_family: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language_region: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language_name_native: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub espeak_voice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dataset: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<String>,
+    pub sample_rate: u32,
+    pub num_speakers: u32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub speakers: Vec<String>,
+}
+
+impl VoiceInfo {
+    /// Match a `language` filter against the language code or family,
+    /// so both `en_GB` and `en` select British English voices.
+    pub fn matches_language(&self, language: &str) -> bool {
+        self.language_code.eq_ignore_ascii_case(language)
+            || self
+                .language_family
+                .as_deref()
+                .map(|f| f.eq_ignore_ascii_case(language))
+                .unwrap_or(false)
+    }
+
+    pub fn matches_quality(&self, quality: &str) -> bool {
+        self.quality
+            .as_deref()
+            .map(|q| q.eq_ignore_ascii_case(quality))
+            .unwrap_or(false)
+    }
+}
+
+/// Full description of a single voice for `/api/voices/{id}`
+#[derive(Debug, Clone, Serialize)]
+pub struct VoiceDetail {
+    #[serde(flatten)]
+    pub info: VoiceInfo,
+    pub model_size_bytes: u64,
+    pub loaded: bool,
+    pub inference: InferenceConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub piper_version: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIPER_CONFIG: &str = r#"{
+        "dataset": "vctk",
+        "audio": { "sample_rate": 22050, "quality": "medium" },
+        "espeak": { "voice": "en-gb-x-rp" },
+        "language": {
+            "code": "en_GB",
+            "family": "en",
+            "region": "GB",
+            "name_native": "English",
+            "name_english": "English",
+            "country_english": "Great Britain"
+        },
+        "inference": { "noise_scale": 0.667, "length_scale": 1, "noise_w": 0.8 },
+        "phoneme_id_map": { "^": [1], "$": [2], "_": [0] },
+        "num_speakers": 3,
+        "speaker_id_map": { "p239": 1, "p236": 0, "p264": 2 },
+        "piper_version": "1.0.0"
+    }"#;
+
+    #[test]
+    fn parses_piper_metadata() {
+        let config: VoiceConfig = serde_json::from_str(PIPER_CONFIG).unwrap();
+        assert_eq!(config.dataset.as_deref(), Some("vctk"));
+        assert_eq!(config.audio.quality.as_deref(), Some("medium"));
+        assert_eq!(config.language.as_ref().unwrap().code, "en_GB");
+        assert_eq!(config.num_speakers, 3);
+        assert_eq!(config.speakers(), vec!["p236", "p239", "p264"]);
+    }
+
+    #[test]
+    fn parses_minimal_config() {
+        let config: VoiceConfig =
+            serde_json::from_str(r#"{ "audio": { "sample_rate": 16000 } }"#).unwrap();
+        assert!(config.language.is_none());
+        assert_eq!(config.num_speakers, 1);
+        assert!(config.speakers().is_empty());
+    }
 }
@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+
+use crate::error::AppError;
+
+/// How long the directory must be quiet before changes are applied, so a
+/// model that is still being copied in isn't loaded half-written.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VoiceEventKind {
+    Added,
+    Changed,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VoiceEvent {
+    pub voice: String,
+    pub kind: VoiceEventKind,
+    /// Whether a loaded engine was reloaded from the new files
+    pub reloaded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Watches the voices directory and reports debounced per-voice changes.
+///
+/// The underlying watcher stops when this value is dropped.
+pub struct VoiceWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl VoiceWatcher {
+    pub fn start<F>(voices_dir: &Path, on_change: F) -> Result<Self, AppError>
+    where
+        F: Fn(&str, VoiceEventKind) + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel::<PathBuf>();
+
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+                Ok(event) => {
+                    for path in event.paths {
+                        let _ = tx.send(path);
+                    }
+                }
+                Err(e) => tracing::warn!("Voice watcher error: {}", e),
+            })
+            .map_err(|e| AppError::TtsError(format!("Failed to create voice watcher: {}", e)))?;
+
+        watcher
+            .watch(voices_dir, RecursiveMode::NonRecursive)
+            .map_err(|e| {
+                AppError::TtsError(format!("Failed to watch {}: {}", voices_dir.display(), e))
+            })?;
+
+        let voices_dir = voices_dir.to_path_buf();
+        std::thread::spawn(move || {
+            let mut known = scan_voices(&voices_dir);
+            let mut pending: HashSet<String> = HashSet::new();
+
+            loop {
+                match rx.recv_timeout(DEBOUNCE) {
+                    Ok(path) => {
+                        if let Some(id) = voice_id_from_path(&path) {
+                            pending.insert(id);
+                        }
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        for id in pending.drain() {
+                            let present = voice_files_present(&voices_dir, &id);
+                            let kind = match (known.contains(&id), present) {
+                                (false, true) => VoiceEventKind::Added,
+                                (true, true) => VoiceEventKind::Changed,
+                                (true, false) => VoiceEventKind::Removed,
+                                // Partial files for a voice we never had
+                                (false, false) => continue,
+                            };
+                            if present {
+                                known.insert(id.clone());
+                            } else {
+                                known.remove(&id);
+                            }
+                            on_change(&id, kind);
+                        }
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+
+        tracing::info!("Watching voices directory for changes");
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+/// Map a model or config file to its voice id
+fn voice_id_from_path(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    name.strip_suffix(".onnx.json")
+        .or_else(|| name.strip_suffix(".onnx"))
+        .filter(|id| !id.is_empty())
+        .map(|id| id.to_string())
+}
+
+fn voice_files_present(voices_dir: &Path, voice_id: &str) -> bool {
+    voices_dir.join(format!("{}.onnx", voice_id)).exists()
+        && voices_dir.join(format!("{}.onnx.json", voice_id)).exists()
+}
+
+fn scan_voices(voices_dir: &Path) -> HashSet<String> {
+    std::fs::read_dir(voices_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| voice_id_from_path(&entry.path()))
+                .filter(|id| voice_files_present(voices_dir, id))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_model_and_config_to_voice_id() {
+        assert_eq!(
+            voice_id_from_path(Path::new("voices/en_GB-alba-medium.onnx")).as_deref(),
+            Some("en_GB-alba-medium")
+        );
+        assert_eq!(
+            voice_id_from_path(Path::new("voices/en_GB-alba-medium.onnx.json")).as_deref(),
+            Some("en_GB-alba-medium")
+        );
+    }
+
+    #[test]
+    fn ignores_unrelated_files() {
+        assert_eq!(voice_id_from_path(Path::new("voices/.gitkeep")), None);
+        assert_eq!(voice_id_from_path(Path::new("voices/readme.txt")), None);
+        assert_eq!(voice_id_from_path(Path::new("voices/.onnx")), None);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::error::AppError;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const VERSION: u16 = 20;
+/// Names are UTF-8
+const FLAGS: u16 = 0x0800;
+/// 1980-01-01 00:00, the earliest DOS date
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Builds a ZIP archive in memory with stored (uncompressed) entries,
+/// which is all audio needs. No ZIP64, so archives stay under 4 GiB.
+#[derive(Default)]
+pub struct ZipWriter {
+    data: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: &str, contents: &[u8]) -> Result<(), AppError> {
+        let too_large = || AppError::BadRequest("Archive too large (4 GiB max)".into());
+        if self.entries.len() >= u16::MAX as usize {
+            return Err(AppError::BadRequest("Too many archive entries".into()));
+        }
+        let offset = u32::try_from(self.data.len()).map_err(|_| too_large())?;
+        let size = u32::try_from(contents.len()).map_err(|_| too_large())?;
+        if (offset as u64) + 30 + name.len() as u64 + size as u64 > u32::MAX as u64 {
+            return Err(too_large());
+        }
+        let entry = Entry {
+            name: name.to_string(),
+            crc: crc32fast::hash(contents),
+            size,
+            offset,
+        };
+
+        put_u32(&mut self.data, LOCAL_HEADER);
+        put_u16(&mut self.data, VERSION);
+        put_common(&mut self.data, &entry);
+        put_u16(&mut self.data, 0); // extra field length
+        self.data.extend_from_slice(entry.name.as_bytes());
+        self.data.extend_from_slice(contents);
+
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<Vec<u8>, AppError> {
+        let too_large = || AppError::BadRequest("Archive too large (4 GiB max)".into());
+        let directory_offset = u32::try_from(self.data.len()).map_err(|_| too_large())?;
+        for entry in &self.entries {
+            put_u32(&mut self.data, CENTRAL_HEADER);
+            put_u16(&mut self.data, VERSION); // made by
+            put_u16(&mut self.data, VERSION); // needed to extract
+            put_common(&mut self.data, entry);
+            put_u16(&mut self.data, 0); // extra field length
+            put_u16(&mut self.data, 0); // comment length
+            put_u16(&mut self.data, 0); // disk number
+            put_u16(&mut self.data, 0); // internal attributes
+            put_u32(&mut self.data, 0); // external attributes
+            put_u32(&mut self.data, entry.offset);
+            self.data.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size =
+            u32::try_from(self.data.len()).map_err(|_| too_large())? - directory_offset;
+
+        put_u32(&mut self.data, END_OF_CENTRAL_DIRECTORY);
+        put_u16(&mut self.data, 0); // this disk
+        put_u16(&mut self.data, 0); // disk with the directory
+        put_u16(&mut self.data, self.entries.len() as u16);
+        put_u16(&mut self.data, self.entries.len() as u16);
+        put_u32(&mut self.data, directory_size);
+        put_u32(&mut self.data, directory_offset);
+        put_u16(&mut self.data, 0); // comment length
+        Ok(self.data)
+    }
+}
+
+/// Fields shared by local and central headers, up to the name length
+fn put_common(out: &mut Vec<u8>, entry: &Entry) {
+    put_u16(out, FLAGS);
+    put_u16(out, 0); // stored
+    put_u16(out, DOS_TIME);
+    put_u16(out, DOS_DATE);
+    put_u32(out, entry.crc);
+    put_u32(out, entry.size); // compressed
+    put_u32(out, entry.size);
+    put_u16(out, entry.name.len() as u16);
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(data: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(data[at..at + 2].try_into().unwrap())
+    }
+
+    fn u32_at(data: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn writes_stored_entries() {
+        let mut zip = ZipWriter::new();
+        zip.add("a.txt", b"hello").unwrap();
+        zip.add("b.txt", b"").unwrap();
+        let data = zip.finish().unwrap();
+
+        assert_eq!(u32_at(&data, 0), LOCAL_HEADER);
+        assert_eq!(u32_at(&data, 14), 0x3610_a686); // crc32("hello")
+        assert_eq!(u32_at(&data, 22), 5);
+        assert_eq!(&data[30..35], b"a.txt");
+        assert_eq!(&data[35..40], b"hello");
+
+        let end = data.len() - 22;
+        assert_eq!(u32_at(&data, end), END_OF_CENTRAL_DIRECTORY);
+        assert_eq!(u16_at(&data, end + 10), 2);
+        let directory = u32_at(&data, end + 16) as usize;
+        assert_eq!(directory + u32_at(&data, end + 12) as usize, end);
+        assert_eq!(u32_at(&data, directory), CENTRAL_HEADER);
+        assert_eq!(u32_at(&data, directory + 42), 0);
+        assert_eq!(&data[directory + 46..directory + 51], b"a.txt");
+    }
+}
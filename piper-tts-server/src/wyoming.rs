@@ -0,0 +1,401 @@
+//! Wyoming protocol server, so Home Assistant (and other Wyoming clients)
+//! can use the installed voices as a text-to-speech service.
+//!
+//! Every event is a JSON header line, optionally followed by
+//! `data_length` bytes of extra JSON data and `payload_length` bytes of
+//! binary payload.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::api::auth::Caller;
+use crate::api::routes::AppState;
+use crate::config::AuthConfig;
+use crate::error::AppError;
+use crate::tts::{Audio, AudioFormat, VoiceInfo};
+
+/// Protocol version we speak
+pub const VERSION: &str = "1.5.2";
+
+/// Samples per `audio-chunk` event
+pub const SAMPLES_PER_CHUNK: usize = 1024;
+
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+const MAX_DATA_BYTES: usize = 1024 * 1024;
+const MAX_PAYLOAD_BYTES: usize = 16 * 1024 * 1024;
+
+/// What the Wyoming server needs from the TTS engine
+pub trait Synthesizer: Send + Sync + 'static {
+    fn voices(&self) -> Result<Vec<VoiceInfo>, AppError>;
+
+    /// Synthesize `text` with a voice id or alias (the default if `None`)
+    /// for the client at `peer`
+    fn synthesize(&self, text: &str, voice: Option<&str>, peer: IpAddr) -> Result<Audio, AppError>;
+}
+
+impl Synthesizer for AppState {
+    fn voices(&self) -> Result<Vec<VoiceInfo>, AppError> {
+        self.tts.list_voices()
+    }
+
+    /// Held to the same voice rules and per-IP limits as HTTP clients
+    /// without a key
+    fn synthesize(&self, text: &str, voice: Option<&str>, peer: IpAddr) -> Result<Audio, AppError> {
+        let chars = text.chars().count();
+        let max = self.config.server.max_text_length;
+        if chars > max {
+            return Err(AppError::BadRequest(format!(
+                "Text too long (max {} chars)",
+                max
+            )));
+        }
+        let caller = Caller::from_ip(peer);
+        caller.check_voice(&self.tts, voice)?;
+        self.limiter.admit(&caller, chars)?;
+        self.limiter
+            .refund_on_error(&caller, chars, self.tts.synthesize(text, voice))
+    }
+}
+
+/// One protocol message
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Event {
+    pub kind: String,
+    pub data: Map<String, Value>,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Serialize)]
+struct Header<'a> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    version: &'static str,
+    #[serde(skip_serializing_if = "is_zero")]
+    data_length: usize,
+    #[serde(skip_serializing_if = "is_zero")]
+    payload_length: usize,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+impl Event {
+    pub fn new(kind: &str, data: Value) -> Self {
+        Self {
+            kind: kind.to_string(),
+            data: match data {
+                Value::Object(map) => map,
+                _ => Map::new(),
+            },
+            payload: Vec::new(),
+        }
+    }
+
+    /// Read the next event; `None` at end of stream
+    pub async fn read<R: AsyncBufRead + Unpin>(reader: &mut R) -> std::io::Result<Option<Event>> {
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let read = (&mut *reader)
+                .take(MAX_HEADER_BYTES as u64 + 1)
+                .read_until(b'\n', &mut line)
+                .await?;
+            if read == 0 {
+                return Ok(None);
+            }
+            if line.len() > MAX_HEADER_BYTES {
+                return Err(invalid_data("event header too long"));
+            }
+            if !line.iter().all(u8::is_ascii_whitespace) {
+                break;
+            }
+        }
+
+        let header: Map<String, Value> = serde_json::from_slice(&line)
+            .map_err(|e| invalid_data(&format!("invalid event header: {}", e)))?;
+        let kind = header
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid_data("event header has no type"))?
+            .to_string();
+        let length = |field: &str, max: usize| -> std::io::Result<usize> {
+            let length = header.get(field).and_then(Value::as_u64).unwrap_or(0) as usize;
+            if length > max {
+                return Err(invalid_data(&format!("{} {} is too large", field, length)));
+            }
+            Ok(length)
+        };
+        let data_length = length("data_length", MAX_DATA_BYTES)?;
+        let payload_length = length("payload_length", MAX_PAYLOAD_BYTES)?;
+
+        // Older clients send data inline in the header
+        let mut data = match header.get("data") {
+            Some(Value::Object(data)) => data.clone(),
+            _ => Map::new(),
+        };
+        if data_length > 0 {
+            let mut bytes = vec![0; data_length];
+            reader.read_exact(&mut bytes).await?;
+            let extra: Map<String, Value> = serde_json::from_slice(&bytes)
+                .map_err(|e| invalid_data(&format!("invalid event data: {}", e)))?;
+            data.extend(extra);
+        }
+
+        let mut payload = vec![0; payload_length];
+        reader.read_exact(&mut payload).await?;
+
+        Ok(Some(Event {
+            kind,
+            data,
+            payload,
+        }))
+    }
+
+    pub async fn write<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> std::io::Result<()> {
+        let data = if self.data.is_empty() {
+            Vec::new()
+        } else {
+            serde_json::to_vec(&self.data)?
+        };
+        let mut message = serde_json::to_vec(&Header {
+            kind: &self.kind,
+            version: VERSION,
+            data_length: data.len(),
+            payload_length: self.payload.len(),
+        })?;
+        message.push(b'\n');
+        message.extend_from_slice(&data);
+        message.extend_from_slice(&self.payload);
+        writer.write_all(&message).await
+    }
+}
+
+fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Address to listen on: `server.host`, or loopback once `auth.keys` are
+/// set, as Wyoming clients have no way to present a key
+pub fn listen_ip(auth: &AuthConfig, host: IpAddr) -> IpAddr {
+    match host {
+        _ if auth.keys.is_empty() || host.is_loopback() => host,
+        IpAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+        IpAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+    }
+}
+
+/// Accept Wyoming clients on `listener` until it fails
+pub async fn serve<S: Synthesizer>(
+    listener: TcpListener,
+    synthesizer: Arc<S>,
+) -> std::io::Result<()> {
+    if let Ok(addr) = listener.local_addr() {
+        tracing::info!("Wyoming server listening on tcp://{}", addr);
+    }
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let synthesizer = Arc::clone(&synthesizer);
+        tokio::spawn(async move {
+            tracing::debug!("Wyoming client {} connected", peer);
+            if let Err(e) = handle_client(stream, peer.ip(), synthesizer).await {
+                tracing::warn!("Wyoming client {}: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle_client<S: Synthesizer>(
+    stream: TcpStream,
+    peer: IpAddr,
+    synthesizer: Arc<S>,
+) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    while let Some(event) = Event::read(&mut reader).await? {
+        match event.kind.as_str() {
+            "describe" => {
+                let synthesizer = Arc::clone(&synthesizer);
+                let voices = tokio::task::spawn_blocking(move || synthesizer.voices())
+                    .await
+                    .map_err(std::io::Error::other)?;
+                match voices {
+                    Ok(voices) => info(&voices).write(&mut writer).await?,
+                    Err(e) => error_event(&e).write(&mut writer).await?,
+                }
+            }
+            "synthesize" => {
+                let text = event
+                    .data
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let voice = event
+                    .data
+                    .get("voice")
+                    .and_then(|voice| voice.get("name"))
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+
+                let synthesizer = Arc::clone(&synthesizer);
+                let audio = tokio::task::spawn_blocking(move || {
+                    if text.trim().is_empty() {
+                        return Err(AppError::BadRequest("Text cannot be empty".into()));
+                    }
+                    synthesizer.synthesize(&text, voice.as_deref(), peer)
+                })
+                .await
+                .map_err(std::io::Error::other)?;
+
+                match audio
+                    .and_then(|audio| Ok((audio.sample_rate, audio.encode(AudioFormat::Pcm)?)))
+                {
+                    Ok((rate, pcm)) => write_audio(&mut writer, rate, &pcm).await?,
+                    Err(e) => {
+                        tracing::warn!("Wyoming synthesis failed: {}", e);
+                        error_event(&e).write(&mut writer).await?
+                    }
+                }
+            }
+            "ping" => {
+                Event::new("pong", Value::Object(event.data))
+                    .write(&mut writer)
+                    .await?
+            }
+            other => tracing::debug!("Ignoring Wyoming event '{}'", other),
+        }
+        writer.flush().await?;
+    }
+    Ok(())
+}
+
+/// Stream 16-bit mono PCM as `audio-start`, `audio-chunk`s and `audio-stop`
+async fn write_audio<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    rate: u32,
+    pcm: &[u8],
+) -> std::io::Result<()> {
+    let format = json!({ "rate": rate, "width": 2, "channels": 1 });
+    Event::new("audio-start", format.clone())
+        .write(writer)
+        .await?;
+    for chunk in pcm.chunks(SAMPLES_PER_CHUNK * 2) {
+        let mut event = Event::new("audio-chunk", format.clone());
+        event.payload = chunk.to_vec();
+        event.write(writer).await?;
+    }
+    Event::new("audio-stop", json!({})).write(writer).await
+}
+
+/// Describe this server and its voices in the shape Home Assistant expects
+fn info(voices: &[VoiceInfo]) -> Event {
+    let attribution = json!({ "name": "rhasspy", "url": "https://github.com/rhasspy/piper" });
+    let voices: Vec<Value> = voices
+        .iter()
+        .map(|voice| {
+            json!({
+                "name": voice.id,
+                "description": match &voice.quality {
+                    Some(quality) => format!("{} ({})", voice.name, quality),
+                    None => voice.name.clone(),
+                },
+                "attribution": attribution,
+                "installed": true,
+                "version": null,
+                "languages": [voice.language_code],
+                "speakers": if voice.speakers.is_empty() {
+                    Value::Null
+                } else {
+                    voice.speakers.iter().map(|name| json!({ "name": name })).collect()
+                },
+            })
+        })
+        .collect();
+
+    Event::new(
+        "info",
+        json!({
+            "tts": [{
+                "name": "piper",
+                "description": "Piper TTS Server",
+                "attribution": attribution,
+                "installed": true,
+                "version": env!("CARGO_PKG_VERSION"),
+                "voices": voices,
+            }],
+            "asr": [],
+            "handle": [],
+            "intent": [],
+            "wake": [],
+            "mic": [],
+            "snd": [],
+        }),
+    )
+}
+
+fn error_event(error: &AppError) -> Event {
+    let (_, code, message) = error.parts();
+    Event::new("error", json!({ "text": message, "code": code }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn round_trips_events() {
+        let mut event = Event::new("audio-chunk", json!({ "rate": 22050 }));
+        event.payload = vec![1, 2, 3];
+        let mut bytes = Vec::new();
+        event.write(&mut bytes).await.unwrap();
+
+        let header_end = bytes.iter().position(|&b| b == b'\n').unwrap();
+        let header: Value = serde_json::from_slice(&bytes[..header_end]).unwrap();
+        assert_eq!(header["type"], "audio-chunk");
+        assert_eq!(header["payload_length"], 3);
+
+        let mut reader = &bytes[..];
+        assert_eq!(Event::read(&mut reader).await.unwrap(), Some(event));
+        assert_eq!(Event::read(&mut reader).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn reads_inline_data_and_rejects_huge_payloads() {
+        let mut reader = &b"{\"type\":\"synthesize\",\"data\":{\"text\":\"Hi\"}}\n"[..];
+        let event = Event::read(&mut reader).await.unwrap().unwrap();
+        assert_eq!(event.data["text"], "Hi");
+
+        let mut reader = &b"{\"type\":\"audio-chunk\",\"payload_length\":999999999}\n"[..];
+        assert!(Event::read(&mut reader).await.is_err());
+    }
+
+    #[test]
+    fn listens_on_loopback_once_keys_are_set() {
+        let mut auth = AuthConfig::default();
+        let any: IpAddr = "0.0.0.0".parse().unwrap();
+        assert_eq!(listen_ip(&auth, any), any);
+
+        auth.keys.push(crate::config::ApiKey {
+            name: "kiosk".into(),
+            key: "kiosk-key-0123456789".into(),
+            scopes: vec![crate::api::auth::Scope::Synthesize],
+            voices: None,
+            requests_per_minute: None,
+            daily_chars: None,
+        });
+        assert_eq!(listen_ip(&auth, any), IpAddr::from(Ipv4Addr::LOCALHOST));
+        assert_eq!(
+            listen_ip(&auth, "::".parse().unwrap()),
+            IpAddr::from(Ipv6Addr::LOCALHOST)
+        );
+    }
+}
@@ -24,7 +24,7 @@ async function loadVoices() {
         }
 
         voiceSelect.innerHTML = data.voices
-            .map(v => `<option value="${v.id}">${v.name || v.id} (${v.language})</option>`)
+            .map(v => `<option value="${v.id}">${v.name || v.id} (${v.language_code || v.language})</option>`)
             .join('');
 
     } catch (err) {
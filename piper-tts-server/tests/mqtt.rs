@@ -0,0 +1,305 @@
+//! Drives the MQTT client through a minimal in-process broker, against a
+//! stand-in announcer so no voice model or sound card is needed.
+#![cfg(feature = "mqtt")]
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::body::Bytes;
+use piper_tts_server::api::{SpeakAloudRequest, SpeakRequest};
+use piper_tts_server::config::{MqttAction, MqttConfig, MqttTopic};
+use piper_tts_server::error::AppError;
+use piper_tts_server::mqtt::{self, Announcer};
+use piper_tts_server::tts::playback::QueuedItem;
+use piper_tts_server::tts::AudioFormat;
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+struct FakeAnnouncer;
+
+impl FakeAnnouncer {
+    fn check(text: &str, voice: Option<&str>) -> Result<(), AppError> {
+        if text.is_empty() {
+            return Err(AppError::BadRequest("Text cannot be empty".into()));
+        }
+        if let Some(voice) = voice.filter(|voice| *voice != "en_US-test-low") {
+            return Err(AppError::VoiceNotFound(voice.to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl Announcer for FakeAnnouncer {
+    fn speak_aloud(&self, request: SpeakAloudRequest) -> Result<QueuedItem, AppError> {
+        Self::check(&request.text, request.voice.as_deref())?;
+        Ok(QueuedItem {
+            id: 1,
+            duration_ms: 100 * request.text.len() as u64,
+            text: request.text,
+            voice: request.voice,
+            priority: request.options.priority,
+            device: request.options.device,
+            volume: request.options.volume,
+            chime: request.options.chime,
+        })
+    }
+
+    fn speak(&self, request: &SpeakRequest, format: AudioFormat) -> Result<Bytes, AppError> {
+        Self::check(&request.text, request.voice.as_deref())?;
+        Ok(Bytes::from(format!(
+            "{}:{}",
+            format.extension(),
+            request.text
+        )))
+    }
+
+    fn speak_url(&self, request: &SpeakRequest, format: AudioFormat) -> Result<String, AppError> {
+        Self::check(&request.text, request.voice.as_deref())?;
+        Ok(format!(
+            "/api/speak?text={}&format={}",
+            request.text,
+            format.extension()
+        ))
+    }
+}
+
+/// Accepts one client, acknowledges everything it sends, records what it
+/// publishes and forwards `publish` calls to it
+struct Broker {
+    subscriptions: Arc<Mutex<Vec<String>>>,
+    to_client: mpsc::UnboundedSender<Vec<u8>>,
+    published: mpsc::UnboundedReceiver<(String, Vec<u8>)>,
+}
+
+impl Broker {
+    async fn start() -> (Broker, SocketAddr) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let subscriptions = Arc::new(Mutex::new(Vec::new()));
+        let (to_client, mut outgoing) = mpsc::unbounded_channel::<Vec<u8>>();
+        let (published_tx, published) = mpsc::unbounded_channel();
+
+        let client_subscriptions = Arc::clone(&subscriptions);
+        let replies = to_client.clone();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (mut reader, mut writer) = stream.into_split();
+            tokio::spawn(async move {
+                while let Some(bytes) = outgoing.recv().await {
+                    if writer.write_all(&bytes).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            while let Some((kind, body)) = read_packet(&mut reader).await {
+                match kind >> 4 {
+                    // CONNECT
+                    1 => replies.send(packet(0x20, &[0, 0])).unwrap(),
+                    // PUBLISH
+                    3 => {
+                        let (topic, rest) = string(&body);
+                        let rest = if (kind >> 1) & 3 > 0 {
+                            replies.send(packet(0x40, &rest[..2])).unwrap();
+                            &rest[2..]
+                        } else {
+                            rest
+                        };
+                        published_tx.send((topic, rest.to_vec())).unwrap();
+                    }
+                    // SUBSCRIBE
+                    8 => {
+                        let mut rest = &body[2..];
+                        let mut granted = body[..2].to_vec();
+                        while !rest.is_empty() {
+                            let (filter, after) = string(rest);
+                            client_subscriptions.lock().unwrap().push(filter);
+                            granted.push(1);
+                            rest = &after[1..];
+                        }
+                        replies.send(packet(0x90, &granted)).unwrap();
+                    }
+                    // PINGREQ
+                    12 => replies.send(packet(0xd0, &[])).unwrap(),
+                    _ => {}
+                }
+            }
+        });
+
+        (
+            Broker {
+                subscriptions,
+                to_client,
+                published,
+            },
+            addr,
+        )
+    }
+
+    async fn wait_for_subscriptions(&self, count: usize) {
+        tokio::time::timeout(TIMEOUT, async {
+            while self.subscriptions.lock().unwrap().len() < count {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("client never subscribed");
+    }
+
+    fn publish(&self, topic: &str, payload: &[u8]) {
+        let mut body = encode_string(topic);
+        body.extend_from_slice(payload);
+        self.to_client.send(packet(0x30, &body)).unwrap();
+    }
+
+    async fn next_published(&mut self) -> (String, Vec<u8>) {
+        tokio::time::timeout(TIMEOUT, self.published.recv())
+            .await
+            .expect("nothing was published")
+            .unwrap()
+    }
+}
+
+async fn read_packet(reader: &mut OwnedReadHalf) -> Option<(u8, Vec<u8>)> {
+    let kind = reader.read_u8().await.ok()?;
+    let mut len = 0usize;
+    for shift in (0..28).step_by(7) {
+        let byte = reader.read_u8().await.ok()?;
+        len |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).await.ok()?;
+    Some((kind, body))
+}
+
+fn packet(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![kind];
+    let mut len = body.len();
+    loop {
+        let byte = (len % 128) as u8;
+        len /= 128;
+        bytes.push(if len > 0 { byte | 0x80 } else { byte });
+        if len == 0 {
+            break;
+        }
+    }
+    bytes.extend_from_slice(body);
+    bytes
+}
+
+fn encode_string(s: &str) -> Vec<u8> {
+    let mut bytes = (s.len() as u16).to_be_bytes().to_vec();
+    bytes.extend_from_slice(s.as_bytes());
+    bytes
+}
+
+/// A length-prefixed string and what follows it
+fn string(bytes: &[u8]) -> (String, &[u8]) {
+    let len = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+    (
+        String::from_utf8(bytes[2..2 + len].to_vec()).unwrap(),
+        &bytes[2 + len..],
+    )
+}
+
+fn topic(filter: &str, action: MqttAction, reply_topic: Option<&str>) -> MqttTopic {
+    MqttTopic {
+        topic: filter.to_string(),
+        action,
+        reply_topic: reply_topic.map(str::to_string),
+        format: AudioFormat::Wav,
+    }
+}
+
+async fn connect(topics: Vec<MqttTopic>) -> Broker {
+    let (broker, addr) = Broker::start().await;
+    let count = topics.len();
+    let config = MqttConfig {
+        broker: Some(addr.to_string()),
+        base_url: Some("http://tts.local:3000/".to_string()),
+        topics,
+        ..MqttConfig::default()
+    };
+    tokio::spawn(mqtt::run(config, Arc::new(FakeAnnouncer)));
+    broker.wait_for_subscriptions(count).await;
+    broker
+}
+
+#[tokio::test]
+async fn publishes_audio_to_the_reply_topic() {
+    let mut broker = connect(vec![topic("tts/say", MqttAction::Audio, Some("tts/audio"))]).await;
+
+    broker.publish(
+        "tts/say",
+        br#"{"text": "Hello", "voice": "en_US-test-low"}"#,
+    );
+    let (topic, payload) = broker.next_published().await;
+    assert_eq!(topic, "tts/audio");
+    assert_eq!(payload, b"wav:Hello");
+}
+
+#[tokio::test]
+async fn queues_playback_from_wildcard_topics() {
+    let mut broker = connect(vec![topic(
+        "building/+/announce",
+        MqttAction::Play,
+        Some("building/played"),
+    )])
+    .await;
+
+    broker.publish(
+        "building/lobby/announce",
+        br#"{"text": "Fire drill", "priority": "high", "chime": true}"#,
+    );
+    let (topic, payload) = broker.next_published().await;
+    assert_eq!(topic, "building/played");
+    let item: Value = serde_json::from_slice(&payload).unwrap();
+    assert_eq!(item["text"], "Fire drill");
+    assert_eq!(item["priority"], "high");
+    assert_eq!(item["chime"], true);
+}
+
+#[tokio::test]
+async fn publishes_urls() {
+    let mut broker = connect(vec![topic(
+        "tts/url",
+        MqttAction::Url,
+        Some("tts/url/reply"),
+    )])
+    .await;
+
+    broker.publish("tts/url", br#"{"text": "Hi"}"#);
+    let (topic, payload) = broker.next_published().await;
+    assert_eq!(topic, "tts/url/reply");
+    assert_eq!(
+        String::from_utf8(payload).unwrap(),
+        "http://tts.local:3000/api/speak?text=Hi&format=wav"
+    );
+}
+
+#[tokio::test]
+async fn reports_errors_to_the_error_topic() {
+    let mut broker = connect(vec![topic("tts/say", MqttAction::Audio, Some("tts/audio"))]).await;
+
+    broker.publish("tts/say", b"not json");
+    let (topic, payload) = broker.next_published().await;
+    assert_eq!(topic, "tts/audio/error");
+    let error: Value = serde_json::from_slice(&payload).unwrap();
+    assert_eq!(error["code"], "BAD_REQUEST");
+
+    broker.publish("tts/say", br#"{"text": "Hi", "voice": "nope"}"#);
+    let (topic, payload) = broker.next_published().await;
+    assert_eq!(topic, "tts/audio/error");
+    assert_eq!(
+        serde_json::from_slice::<Value>(&payload).unwrap(),
+        json!({ "error": "Voice 'nope' not found", "code": "VOICE_NOT_FOUND" })
+    );
+}
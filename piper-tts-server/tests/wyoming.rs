@@ -0,0 +1,164 @@
+//! Drives the Wyoming server over TCP the way Home Assistant does, against
+//! a stand-in synthesizer so no voice model is needed.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use piper_tts_server::error::AppError;
+use piper_tts_server::tts::{Audio, VoiceInfo};
+use piper_tts_server::wyoming::{self, Event, Synthesizer, SAMPLES_PER_CHUNK};
+use serde_json::json;
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+
+const SAMPLE_RATE: u32 = 16000;
+
+struct FakeSynthesizer;
+
+impl Synthesizer for FakeSynthesizer {
+    fn voices(&self) -> Result<Vec<VoiceInfo>, AppError> {
+        Ok(vec![VoiceInfo {
+            id: "en_US-test-low".into(),
+            name: "Test".into(),
+            language: "en-us".into(),
+            language_code: "en_US".into(),
+            language_family: Some("en".into()),
+            language_region: Some("US".into()),
+            language_name: None,
+            language_name_native: None,
+            country: None,
+            espeak_voice: None,
+            dataset: Some("test".into()),
+            quality: Some("low".into()),
+            sample_rate: SAMPLE_RATE,
+            num_speakers: 1,
+            speakers: Vec::new(),
+        }])
+    }
+
+    /// 1.5 chunks of audio per character, so chunking is exercised
+    fn synthesize(
+        &self,
+        text: &str,
+        voice: Option<&str>,
+        _peer: IpAddr,
+    ) -> Result<Audio, AppError> {
+        if voice.is_some_and(|voice| voice != "en_US-test-low") {
+            return Err(AppError::VoiceNotFound(voice.unwrap().to_string()));
+        }
+        let len = text.chars().count() * SAMPLES_PER_CHUNK * 3 / 2;
+        Ok(Audio {
+            samples: (0..len).map(|i| (i % 100) as f32 / 100.0).collect(),
+            sample_rate: SAMPLE_RATE,
+        })
+    }
+}
+
+struct Client {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+}
+
+impl Client {
+    async fn connect() -> Client {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(wyoming::serve(listener, Arc::new(FakeSynthesizer)));
+
+        let (reader, writer) = TcpStream::connect(addr).await.unwrap().into_split();
+        Client {
+            reader: BufReader::new(reader),
+            writer,
+        }
+    }
+
+    async fn send(&mut self, event: Event) {
+        event.write(&mut self.writer).await.unwrap();
+    }
+
+    async fn send_raw(&mut self, bytes: &[u8]) {
+        self.writer.write_all(bytes).await.unwrap();
+    }
+
+    async fn receive(&mut self) -> Event {
+        Event::read(&mut self.reader)
+            .await
+            .unwrap()
+            .expect("connection closed")
+    }
+}
+
+#[tokio::test]
+async fn describes_installed_voices() {
+    let mut client = Client::connect().await;
+    client.send(Event::new("describe", json!({}))).await;
+
+    let info = client.receive().await;
+    assert_eq!(info.kind, "info");
+    let tts = &info.data["tts"][0];
+    assert_eq!(tts["installed"], true);
+    let voice = &tts["voices"][0];
+    assert_eq!(voice["name"], "en_US-test-low");
+    assert_eq!(voice["languages"], json!(["en_US"]));
+    assert_eq!(voice["description"], "Test (low)");
+}
+
+#[tokio::test]
+async fn streams_synthesized_audio() {
+    let mut client = Client::connect().await;
+    client
+        .send(Event::new(
+            "synthesize",
+            json!({ "text": "Hi", "voice": { "name": "en_US-test-low" } }),
+        ))
+        .await;
+
+    let start = client.receive().await;
+    assert_eq!(start.kind, "audio-start");
+    assert_eq!(start.data["rate"], SAMPLE_RATE);
+    assert_eq!(start.data["width"], 2);
+    assert_eq!(start.data["channels"], 1);
+
+    let mut chunks = 0;
+    let mut bytes = 0;
+    let stop = loop {
+        let event = client.receive().await;
+        if event.kind != "audio-chunk" {
+            break event;
+        }
+        assert_eq!(event.data["rate"], SAMPLE_RATE);
+        assert!(event.payload.len() <= SAMPLES_PER_CHUNK * 2);
+        chunks += 1;
+        bytes += event.payload.len();
+    };
+    assert_eq!(stop.kind, "audio-stop");
+    assert_eq!(chunks, 3);
+    assert_eq!(bytes, 2 * SAMPLES_PER_CHUNK * 3 / 2 * 2);
+}
+
+#[tokio::test]
+async fn reports_errors_and_keeps_the_connection() {
+    let mut client = Client::connect().await;
+    // Older clients put data inline in the header line
+    client
+        .send_raw(
+            b"{\"type\":\"synthesize\",\"data\":{\"text\":\"Hi\",\"voice\":{\"name\":\"nope\"}}}\n",
+        )
+        .await;
+    let error = client.receive().await;
+    assert_eq!(error.kind, "error");
+    assert_eq!(error.data["code"], "VOICE_NOT_FOUND");
+
+    client
+        .send(Event::new("synthesize", json!({ "text": " " })))
+        .await;
+    assert_eq!(client.receive().await.kind, "error");
+
+    client
+        .send(Event::new("ping", json!({ "text": "x" })))
+        .await;
+    let pong = client.receive().await;
+    assert_eq!(pong.kind, "pong");
+    assert_eq!(pong.data["text"], "x");
+}